@@ -0,0 +1,275 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::config::match_reviews_path;
+use crate::exe_ranking::{normalize, similarity};
+use crate::metadata::{GameMetadata, MetadataSource};
+use crate::models::GameEntry;
+use crate::store::Store;
+
+// Matching games to metadata entries by name. Folder and exe names carry release noise
+// ("[190125][Key] Summer Pockets v1.02 [汉化]"); it is stripped before searching and
+// candidates are scored against every title and alias of the entry.
+
+/// Link without asking when the best candidate scores at least this
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+/// ... and beats the runner-up by this much; otherwise the user picks
+const MIN_MARGIN: f64 = 0.05;
+/// Candidates kept for review
+pub const REVIEW_CANDIDATES: usize = 5;
+
+// Removed wherever they appear
+const RELEASE_MARKERS: &[&str] = &[
+    "简体中文", "繁体中文", "繁體中文", "汉化版", "漢化版", "汉化", "漢化", "中文版", "简中", "繁中",
+    "硬盘版", "硬碟版", "免安装版", "免安装", "绿色版",
+];
+// Removed when they are a whole word
+const RELEASE_WORDS: &[&str] = &["chs", "cht", "cn", "jp", "repack", "portable"];
+const BRACKETS: &[(char, char)] = &[('[', ']'), ('【', '】'), ('(', ')'), ('（', '）'), ('{', '}'), ('〔', '〕')];
+
+/// Strip brackets, version tags, dates and translation markers from a folder or exe name
+pub fn clean_title(name: &str) -> String {
+    let mut s = strip_brackets(name);
+    if s.trim().is_empty() {
+        // the whole name was bracketed, e.g. "[Summer Pockets]"
+        s = name.chars().filter(|c| !BRACKETS.iter().any(|(o, e)| c == o || c == e)).collect();
+    }
+    for m in RELEASE_MARKERS {
+        s = s.replace(m, " ");
+    }
+    let words: Vec<&str> = s
+        .split(|c: char| c.is_whitespace() || c == '_')
+        .map(strip_version_suffix)
+        .map(|w| w.trim_matches(|c: char| "-.~+".contains(c)))
+        .filter(|w| !w.is_empty() && !is_version(w) && !is_date(w))
+        .filter(|w| !RELEASE_WORDS.iter().any(|r| w.eq_ignore_ascii_case(r)))
+        .collect();
+    words.join(" ")
+}
+
+fn strip_brackets(s: &str) -> String {
+    let mut out = String::new();
+    let mut closing: Vec<char> = Vec::new();
+    for c in s.chars() {
+        if let Some((_, close)) = BRACKETS.iter().find(|(open, _)| *open == c) {
+            closing.push(*close);
+        } else if closing.last() == Some(&c) {
+            closing.pop();
+            out.push(' ');
+        } else if closing.is_empty() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// "v1.02", "ver1.0", "Ver.2", "1.0.3"
+fn is_version(w: &str) -> bool {
+    let lower = w.to_lowercase();
+    let rest = ["version", "ver.", "ver", "v"]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p))
+        .unwrap_or(&lower);
+    let has_prefix = rest.len() < lower.len();
+    !rest.is_empty()
+        && rest.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && rest.chars().all(|c| c.is_ascii_digit() || c == '.' || c.is_ascii_lowercase())
+        && (has_prefix || rest.contains('.'))
+}
+
+// "Game.v1.2" / "Game-ver1.0" -> "Game"
+fn strip_version_suffix(w: &str) -> &str {
+    for (i, c) in w.char_indices().rev() {
+        if (c == '.' || c == '-') && i > 0 && is_version(&w[i + c.len_utf8()..]) {
+            return &w[..i];
+        }
+    }
+    w
+}
+
+// "20190125", "190125", "2019-01-25", "2019.01.25"
+fn is_date(w: &str) -> bool {
+    let digits: String = w.chars().filter(|c| c.is_ascii_digit()).collect();
+    let only_date_chars = w.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '.');
+    only_date_chars && match digits.len() {
+        6 => digits.len() == w.len(),
+        8 => digits.starts_with("19") || digits.starts_with("20"),
+        _ => false,
+    }
+}
+
+/// What to search for: the game's folder, then its name, cleaned and without duplicates
+pub fn queries_for(game: &GameEntry) -> Vec<String> {
+    let exe = Path::new(&game.path);
+    let folder = game
+        .folder_path
+        .as_ref()
+        .and_then(|f| f.last().cloned())
+        .or_else(|| exe.parent().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string()));
+    let stem = exe.file_stem().map(|s| s.to_string_lossy().to_string());
+    let mut queries: Vec<String> = Vec::new();
+    for name in [folder, Some(game.name.clone()), stem].into_iter().flatten() {
+        let q = clean_title(&name);
+        if !normalize(&q).is_empty() && !queries.iter().any(|e| normalize(e) == normalize(&q)) {
+            queries.push(q);
+        }
+    }
+    queries
+}
+
+/// How well an entry fits the game's names, in [0, 1]
+pub fn confidence(queries: &[String], meta: &GameMetadata) -> f64 {
+    let titles: Vec<String> = meta.all_titles().map(|t| normalize(&clean_title(t))).filter(|t| !t.is_empty()).collect();
+    queries
+        .iter()
+        .map(|q| normalize(q))
+        .filter(|q| !q.is_empty())
+        .flat_map(|q| titles.iter().map(move |t| similarity(&q, t)).collect::<Vec<_>>())
+        .fold(0.0, f64::max)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchCandidate {
+    pub metadata: GameMetadata,
+    pub confidence: f64,
+}
+
+/// Candidates sorted best first, and whether the best one is safe to link
+pub fn rank(queries: &[String], results: Vec<GameMetadata>, threshold: f64) -> (Vec<MatchCandidate>, bool) {
+    let mut candidates: Vec<MatchCandidate> = results
+        .into_iter()
+        .map(|m| MatchCandidate { confidence: confidence(queries, &m), metadata: m })
+        .collect();
+    // stable sort: ties keep the source's own ranking
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let confident = match candidates.as_slice() {
+        [] => false,
+        [best] => best.confidence >= threshold,
+        [best, second, ..] => best.confidence >= threshold && best.confidence - second.confidence >= MIN_MARGIN,
+    };
+    (candidates, confident)
+}
+
+/// A game auto-match couldn't decide on, waiting for the user to pick a candidate
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchReview {
+    pub game_id: String,
+    pub game_name: String,
+    pub source: MetadataSource,
+    pub queries: Vec<String>,
+    pub candidates: Vec<MatchCandidate>,
+    pub created: String,
+}
+
+// Rebuilt by running auto-match again, so a corrupt file doesn't block anything
+pub fn review_store() -> Store<Vec<MatchReview>> {
+    Store::new(match_reviews_path()).with_backups(1).disposable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_title_strips_release_noise() {
+        let cases = [
+            ("[190125][Key] Summer Pockets v1.02 [汉化]", "Summer Pockets"),
+            ("【汉化硬盘版】Summer Pockets", "Summer Pockets"),
+            // nested brackets are removed as a whole
+            ("[Key [Visual Arts]] Summer Pockets (2018)", "Summer Pockets"),
+            ("Summer Pockets [Key (VA)]", "Summer Pockets"),
+            // a name that is nothing but a bracket keeps its content
+            ("[Summer Pockets]", "Summer Pockets"),
+            ("Summer_Pockets.v1.02", "Summer Pockets"),
+            ("Summer Pockets-ver1.0 CHS", "Summer Pockets"),
+            ("20190125 Summer Pockets 2019-01-25", "Summer Pockets"),
+            ("ef - a fairy tale of the two.", "ef a fairy tale of the two"),
+            ("Steins;Gate 0", "Steins;Gate 0"),
+            ("Chaos;Child repack", "Chaos;Child"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean_title(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn version_tags() {
+        let cases = [
+            ("v1.02", true),
+            ("V1.02", true),
+            ("ver1.0", true),
+            ("Ver.2", true),
+            ("version2", true),
+            ("1.0.3", true),
+            ("v2a", true),
+            ("v", false),
+            ("1", false),
+            ("0", false),
+            ("vivid", false),
+            ("Vol.2", false),
+            ("version", false),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(is_version(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let cases = [
+            ("190125", true),
+            ("20190125", true),
+            ("2019-01-25", true),
+            ("2019.01.25", true),
+            ("12345", false),
+            ("1234567", false),
+            ("30190125", false),
+            ("19012a", false),
+            ("190-125", false),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(is_date(input), expected, "{:?}", input);
+        }
+    }
+
+    fn entry(id: &str, title: &str) -> GameMetadata {
+        GameMetadata { id: id.into(), title: title.into(), ..Default::default() }
+    }
+
+    #[test]
+    fn rank_needs_threshold_and_margin() {
+        let queries = vec!["Summer Pockets".to_string()];
+        // (results, threshold, expected order, confident)
+        let cases = [
+            (vec![], DEFAULT_THRESHOLD, vec![], false),
+            (vec![entry("1", "Summer Pockets")], DEFAULT_THRESHOLD, vec!["1"], true),
+            (vec![entry("1", "Clannad")], DEFAULT_THRESHOLD, vec!["1"], false),
+            (vec![entry("1", "Clannad")], 0.0, vec!["1"], true),
+            // best first whatever the source's order
+            (vec![entry("1", "Clannad"), entry("2", "Summer Pockets")], DEFAULT_THRESHOLD, vec!["2", "1"], true),
+            // two equally good entries: within the margin, so the user decides; ties keep the source's order
+            (vec![entry("1", "Summer Pockets"), entry("2", "Summer Pockets")], DEFAULT_THRESHOLD, vec!["1", "2"], false),
+            (vec![entry("2", "Summer Pockets"), entry("1", "Summer Pockets")], DEFAULT_THRESHOLD, vec!["2", "1"], false),
+        ];
+        for (results, threshold, order, confident) in cases {
+            let (candidates, ok) = rank(&queries, results, threshold);
+            let ids: Vec<&str> = candidates.iter().map(|c| c.metadata.id.as_str()).collect();
+            assert_eq!((ids, ok), (order, confident));
+        }
+    }
+
+    #[test]
+    fn rank_margin_is_measured_against_the_runner_up() {
+        let queries = vec!["Summer Pockets Reflection Blue".to_string()];
+        // one letter off in a long title: above the threshold, but too close to the best
+        let results = vec![entry("1", "Summer Pockets Reflection Blue"), entry("2", "Summer Pockets Reflection Blu")];
+        let (candidates, confident) = rank(&queries, results, DEFAULT_THRESHOLD);
+        let gap = candidates[0].confidence - candidates[1].confidence;
+        assert!(candidates[1].confidence >= DEFAULT_THRESHOLD && gap > 0.0 && gap < MIN_MARGIN, "gap {}", gap);
+        assert!(!confident);
+
+        let results = vec![entry("1", "Summer Pockets Reflection Blue"), entry("2", "Summer Pockets")];
+        let (candidates, confident) = rank(&queries, results, DEFAULT_THRESHOLD);
+        assert!(candidates[0].confidence - candidates[1].confidence >= MIN_MARGIN);
+        assert!(confident);
+    }
+}
@@ -0,0 +1,291 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::bangumi_cache::{self, CacheEntry, CachePolicy};
+use crate::error::KanoError;
+use crate::metadata::{
+    http_client, iso_date, CoverUrls, ExternalIds, GameMetadata, LocalizedTitle, MetadataFuture, MetadataProvider, MetadataSource,
+    StaffCredit,
+};
+
+// Client for the Bangumi v0 API (https://bangumi.github.io/api/). Games are subjects of
+// type 4; the access token is optional and only needed to see NSFW entries.
+// Responses go through bangumi_cache when the client has a cache policy.
+
+pub const DEFAULT_API_URL: &str = "https://api.bgm.tv";
+const SUBJECT_TYPE_GAME: i64 = 4;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Subject {
+    id: i64,
+    name: String,
+    name_cn: String,
+    summary: String,
+    date: Option<String>,
+    platform: String,
+    image: Option<String>, // 搜索结果里可能只有这一项
+    images: Option<SubjectImages>,
+    infobox: Vec<InfoboxItem>,
+    rating: Option<Rating>,
+    score: Option<f64>,
+    meta_tags: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct SubjectImages {
+    large: String,
+    common: String,
+    medium: String,
+    small: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct InfoboxItem {
+    key: String,
+    value: Value, // 字符串，或 [{ "k"?: ..., "v": ... }]
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Rating {
+    score: f64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct SearchResponse {
+    data: Vec<Subject>,
+}
+
+pub struct BangumiClient {
+    base_url: String,
+    token: Option<String>,
+    cache: Option<CachePolicy>,
+}
+
+impl BangumiClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Self {
+        let base_url = match base_url.trim().trim_end_matches('/') {
+            "" => DEFAULT_API_URL.to_string(),
+            url => url.to_string(),
+        };
+        BangumiClient { base_url, token: token.filter(|t| !t.trim().is_empty()), cache: None }
+    }
+
+    pub fn with_cache(mut self, policy: Option<CachePolicy>) -> Self {
+        self.cache = policy;
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let req = http_client()
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Accept", "application/json");
+        match &self.token {
+            Some(tok) => req.bearer_auth(tok),
+            None => req,
+        }
+    }
+
+    // The response body and its ETag; None for 304 Not Modified. A 404 is reported as
+    // `what` not found.
+    async fn send(req: reqwest::RequestBuilder, what: &'static str) -> Result<Option<(Value, Option<String>)>, KanoError> {
+        let resp = req.send().await?;
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        // read the body as text so non-2xx responses give a useful error
+        let body = resp.text().await?;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(KanoError::not_found(what));
+        }
+        if !status.is_success() {
+            return Err(KanoError::Http { status: status.as_u16(), message: body });
+        }
+        Ok(Some((serde_json::from_str(&body)?, etag)))
+    }
+
+    // Answer from the cache while the entry is fresh; otherwise ask the server, sending
+    // the entry's ETag so an unchanged response costs nothing
+    async fn fetch<T: serde::de::DeserializeOwned>(
+        &self,
+        key: String,
+        what: &'static str,
+        req: reqwest::RequestBuilder,
+    ) -> Result<T, KanoError> {
+        let Some(policy) = self.cache else {
+            let (body, _) = Self::send(req, what).await?.ok_or(KanoError::Http { status: 304, message: String::new() })?;
+            return Ok(serde_json::from_value(body)?);
+        };
+        let cached = bangumi_cache::get(&key);
+        if let Some(entry) = cached.as_ref().filter(|e| !policy.refresh && e.is_fresh(policy.ttl)) {
+            return Ok(serde_json::from_value(entry.body.clone())?);
+        }
+        let req = match cached.as_ref().and_then(|e| e.etag.as_deref()) {
+            Some(etag) => req.header(reqwest::header::IF_NONE_MATCH, etag),
+            None => req,
+        };
+        let (body, etag) = match (Self::send(req, what).await?, cached) {
+            (Some(fresh), _) => fresh,
+            (None, Some(entry)) => (entry.body, entry.etag),
+            (None, None) => return Err(KanoError::Http { status: 304, message: String::new() }),
+        };
+        let value = serde_json::from_value(body.clone())?;
+        bangumi_cache::put(key, CacheEntry::new(body, etag), &policy);
+        Ok(value)
+    }
+
+    pub async fn search_games(&self, query: &str, limit: u32) -> Result<Vec<GameMetadata>, KanoError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = json!({
+            "keyword": query,
+            "filter": { "type": [SUBJECT_TYPE_GAME], "nsfw": true },
+        });
+        let limit = limit.clamp(1, 50);
+        let path = format!("/v0/search/subjects?limit={}", limit);
+        let req = self.request(reqwest::Method::POST, &path).json(&body);
+        let resp: SearchResponse = self.fetch(bangumi_cache::search_key(query, limit), "bangumi_search", req).await?;
+        Ok(resp.data.into_iter().map(to_metadata).collect())
+    }
+
+    pub async fn subject(&self, id: i64) -> Result<GameMetadata, KanoError> {
+        let req = self.request(reqwest::Method::GET, &format!("/v0/subjects/{}", id));
+        let subject: Subject = self.fetch(bangumi_cache::subject_key(id), "bangumi_subject", req).await?;
+        Ok(to_metadata(subject))
+    }
+}
+
+impl MetadataProvider for BangumiClient {
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> MetadataFuture<'a, Vec<GameMetadata>> {
+        Box::pin(self.search_games(query, limit))
+    }
+
+    fn fetch_details<'a>(&'a self, id: &'a str) -> MetadataFuture<'a, GameMetadata> {
+        Box::pin(async move {
+            let id: i64 = id
+                .trim()
+                .parse()
+                .map_err(|_| KanoError::invalid("bangumi_id", format!("invalid Bangumi id: {}", id)))?;
+            self.subject(id).await
+        })
+    }
+}
+
+// Infobox values are a string or a list of { k, v }
+fn infobox_values(subject: &Subject, key: &str) -> Vec<String> {
+    let Some(item) = subject.infobox.iter().find(|i| i.key == key) else {
+        return Vec::new();
+    };
+    let values = match &item.value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(list) => list
+            .iter()
+            .filter_map(|v| v.get("v").unwrap_or(v).as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    };
+    values.into_iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+// Names like "Key、Visual Arts" hold several companies
+fn split_names(values: Vec<String>) -> Vec<String> {
+    values
+        .iter()
+        .flat_map(|v| v.split(['、', '/', '，']))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+// Infobox keys that credit people, in display order
+const STAFF_ROLES: &[&str] = &["原作", "导演", "剧本", "脚本", "原画", "人物设定", "美术", "音乐", "主题歌演出", "主题歌作曲", "主题歌作词", "程序"];
+
+fn staff(s: &Subject) -> Vec<StaffCredit> {
+    STAFF_ROLES
+        .iter()
+        .flat_map(|role| {
+            split_names(infobox_values(s, role))
+                .into_iter()
+                .map(|name| StaffCredit { role: role.to_string(), name })
+        })
+        .collect()
+}
+
+/// A subject as the API returns it (e.g. kept by older versions) as GameMetadata
+pub fn metadata_from_json(subject: Value) -> Option<GameMetadata> {
+    let s: Subject = serde_json::from_value(subject).ok()?;
+    if s.id <= 0 {
+        return None;
+    }
+    let mut meta = to_metadata(s);
+    // older versions replaced the cover URL with where they saved it (kano_data/images/{id}.jpg);
+    // that file is already the one kept under the subject id, only web addresses are covers
+    meta.cover = meta.cover.and_then(|c| {
+        let mut urls = [Some(c.large), c.medium, c.small].into_iter().flatten().filter(|u| is_web_url(u));
+        Some(CoverUrls { large: urls.next()?, medium: urls.next(), small: urls.next() })
+    });
+    Some(meta)
+}
+
+fn is_web_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+fn to_metadata(s: Subject) -> GameMetadata {
+    let mut titles = Vec::new();
+    if let Some(cn) = non_empty(&s.name_cn).or_else(|| infobox_values(&s, "中文名").into_iter().next()) {
+        titles.push(LocalizedTitle { lang: "zh-Hans".into(), title: cn });
+    }
+    let cover = match &s.images {
+        Some(i) if !i.large.is_empty() => Some(CoverUrls {
+            large: i.large.clone(),
+            medium: non_empty(&i.medium).or_else(|| non_empty(&i.common)),
+            small: non_empty(&i.small),
+        }),
+        _ => s.image.as_deref().and_then(non_empty).map(|large| CoverUrls { large, ..Default::default() }),
+    };
+    let release_date = s
+        .date
+        .as_deref()
+        .and_then(iso_date)
+        .or_else(|| infobox_values(&s, "发行日期").iter().find_map(|d| iso_date(d)));
+    let platforms = match infobox_values(&s, "平台") {
+        p if p.is_empty() => non_empty(&s.platform).into_iter().collect(),
+        p => p,
+    };
+    GameMetadata {
+        source: MetadataSource::Bangumi,
+        id: s.id.to_string(),
+        url: format!("https://bgm.tv/subject/{}", s.id),
+        title: non_empty(&s.name).unwrap_or_else(|| s.name_cn.clone()),
+        titles,
+        aliases: infobox_values(&s, "别名"),
+        developers: split_names(infobox_values(&s, "开发")),
+        publishers: split_names(infobox_values(&s, "发行")),
+        release_date,
+        platforms,
+        genre: infobox_values(&s, "游戏类型").into_iter().next(),
+        summary: non_empty(&s.summary),
+        staff: staff(&s),
+        cover,
+        tags: s.meta_tags.clone(),
+        rating: s.rating.as_ref().map(|r| r.score).or(s.score).filter(|r| *r > 0.0),
+        external_ids: ExternalIds { bangumi: Some(s.id), vndb: None },
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::config::cache_path;
+use crate::error::KanoError;
+use crate::models::MetadataSettings;
+use crate::store::Store;
+
+// Bangumi API responses kept in kano_data/bangumi_cache.json, keyed by request:
+// "subject:{id}" and "search:{limit}:{query}". The file is read once and kept in memory;
+// new responses are written out shortly after they arrive, in batches, and clears and
+// imports right away. Stale entries are revalidated with their ETag.
+
+/// One cached response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub body: Value,
+    pub fetched_at: i64, // unix 秒
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(skip)]
+    size: u64, // 序列化后的字节数，用到时才计算
+}
+
+impl CacheEntry {
+    pub fn new(body: Value, etag: Option<String>) -> Self {
+        CacheEntry { body, fetched_at: chrono::Utc::now().timestamp(), etag, size: 0 }
+    }
+
+    // About what the entry takes in the file (compact JSON plus its key)
+    fn size(&mut self, key: &str) -> u64 {
+        if self.size == 0 {
+            self.size = (key.len() + serde_json::to_vec(self).map_or(0, |v| v.len())) as u64;
+        }
+        self.size
+    }
+
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = chrono::Utc::now().timestamp() - self.fetched_at;
+        age >= 0 && (age as u64) < ttl.as_secs()
+    }
+}
+
+// Older versions stored a frontend-managed map here; it has no `entries` and is
+// simply replaced
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BangumiCache {
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+impl BangumiCache {
+    /// Drop the oldest entries until at most `max_entries` remain and they take at most
+    /// `max_bytes`; 0 turns a limit off
+    pub fn evict(&mut self, max_entries: usize, max_bytes: u64) {
+        if max_entries == 0 && max_bytes == 0 {
+            return;
+        }
+        let mut by_age: Vec<(i64, String, u64)> = self
+            .entries
+            .iter_mut()
+            .map(|(k, e)| (e.fetched_at, k.clone(), if max_bytes > 0 { e.size(k) } else { 0 }))
+            .collect();
+        by_age.sort();
+        let mut total: u64 = by_age.iter().map(|(_, _, size)| size).sum();
+        for (_, k, size) in by_age {
+            let over = (max_entries > 0 && self.entries.len() > max_entries) || (max_bytes > 0 && total > max_bytes);
+            if !over {
+                break;
+            }
+            self.entries.remove(&k);
+            total -= size;
+        }
+    }
+}
+
+/// How a client uses the cache
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    pub ttl: Duration,
+    pub max_entries: usize,
+    pub max_bytes: u64,
+    pub refresh: bool, // 忽略有效期，总是向服务器确认
+}
+
+impl CachePolicy {
+    /// None when caching is turned off (TTL 0)
+    pub fn from_settings(settings: &MetadataSettings, refresh: bool) -> Option<Self> {
+        (settings.cache_ttl_hours > 0).then(|| CachePolicy {
+            ttl: Duration::from_secs(settings.cache_ttl_hours * 3600),
+            max_entries: settings.cache_max_entries,
+            max_bytes: settings.cache_max_bytes,
+            refresh,
+        })
+    }
+}
+
+/// What `clear` removes
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CacheScope {
+    All,
+    Subjects,
+    Searches,
+    Subject { ids: Vec<i64> },
+    Expired, // 超过有效期的条目
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CacheStats {
+    pub subjects: usize,
+    pub searches: usize,
+    pub size_bytes: u64, // 缓存文件大小
+    pub oldest: Option<i64>,
+}
+
+pub fn subject_key(id: i64) -> String {
+    format!("subject:{}", id)
+}
+
+pub fn search_key(query: &str, limit: u32) -> String {
+    format!("search:{}:{}", limit, query.trim().to_lowercase())
+}
+
+// The cache can always be rebuilt, so a corrupt one doesn't block writes
+pub fn store() -> Store<BangumiCache> {
+    Store::new(cache_path()).with_backups(1).disposable()
+}
+
+// The file's contents as last read, plus what hasn't been written yet
+struct Loaded {
+    path: PathBuf, // 读取时的位置；数据目录移动后重新读取
+    cache: BangumiCache,
+    dirty: bool,
+}
+
+static CACHE: Mutex<Option<Loaded>> = Mutex::new(None);
+// held while writing, so snapshots reach the file in the order they were taken
+static SAVING: Mutex<()> = Mutex::new(());
+static FLUSH_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// How long new responses wait before being written, so a batch of lookups (e.g. an
+/// auto-match run) costs one write
+const SAVE_DELAY: Duration = Duration::from_secs(3);
+
+fn with_cache<R>(f: impl FnOnce(&mut Loaded) -> R) -> R {
+    let mut guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let path = cache_path();
+    // after a move, unsaved responses for the old location are simply dropped
+    if guard.as_ref().is_none_or(|l| l.path != path) {
+        *guard = Some(Loaded { path, cache: store().load().unwrap_or_default(), dirty: false });
+    }
+    f(guard.as_mut().expect("cache loaded above"))
+}
+
+/// Write pending changes now. The file is written from a copy, so lookups aren't held
+/// up by the disk.
+pub fn flush() -> Result<(), KanoError> {
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    let pending = {
+        let mut guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        match guard.as_mut() {
+            Some(l) if l.dirty && l.path == cache_path() => {
+                l.dirty = false;
+                l.cache.clone()
+            }
+            _ => return Ok(()),
+        }
+    };
+    store().save(&pending).inspect_err(|_| {
+        // try again with the next change
+        if let Some(l) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            l.dirty = true;
+        }
+    })
+}
+
+// One background write per SAVE_DELAY at most; changes made meanwhile go with it
+fn schedule_flush() {
+    if FLUSH_SCHEDULED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(|| {
+        std::thread::sleep(SAVE_DELAY);
+        FLUSH_SCHEDULED.store(false, Ordering::SeqCst);
+        // a failed write only costs the cache, so it's logged rather than reported
+        if let Err(e) = flush() {
+            eprintln!("failed to save bangumi cache: {}", e);
+        }
+    });
+}
+
+/// Forget the in-memory copy, e.g. after the file was restored from a backup
+pub fn reload() {
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn get(key: &str) -> Option<CacheEntry> {
+    with_cache(|l| l.cache.entries.get(key).cloned())
+}
+
+/// Store a response and drop the oldest entries beyond the policy's limits. Written
+/// out a few seconds later (see `flush`).
+pub fn put(key: String, entry: CacheEntry, policy: &CachePolicy) {
+    with_cache(|l| {
+        l.cache.entries.insert(key, entry);
+        l.cache.evict(policy.max_entries, policy.max_bytes);
+        l.dirty = true;
+    });
+    schedule_flush();
+}
+
+/// The whole cache, for library exports
+pub fn snapshot() -> BangumiCache {
+    with_cache(|l| l.cache.clone())
+}
+
+/// Library imports: replace the cache, or add the entries missing locally
+pub fn import(imported: BangumiCache, replace: bool) -> Result<(), KanoError> {
+    with_cache(|l| {
+        if replace {
+            l.cache = imported;
+        } else {
+            for (k, e) in imported.entries {
+                l.cache.entries.entry(k).or_insert(e);
+            }
+        }
+        l.dirty = true;
+    });
+    flush()
+}
+
+/// Remove the entries in `scope`; returns how many were removed
+pub fn clear(scope: &CacheScope, ttl: Duration) -> Result<usize, KanoError> {
+    let removed = with_cache(|l| {
+        let entries = &mut l.cache.entries;
+        let before = entries.len();
+        match scope {
+            CacheScope::All => entries.clear(),
+            CacheScope::Subjects => entries.retain(|k, _| !k.starts_with("subject:")),
+            CacheScope::Searches => entries.retain(|k, _| !k.starts_with("search:")),
+            CacheScope::Subject { ids } => {
+                for id in ids {
+                    entries.remove(&subject_key(*id));
+                }
+            }
+            CacheScope::Expired => entries.retain(|_, e| e.is_fresh(ttl)),
+        }
+        let removed = before - entries.len();
+        l.dirty |= removed > 0;
+        removed
+    });
+    flush()?;
+    Ok(removed)
+}
+
+pub fn stats() -> CacheStats {
+    let mut stats = with_cache(|l| CacheStats {
+        subjects: l.cache.entries.keys().filter(|k| k.starts_with("subject:")).count(),
+        searches: l.cache.entries.keys().filter(|k| k.starts_with("search:")).count(),
+        size_bytes: 0,
+        oldest: l.cache.entries.values().map(|e| e.fetched_at).min(),
+    });
+    stats.size_bytes = std::fs::metadata(cache_path()).map(|m| m.len()).unwrap_or(0);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(entries: &[(&str, i64, usize)]) -> BangumiCache {
+        let mut c = BangumiCache::default();
+        for (key, fetched_at, body_len) in entries {
+            let mut e = CacheEntry::new(Value::String("x".repeat(*body_len)), None);
+            e.fetched_at = *fetched_at;
+            c.entries.insert(key.to_string(), e);
+        }
+        c
+    }
+
+    fn keys(c: &BangumiCache) -> Vec<&str> {
+        c.entries.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn evicts_oldest_beyond_limits() {
+        // same-sized entries; oldest first: 1, 3, 2
+        let entries = [("subject:1", 10, 1000), ("subject:2", 30, 1000), ("subject:3", 20, 1000)];
+        let one = cache(&entries[..1]).entries.get_mut("subject:1").unwrap().size("subject:1");
+        // (max_entries, max_bytes, kept)
+        let cases = [
+            (0, 0, vec!["subject:1", "subject:2", "subject:3"]),
+            (3, 0, vec!["subject:1", "subject:2", "subject:3"]),
+            (2, 0, vec!["subject:2", "subject:3"]),
+            (1, 0, vec!["subject:2"]),
+            (0, 3 * one, vec!["subject:1", "subject:2", "subject:3"]),
+            (0, 3 * one - 1, vec!["subject:2", "subject:3"]),
+            (0, one, vec!["subject:2"]),
+            // both limits: whichever removes more
+            (2, one, vec!["subject:2"]),
+            (1, 3 * one, vec!["subject:2"]),
+        ];
+        for (max_entries, max_bytes, kept) in cases {
+            let mut c = cache(&entries);
+            c.evict(max_entries, max_bytes);
+            assert_eq!(keys(&c), kept, "max_entries {} max_bytes {}", max_entries, max_bytes);
+        }
+    }
+
+    #[test]
+    fn size_is_the_serialized_entry() {
+        let mut c = cache(&[("subject:1", 10, 5000), ("subject:2", 10, 10)]);
+        let big = c.entries.get_mut("subject:1").unwrap().size("subject:1");
+        let small = c.entries.get_mut("subject:2").unwrap().size("subject:2");
+        assert!(big > 5000 && big < 5100, "{}", big);
+        assert!(small < 100 && big - small == 4990);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+use tauri::Emitter;
+use crate::error::KanoError;
+use crate::auto_match::{queries_for, rank, review_store, MatchReview, DEFAULT_THRESHOLD, REVIEW_CANDIDATES};
+use crate::config::{load_config, load_games_db, save_games_db};
+use crate::data_dir;
+use crate::metadata::{provider_for, GameMetadata, MetadataProvider, MetadataSource};
+use crate::models::{GameEntry, GameKey};
+
+// Results fetched per query; the source's ranking is only a hint, we score them ourselves
+const SEARCH_LIMIT: u32 = 10;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Matched,   // 已自动关联
+    Review,    // 不确定，加入待确认列表
+    NoResults, // 没有搜索结果
+    Failed,
+}
+
+/// Sent as `auto-match-progress` after each game
+#[derive(Serialize, Debug, Clone)]
+pub struct AutoMatchProgress {
+    pub current: usize,
+    pub total: usize,
+    pub game_id: String,
+    pub game_name: String,
+    pub status: MatchStatus,
+    pub confidence: Option<f64>,    // 最佳候选的匹配度
+    pub metadata_id: Option<String>, // 已关联的条目
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AutoMatchSummary {
+    pub matched: usize,
+    pub review: usize,
+    pub no_results: usize,
+    pub failed: usize,
+}
+
+fn is_linked(g: &GameEntry, source: MetadataSource) -> bool {
+    match source {
+        MetadataSource::Bangumi => g.subject_id.is_some(),
+        MetadataSource::Vndb => g.vndb_id.is_some(),
+    }
+}
+
+// Link games to metadata entries by their folder and exe names. Confident matches are
+// linked right away, the rest go to the review list with their best candidates.
+// Without `game_ids`, every game not yet linked to `source` is tried. Games already
+// linked are skipped either way unless `rematch` is set.
+#[tauri::command]
+pub async fn auto_match_games(
+    app: tauri::AppHandle,
+    game_ids: Option<Vec<String>>,
+    source: Option<MetadataSource>,
+    threshold: Option<f64>,
+    rematch: Option<bool>,
+) -> Result<AutoMatchSummary, KanoError> {
+    // links are written throughout the run; keep the data directory where it is meanwhile
+    let _writing = data_dir::begin_write()?;
+    let source = source.unwrap_or_default();
+    let rematch = rematch.unwrap_or(false);
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD).clamp(0.0, 1.0);
+    let provider = provider_for(source, &load_config()?.metadata, false);
+    let games: Vec<GameEntry> = load_games_db()?
+        .into_inner()
+        .games
+        .into_iter()
+        .filter(|g| game_ids.as_ref().is_none_or(|ids| ids.contains(&g.id)))
+        .filter(|g| (rematch && game_ids.is_some()) || !is_linked(g, source))
+        .collect();
+
+    let total = games.len();
+    let mut summary = AutoMatchSummary::default();
+    let mut reviews = Vec::new();
+    for (i, game) in games.iter().enumerate() {
+        let mut progress = AutoMatchProgress {
+            current: i + 1,
+            total,
+            game_id: game.id.clone(),
+            game_name: game.name.clone(),
+            status: MatchStatus::Failed,
+            confidence: None,
+            metadata_id: None,
+            error: None,
+        };
+        match find_candidates(provider.as_ref(), game, source, threshold).await {
+            Ok(None) => {
+                progress.status = MatchStatus::NoResults;
+                summary.no_results += 1;
+            }
+            Ok(Some((review, true))) => {
+                let best = &review.candidates[0];
+                progress.confidence = Some(best.confidence);
+                match link_game(provider.as_ref(), &game.id, &best.metadata).await {
+                    Ok(_) => {
+                        progress.status = MatchStatus::Matched;
+                        progress.metadata_id = Some(best.metadata.id.clone());
+                        summary.matched += 1;
+                    }
+                    Err(e) => {
+                        progress.error = Some(e.to_string());
+                        summary.failed += 1;
+                    }
+                }
+            }
+            Ok(Some((review, false))) => {
+                progress.status = MatchStatus::Review;
+                progress.confidence = Some(review.candidates[0].confidence);
+                summary.review += 1;
+                reviews.push(review);
+            }
+            Err(e) => {
+                progress.error = Some(e.to_string());
+                summary.failed += 1;
+            }
+        }
+        let _ = app.emit("auto-match-progress", &progress);
+    }
+
+    // the games just tried get fresh reviews (or none); others keep theirs
+    let mut queue = review_store().load().unwrap_or_default();
+    queue.retain(|r| !games.iter().any(|g| g.id == r.game_id));
+    queue.extend(reviews);
+    review_store().save(&queue)?;
+    Ok(summary)
+}
+
+// Search with the game's names until something comes back. Returns the ranked candidates
+// and whether the best one can be linked without asking.
+async fn find_candidates(
+    provider: &dyn MetadataProvider,
+    game: &GameEntry,
+    source: MetadataSource,
+    threshold: f64,
+) -> Result<Option<(MatchReview, bool)>, KanoError> {
+    let queries = queries_for(game);
+    let mut results = Vec::new();
+    for q in &queries {
+        results = provider.search(q, SEARCH_LIMIT).await?;
+        if !results.is_empty() {
+            break;
+        }
+    }
+    if results.is_empty() {
+        return Ok(None);
+    }
+    let (mut candidates, confident) = rank(&queries, results, threshold);
+    candidates.truncate(REVIEW_CANDIDATES);
+    let review = MatchReview {
+        game_id: game.id.clone(),
+        game_name: game.name.clone(),
+        source,
+        queries,
+        candidates,
+        created: chrono::Utc::now().to_rfc3339(),
+    };
+    Ok(Some((review, confident)))
+}
+
+// Store the entry on the game: its full record, title and cover
+async fn link_game(provider: &dyn MetadataProvider, game_id: &str, candidate: &GameMetadata) -> Result<GameEntry, KanoError> {
+    // search results can lack the infobox and staff; fall back to them if details fail
+    let meta = match provider.fetch_details(&candidate.id).await {
+        Ok(full) => full,
+        Err(e) => {
+            eprintln!("details fetch failed for {}: {}", candidate.id, e);
+            candidate.clone()
+        }
+    };
+    // download first so the library isn't held across the request; without a cover the
+    // card falls back to image_url
+    let image = match provider.fetch_cover(&meta).await {
+        Ok(path) => Some(path),
+        Err(e) => {
+            eprintln!("cover download failed for {}: {}", meta.id, e);
+            None
+        }
+    };
+    let mut db = load_games_db()?;
+    let g = db.find_mut(&GameKey::Id(game_id)).ok_or(KanoError::not_found("game"))?;
+    if !meta.display_title().is_empty() {
+        g.name = meta.display_title().to_string();
+    }
+    if let Some(cover) = &meta.cover {
+        g.image_url = Some(cover.large.clone());
+    }
+    if image.is_some() {
+        g.image = image;
+    }
+    meta.attach_to(g);
+    let updated = g.clone();
+    save_games_db(&db)?;
+    Ok(updated)
+}
+
+// Games waiting for the user to confirm a match
+#[tauri::command]
+pub fn list_match_reviews() -> Result<Vec<MatchReview>, KanoError> {
+    let db = load_games_db()?;
+    let mut queue = review_store().load().unwrap_or_default();
+    queue.retain(|r| db.find(&GameKey::Id(&r.game_id)).is_some());
+    Ok(queue)
+}
+
+// Settle a review: link the chosen candidate, or with None just drop it
+#[tauri::command]
+pub async fn resolve_match_review(game_id: String, metadata: Option<GameMetadata>) -> Result<Option<GameEntry>, KanoError> {
+    let updated = match &metadata {
+        Some(meta) => {
+            let provider = provider_for(meta.source, &load_config()?.metadata, false);
+            Some(link_game(provider.as_ref(), &game_id, meta).await?)
+        }
+        None => None,
+    };
+    let mut queue = review_store().load().unwrap_or_default();
+    queue.retain(|r| r.game_id != game_id);
+    review_store().save(&queue)?;
+    Ok(updated)
+}
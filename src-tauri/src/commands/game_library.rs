@@ -1,10 +1,23 @@
 use std::fs;
 use std::path::PathBuf;
+use serde::Serialize;
 use tauri::{Emitter, Manager, State};
-use crate::models::GameEntry;
-use crate::config::{load_games_db, save_games_db};
+use uuid::Uuid;
+use crate::error::KanoError;
+use crate::commands::sessions::record_session;
+use crate::models::{ExeCandidate, GameEntry, GameHook, GameKey, LaunchProfile, PlaySession};
+use crate::config::{load_config, load_games_db, load_tools_file, save_games_db};
+use crate::data_dir;
+use crate::exe_ranking::ExeRanker;
+use crate::game_query::GameQuery;
+use crate::hooks::{self, HookFailure};
+use crate::idle::IdleTracker;
+use crate::launch;
+use crate::process_tree::{self, ProcessTracker};
+use crate::saves;
 use crate::state::RunningProcesses;
-use crate::utils::find_exe_with_folder_path;
+use crate::utils::list_exe_candidates;
+use crate::vndb;
 
 // A simple greeting kept for compatibility
 #[tauri::command]
@@ -12,53 +25,221 @@ pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-// Launch an executable by path and monitor the process
+// Launch an executable by path and monitor the process. `profile_id` selects one of the
+// game's launch profiles; without it the game's default profile (if any) is used.
 #[tauri::command]
-pub async fn launch_exe(path: String, app: tauri::AppHandle, state: State<'_, RunningProcesses>) -> Result<(), String> {
+pub async fn launch_exe(path: String, profile_id: Option<String>, app: tauri::AppHandle, state: State<'_, RunningProcesses>) -> Result<(), KanoError> {
+    let game = load_games_db()?.find(&GameKey::Path(&path)).cloned();
+    run_pre_launch_hooks(game.as_ref(), &path, &app).await?;
+    spawn_and_monitor(path, game, profile_id, app, &state)
+}
+
+// Launch a library game by its id and monitor the process
+#[tauri::command]
+pub async fn launch_game_by_id(id: String, profile_id: Option<String>, app: tauri::AppHandle, state: State<'_, RunningProcesses>) -> Result<(), KanoError> {
+    let game = load_games_db()?.find(&GameKey::Id(&id)).cloned().ok_or(KanoError::not_found("game"))?;
+    run_pre_launch_hooks(Some(&game), &game.path, &app).await?;
+    spawn_and_monitor(game.path.clone(), Some(game), profile_id, app, &state)
+}
+
+// Pre-launch hooks may wait on slow commands (mounting an image), so they run off the
+// async runtime. They go before the exe check since a mount can make the exe appear.
+async fn run_pre_launch_hooks(game: Option<&GameEntry>, path: &str, app: &tauri::AppHandle) -> Result<(), KanoError> {
+    let game = match game {
+        Some(g) if !g.pre_launch_hooks.is_empty() => g.clone(),
+        _ => return Ok(()),
+    };
+    let app = app.clone();
+    let exe = PathBuf::from(path);
+    tauri::async_runtime::spawn_blocking(move || {
+        hooks::run_hooks(&game.pre_launch_hooks, "pre_launch", &game.id, &exe, &load_tools_file()?, &|f| emit_hook_failure(&app, f))
+    })
+    .await
+    .map_err(|e| KanoError::Other { message: format!("background task failed: {e}") })??;
+    Ok(())
+}
+
+fn emit_hook_failure(app: &tauri::AppHandle, failure: HookFailure) {
+    eprintln!("{} hook failed for {}: {}", failure.stage, failure.game_id, failure.error);
+    let _ = app.emit("hook-failed", failure);
+}
+
+// Running processes are tracked by game id so moving the exe doesn't lose them;
+// executables that aren't in the library fall back to their path.
+fn process_key(path: &str) -> String {
+    load_games_db()
+        .ok()
+        .and_then(|db| db.find(&GameKey::Path(path)).map(|g| g.id.clone()))
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Payload of the `game-exited` event
+#[derive(Serialize, Clone)]
+struct GameExited {
+    path: String,
+    game_id: Option<String>,
+    duration: i64,         // 本次游玩时长（秒）
+    playtime: Option<i64>, // 更新后的总时长，非库内游戏为空
+    last_played: String,
+    killed: bool,
+}
+
+const MONITOR_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+fn spawn_and_monitor(path: String, game: Option<GameEntry>, profile_id: Option<String>, app: tauri::AppHandle, state: &RunningProcesses) -> Result<(), KanoError> {
     let p = PathBuf::from(&path);
     if !p.exists() || !p.is_file() {
-        return Err("executable not found".into());
+        return Err(KanoError::not_found("executable"));
     }
     
-    // Spawn the process. Set the working directory to the exe's parent directory
-    // so programs that use relative paths for resources can find them.
-    let mut cmd = std::process::Command::new(&p);
-    if let Some(parent) = p.parent() {
-        cmd.current_dir(parent);
+    // Spawn the process with the selected launch profile and runner applied
+    let cfg = load_config()?;
+    let profile = launch::resolve_profile(game.as_ref(), profile_id.as_deref())?;
+    let runner = launch::resolve_runner(game.as_ref(), &cfg.runners)?;
+    let wrapped = runner.is_some() || profile.and_then(|pr| pr.run_as.as_deref()).is_some_and(|r| !r.trim().is_empty());
+    let mut cmd = launch::build_command(&p, profile, runner)?;
+    // Own process group on Unix so descendants can be found even after being reparented
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
     }
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| e.to_string())?;
+    let mut child = cmd.spawn()?;
+    let started_at = chrono::Utc::now();
+    let started = std::time::Instant::now();
+
+    // Companion tools (text hookers etc.) start alongside the game
+    let companions = match &game {
+        Some(g) if !g.companion_tools.is_empty() => {
+            hooks::launch_companions(&g.companion_tools, &g.id, &load_tools_file()?, &|f| emit_hook_failure(&app, f))
+        }
+        _ => Vec::new(),
+    };
     
     // Get process ID
     let pid = child.id();
     
     // Store process ID
+    let game_id = game.as_ref().map(|g| g.id.clone());
+    // Behind a wrapper the spawned process is LEProc/Wine, not the game, so follow
+    // the game exe by name unless the user chose a process to watch.
+    let watch_process = game
+        .as_ref()
+        .and_then(|g| g.watch_process.clone())
+        .or_else(|| if wrapped { p.file_name().map(|n| n.to_string_lossy().to_string()) } else { None });
+    let key = game_id.clone().unwrap_or_else(|| path.clone());
     {
         let mut processes = state.processes.lock().unwrap();
-        processes.insert(path.clone(), pid);
+        processes.insert(key.clone(), vec![pid]);
     }
     
-    // Clone app handle for the async task
+    // Clone app handle for the monitor thread
     let app_clone = app.clone();
     let path_clone = path.clone();
     
-    // Monitor the process in a separate thread
-    tauri::async_runtime::spawn(async move {
-        // Wait for the process to exit
-        match child.wait() {
-            Ok(_) => {
-                // Remove from tracking
-                {
-                    let state = app_clone.state::<RunningProcesses>();
-                    let mut processes = state.processes.lock().unwrap();
-                    processes.remove(&path_clone);
+    // Monitor the process tree in a separate thread. Launchers often start the real game
+    // and exit right away, so the session lasts until every descendant (or the watched
+    // process, if configured) is gone, not just the spawned exe.
+    let idle_policy = cfg.idle_policy;
+    let save_backup = cfg.save_backup;
+    std::thread::spawn(move || {
+        let mut tracker = ProcessTracker::new(pid, watch_process);
+        let mut idle = IdleTracker::new(idle_policy, started_at);
+        let mut exit_code = None;
+        let mut root_exited = false;
+        loop {
+            if !root_exited {
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        exit_code = status.code();
+                        root_exited = true;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("Error waiting for process: {}", e);
+                        root_exited = true;
+                    }
                 }
-                // Process has exited, emit event to frontend
-                let _ = app_clone.emit("game-exited", path_clone);
             }
-            Err(e) => {
-                eprintln!("Error waiting for process: {}", e);
+            let tracked = tracker.poll(&process_tree::snapshot());
+            if root_exited && !tracked.alive {
+                break;
+            }
+            idle.tick(&tracked.pids);
+            {
+                let state = app_clone.state::<RunningProcesses>();
+                let mut processes = state.processes.lock().unwrap();
+                processes.insert(key.clone(), tracked.pids);
+            }
+            std::thread::sleep(MONITOR_POLL_INTERVAL);
+        }
+
+        // Remove from tracking
+        let killed = {
+            let state = app_clone.state::<RunningProcesses>();
+            let mut processes = state.processes.lock().unwrap();
+            processes.remove(&key);
+            let mut killed = state.killed.lock().unwrap();
+            killed.remove(&key)
+        };
+        // Record the session and update the library's playtime; the backend
+        // owns playtime so nothing is lost if the window reloads meanwhile.
+        // Idle time is kept on the session but not counted as playtime.
+        let now = chrono::Utc::now();
+        let end = now.to_rfc3339();
+        let grace_wait = tracker.grace_wait(std::time::Instant::now());
+        // idle time while waiting for a relaunch is already part of grace_wait
+        let (idle_spans, idle_seconds) = idle.finish(now - chrono::Duration::from_std(grace_wait).unwrap_or_default());
+        let duration = (started.elapsed().as_secs() as i64 - idle_seconds - grace_wait.as_secs() as i64).max(0);
+        let mut playtime = None;
+        if let Some(gid) = &game_id {
+            let session = PlaySession {
+                id: Uuid::new_v4().to_string(),
+                game_id: gid.clone(),
+                start: started_at.to_rfc3339(),
+                end: end.clone(),
+                duration,
+                exit_code,
+                killed,
+                idle_seconds,
+                idle_spans,
+            };
+            if let Err(e) = record_session(session) {
+                eprintln!("failed to record session: {}", e);
+            }
+            match add_game_playtime(GameKey::Id(gid), duration, &end) {
+                Ok(total) => playtime = Some(total),
+                Err(e) => eprintln!("failed to update playtime: {}", e),
+            }
+        }
+        // Process has exited, emit event to frontend
+        let _ = app_clone.emit("game-exited", GameExited {
+            path: path_clone,
+            game_id,
+            duration,
+            playtime,
+            last_played: end,
+            killed,
+        });
+
+        if let Some(g) = &game {
+            // Back up saves before post-exit hooks run (they may unmount the game)
+            if save_backup.auto_on_exit && !g.save_dirs.is_empty() {
+                // like the manual snapshot, it mustn't write while the data directory moves
+                let res = data_dir::begin_write().and_then(|_writing| saves::create_snapshot(g, "exit", save_backup.keep_last));
+                if let Err(e) = res {
+                    eprintln!("save backup failed for {}: {}", g.id, e);
+                    let _ = app_clone.emit("save-backup-failed", serde_json::json!({ "game_id": g.id, "error": e }));
+                }
+            }
+            if g.close_companions_on_exit {
+                for mut c in companions {
+                    let _ = c.kill();
+                    let _ = c.wait();
+                }
+            }
+            if !g.post_exit_hooks.is_empty() {
+                let _ = hooks::run_hooks(&g.post_exit_hooks, "post_exit", &g.id, &p, &load_tools_file().unwrap_or_default(), &|f| emit_hook_failure(&app_clone, f));
             }
         }
     });
@@ -68,40 +249,215 @@ pub async fn launch_exe(path: String, app: tauri::AppHandle, state: State<'_, Ru
 
 // Kill a running game process
 #[tauri::command]
-pub fn kill_game(path: String, state: State<'_, RunningProcesses>) -> Result<(), String> {
+pub fn kill_game(path: String, state: State<'_, RunningProcesses>) -> Result<(), KanoError> {
+    kill_process(&process_key(&path), &state)
+}
+
+#[tauri::command]
+pub fn kill_game_by_id(id: String, state: State<'_, RunningProcesses>) -> Result<(), KanoError> {
+    kill_process(&id, &state)
+}
+
+// Kills every process currently attributed to the game (launcher, children, watched process).
+// The game is only flagged as killed once a kill went through, so a failed attempt
+// doesn't mark a session that is still running.
+fn kill_process(key: &str, state: &RunningProcesses) -> Result<(), KanoError> {
     let processes = state.processes.lock().unwrap();
-    
-    if let Some(&pid) = processes.get(&path) {
-        #[cfg(target_os = "windows")]
-        {
-            // Use taskkill on Windows
-            std::process::Command::new("taskkill")
-                .args(&["/F", "/PID", &pid.to_string()])
-                .output()
-                .map_err(|e| e.to_string())?;
-        }
-        
-        #[cfg(not(target_os = "windows"))]
-        {
-            // Use kill on Unix-like systems
-            use std::process::Command;
-            Command::new("kill")
-                .args(&["-9", &pid.to_string()])
-                .output()
-                .map_err(|e| e.to_string())?;
+    let Some(pids) = processes.get(key) else {
+        return Err(KanoError::not_found("process"));
+    };
+
+    let mut last_err = None;
+    let mut any_killed = false;
+    for pid in pids {
+        match kill_pid(*pid) {
+            Ok(()) => any_killed = true,
+            Err(e) => last_err = Some(e),
         }
-        
+    }
+    if any_killed {
+        state.killed.lock().unwrap().insert(key.to_string());
+    }
+    match last_err {
+        Some(e) if !any_killed => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn kill_pid(pid: u32) -> Result<(), KanoError> {
+    // Use taskkill on Windows, kill on Unix-like systems
+    #[cfg(target_os = "windows")]
+    let output = std::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .output()?;
+    #[cfg(not(target_os = "windows"))]
+    let output = std::process::Command::new("kill")
+        .args(["-9", &pid.to_string()])
+        .output()?;
+
+    if output.status.success() {
         Ok(())
     } else {
-        Err("Process not found".into())
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(KanoError::invalid("kill_failed", format!("pid {}: {}", pid, stderr)))
     }
 }
 
+// Track a process by name instead of (or in addition to) the launched exe,
+// e.g. when a launcher starts the real game and exits
 #[tauri::command]
-pub fn add_game(path: &str, name: Option<&str>, folder_path: Option<Vec<String>>) -> Result<GameEntry, String> {
+pub fn set_game_watch_process(id: &str, process_name: Option<String>) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(id)) {
+        Some(g) => {
+            g.watch_process = process_name
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty());
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Add or replace a launch profile; a new id is assigned when the profile has none
+#[tauri::command]
+pub fn save_launch_profile(game_id: &str, profile: LaunchProfile) -> Result<LaunchProfile, KanoError> {
+    let mut profile = profile;
+    if profile.id.trim().is_empty() {
+        profile.id = Uuid::new_v4().to_string();
+    }
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => match g.launch_profiles.iter_mut().find(|p| p.id == profile.id) {
+            Some(existing) => *existing = profile.clone(),
+            None => g.launch_profiles.push(profile.clone()),
+        },
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn remove_launch_profile(game_id: &str, profile_id: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            g.launch_profiles.retain(|p| p.id != profile_id);
+            if g.default_profile.as_deref() == Some(profile_id) {
+                g.default_profile = None;
+            }
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Choose the profile used when launching without an explicit profile id (None = plain exe)
+#[tauri::command]
+pub fn set_default_launch_profile(game_id: &str, profile_id: Option<String>) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            if let Some(pid) = &profile_id {
+                if !g.launch_profiles.iter().any(|p| &p.id == pid) {
+                    return Err(KanoError::not_found("launch_profile"));
+                }
+            }
+            g.default_profile = profile_id;
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Pick the runner (compatibility wrapper) used to launch a game; None launches directly
+#[tauri::command]
+pub fn set_game_runner(game_id: &str, runner_id: Option<String>) -> Result<(), KanoError> {
+    if let Some(rid) = &runner_id {
+        if !load_config()?.runners.iter().any(|r| &r.id == rid) {
+            return Err(KanoError::not_found("runner"));
+        }
+    }
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => g.runner_id = runner_id,
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Replace a game's pre-launch and post-exit hooks
+#[tauri::command]
+pub fn set_game_hooks(game_id: &str, pre_launch: Vec<GameHook>, post_exit: Vec<GameHook>) -> Result<(), KanoError> {
+    let tools = load_tools_file()?;
+    for h in pre_launch.iter().chain(post_exit.iter()) {
+        match (&h.tool_id, &h.command) {
+            (Some(tid), _) if !tools.iter().any(|t| &t.id == tid) => return Err(KanoError::not_found("tool")),
+            (None, None) => return Err(KanoError::invalid("hook_without_command", "hook needs a command or a tool")),
+            (None, Some(c)) if c.trim().is_empty() => return Err(KanoError::invalid("hook_command_empty", "hook command is empty")),
+            _ => {}
+        }
+    }
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            g.pre_launch_hooks = pre_launch;
+            g.post_exit_hooks = post_exit;
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Attach Toolbox tools that launch together with the game
+#[tauri::command]
+pub fn set_game_companions(game_id: &str, tool_ids: Vec<String>, close_on_exit: bool) -> Result<(), KanoError> {
+    let tools = load_tools_file()?;
+    if tool_ids.iter().any(|id| !tools.iter().any(|t| &t.id == id)) {
+        return Err(KanoError::not_found("tool"));
+    }
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            g.companion_tools = tool_ids;
+            g.close_companions_on_exit = close_on_exit;
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+// Link a game to its VNDB entry ("v17", "17" or a vndb.org link); None unlinks it
+#[tauri::command]
+pub fn set_game_vndb_id(game_id: &str, vndb_id: Option<String>) -> Result<GameEntry, KanoError> {
+    let vndb_id = match vndb_id.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => Some(vndb::normalize_id(s).ok_or_else(|| KanoError::invalid("vndb_id", format!("invalid VNDB id: {}", s)))?),
+        None => None,
+    };
+    let mut db = load_games_db()?;
+    let updated = match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            g.vndb_id = vndb_id;
+            g.clone()
+        }
+        None => return Err(KanoError::not_found("game")),
+    };
+    save_games_db(&db)?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn add_game(path: &str, name: Option<&str>, folder_path: Option<Vec<String>>) -> Result<GameEntry, KanoError> {
     let p = PathBuf::from(path);
     if !p.exists() || !p.is_file() {
-        return Err("path not found or not a file".into());
+        return Err(KanoError::not_found("file"));
     }
     let nm = name.map(|s| s.to_string()).unwrap_or_else(|| {
         p.file_stem()
@@ -109,20 +465,32 @@ pub fn add_game(path: &str, name: Option<&str>, folder_path: Option<Vec<String>>
             .unwrap_or_else(|| "unnamed".into())
     });
     let entry = GameEntry {
+        id: Uuid::new_v4().to_string(),
         name: nm,
         path: p.to_string_lossy().to_string(),
         image: None,
         image_url: None,
         subject_id: None,
+        vndb_id: None,
         playtime: 0,
         last_played: None,
         folder_path,
         tags: vec![],
+        watch_process: None,
+        launch_profiles: vec![],
+        default_profile: None,
+        runner_id: None,
+        pre_launch_hooks: vec![],
+        post_exit_hooks: vec![],
+        companion_tools: vec![],
+        close_companions_on_exit: false,
+        save_dirs: vec![],
+        metadata: None,
     };
-    let mut db = load_games_db();
+    let mut db = load_games_db()?;
     // avoid duplicates
-    if db.games.iter().any(|g| g.path == entry.path) {
-        return Ok(entry);
+    if let Some(existing) = db.find(&GameKey::Path(&entry.path)) {
+        return Ok(existing.clone());
     }
     db.games.push(entry.clone());
     save_games_db(&db)?;
@@ -130,34 +498,40 @@ pub fn add_game(path: &str, name: Option<&str>, folder_path: Option<Vec<String>>
 }
 
 #[tauri::command]
-pub fn update_game_image(path: &str, image: &str) -> Result<(), String> {
-    let mut db = load_games_db();
-    let mut changed = false;
-    for g in db.games.iter_mut() {
-        if g.path == path {
-            g.image = Some(image.to_string());
-            changed = true;
-            break;
-        }
-    }
-    if changed {
-        save_games_db(&db)?;
-        Ok(())
-    } else {
-        Err("game not found".into())
+pub fn update_game_image(path: &str, image: &str) -> Result<(), KanoError> {
+    set_game_image(GameKey::Path(path), image)
+}
+
+#[tauri::command]
+pub fn update_game_image_by_id(id: &str, image: &str) -> Result<(), KanoError> {
+    set_game_image(GameKey::Id(id), image)
+}
+
+fn set_game_image(key: GameKey, image: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
+    match db.find_mut(&key) {
+        Some(g) => g.image = Some(image.to_string()),
+        None => return Err(KanoError::not_found("game")),
     }
+    save_games_db(&db)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_game_info(path: &str, name: Option<&str>, image: Option<&str>, image_url: Option<&str>, subject_id: Option<i64>) -> Result<serde_json::Value, KanoError> {
+    set_game_info(GameKey::Path(path), name, image, image_url, subject_id)
 }
 
 #[tauri::command]
-pub fn update_game_info(path: &str, name: Option<&str>, image: Option<&str>, image_url: Option<&str>, subject_id: Option<i64>) -> Result<serde_json::Value, String> {
-    let mut db = load_games_db();
-    let mut found = false;
-    let mut updated_entry: Option<GameEntry> = None;
+pub fn update_game_info_by_id(id: &str, name: Option<&str>, image: Option<&str>, image_url: Option<&str>, subject_id: Option<i64>) -> Result<serde_json::Value, KanoError> {
+    set_game_info(GameKey::Id(id), name, image, image_url, subject_id)
+}
+
+fn set_game_info(key: GameKey, name: Option<&str>, image: Option<&str>, image_url: Option<&str>, subject_id: Option<i64>) -> Result<serde_json::Value, KanoError> {
+    let mut db = load_games_db()?;
     
-    for g in db.games.iter_mut() {
-        if g.path == path {
-            found = true;
-            
+    let updated_entry = match db.find_mut(&key) {
+        Some(g) => {
             // Always apply all updates if provided
             if let Some(n) = name {
                 g.name = n.to_string();
@@ -173,67 +547,99 @@ pub fn update_game_info(path: &str, name: Option<&str>, image: Option<&str>, ima
             }
             
             // Important: Only clone AFTER all updates are applied
-            updated_entry = Some(g.clone());
-            break;
+            g.clone()
         }
-    }
+        None => return Err(KanoError::not_found("game")),
+    };
     
-    if found {
-        // Always save if we found the game, regardless of whether values changed
-        save_games_db(&db)?;
-        match updated_entry {
-            Some(entry) => {
-                let v = serde_json::to_value(entry).map_err(|e| e.to_string())?;
-                Ok(v)
+    // Always save if we found the game, regardless of whether values changed
+    save_games_db(&db)?;
+    Ok(serde_json::to_value(updated_entry)?)
+}
+
+// Change where a game's executable lives; the id (and with it playtime, tags and notes) stays the same
+#[tauri::command]
+pub fn update_game_path(id: &str, path: &str, folder_path: Option<Vec<String>>) -> Result<GameEntry, KanoError> {
+    let p = PathBuf::from(path);
+    if !p.exists() || !p.is_file() {
+        return Err(KanoError::not_found("file"));
+    }
+    let new_path = p.to_string_lossy().to_string();
+    let mut db = load_games_db()?;
+    if db.games.iter().any(|g| g.path == new_path && g.id != id) {
+        return Err(KanoError::invalid("path_in_use", "another game already uses this path"));
+    }
+    let updated = match db.find_mut(&GameKey::Id(id)) {
+        Some(g) => {
+            g.path = new_path;
+            if folder_path.is_some() {
+                g.folder_path = folder_path;
             }
-            None => Err("failed to get updated entry".into())
+            g.clone()
         }
-    } else {
-        Err("game not found".into())
-    }
+        None => return Err(KanoError::not_found("game")),
+    };
+    save_games_db(&db)?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn update_game_playtime(path: &str, additional_seconds: i64, last_played: &str) -> Result<i64, KanoError> {
+    add_game_playtime(GameKey::Path(path), additional_seconds, last_played)
 }
 
 #[tauri::command]
-pub fn update_game_playtime(path: &str, additional_seconds: i64, last_played: &str) -> Result<i64, String> {
-    let mut db = load_games_db();
-    let mut found = false;
-    let mut total_playtime = 0i64;
+pub fn update_game_playtime_by_id(id: &str, additional_seconds: i64, last_played: &str) -> Result<i64, KanoError> {
+    add_game_playtime(GameKey::Id(id), additional_seconds, last_played)
+}
+
+fn add_game_playtime(key: GameKey, additional_seconds: i64, last_played: &str) -> Result<i64, KanoError> {
+    let mut db = load_games_db()?;
     
-    for g in db.games.iter_mut() {
-        if g.path == path {
+    let total_playtime = match db.find_mut(&key) {
+        Some(g) => {
             g.playtime += additional_seconds;
             g.last_played = Some(last_played.to_string());
-            total_playtime = g.playtime;
-            found = true;
-            break;
+            g.playtime
         }
-    }
+        None => return Err(KanoError::not_found("game")),
+    };
     
-    if found {
-        save_games_db(&db)?;
-        Ok(total_playtime)
-    } else {
-        Err("game not found".into())
-    }
+    save_games_db(&db)?;
+    Ok(total_playtime)
+}
+
+#[tauri::command]
+pub fn list_games() -> Result<Vec<GameEntry>, KanoError> {
+    Ok(load_games_db()?.into_inner().games)
 }
 
+// Games matching every condition of `query`, in library order
 #[tauri::command]
-pub fn list_games() -> Result<Vec<GameEntry>, String> {
-    let db = load_games_db();
-    Ok(db.games)
+pub fn query_games(query: GameQuery) -> Result<Vec<GameEntry>, KanoError> {
+    let db = load_games_db()?.into_inner();
+    Ok(db.games.into_iter().filter(|g| query.matches(g)).collect())
 }
 
 #[tauri::command]
-pub fn remove_game(path: &str) -> Result<(), String> {
-    let mut db = load_games_db();
+pub fn remove_game(path: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
     db.games.retain(|g| g.path != path);
     save_games_db(&db)?;
     Ok(())
 }
 
+#[tauri::command]
+pub fn remove_game_by_id(id: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
+    db.games.retain(|g| g.id != id);
+    save_games_db(&db)?;
+    Ok(())
+}
+
 // Open a native file dialog (blocking) to pick a single .exe file and return its path.
 #[tauri::command]
-pub fn pick_exe(initial_dir: Option<String>) -> Result<String, String> {
+pub fn pick_exe(initial_dir: Option<String>) -> Result<String, KanoError> {
     // Use rfd (cross-platform) to present a native file dialog
     let mut dialog = rfd::FileDialog::new().add_filter("Executable", &["exe"]);
     
@@ -244,44 +650,71 @@ pub fn pick_exe(initial_dir: Option<String>) -> Result<String, String> {
     
     match dialog.pick_file() {
         Some(p) => Ok(p.to_string_lossy().to_string()),
-        None => Err("cancelled".into()),
+        None => Err(KanoError::Cancelled),
     }
 }
 
 // Open a native folder dialog and scan for game folders (each subfolder is treated as one game)
 #[tauri::command]
-pub fn pick_folder_and_scan() -> Result<Vec<serde_json::Value>, String> {
+pub fn pick_folder_and_scan() -> Result<Vec<serde_json::Value>, KanoError> {
     match rfd::FileDialog::new().pick_folder() {
         Some(parent_folder) => {
             let mut games = Vec::new();
+            let ranker = ExeRanker::from_config(&load_config()?);
             
             // Scan each direct subfolder
             if let Ok(entries) = fs::read_dir(&parent_folder) {
                 for entry in entries.flatten() {
                     let path = entry.path();
                     if path.is_dir() {
-                        // Recursively find exe in this folder and its subfolders
-                        if let Some((exe_path, mut folder_names)) = find_exe_with_folder_path(&path) {
-                            let folder_name = path.file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("Unknown")
-                                .to_string();
-                            
-                            // 在路径前面插入游戏文件夹自己的名字（用户选择文件夹下的第一层）
-                            folder_names.insert(0, folder_name.clone());
-                            
-                            games.push(serde_json::json!({
-                                "path": exe_path,
-                                "name": folder_name,
-                                "folder_path": folder_names  // 从用户选择的文件夹开始的完整路径
-                            }));
+                        // Recursively find and rank exe files in this folder and its subfolders
+                        let mut candidates = list_exe_candidates(&path, &ranker);
+                        if candidates.is_empty() {
+                            continue;
                         }
+                        let folder_name = path.file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or("Unknown")
+                            .to_string();
+                        
+                        // 在路径前面插入游戏文件夹自己的名字（用户选择文件夹下的第一层）
+                        for c in candidates.iter_mut() {
+                            c.folder_path.insert(0, folder_name.clone());
+                        }
+                        let best = candidates.remove(0);
+                        candidates.truncate(3);
+                        
+                        games.push(serde_json::json!({
+                            "path": best.path,
+                            "name": folder_name,
+                            "folder_path": best.folder_path,  // 从用户选择的文件夹开始的完整路径
+                            "score": best.score,
+                            "candidates": candidates  // 次优候选，供用户改选
+                        }));
                     }
                 }
             }
             
             Ok(games)
         }
-        None => Err("cancelled".into()),
+        None => Err(KanoError::Cancelled),
+    }
+}
+
+// List all candidate executables under a game folder so the user can pick the right one.
+// Accepts either the folder itself or an exe inside it (its parent folder is scanned).
+#[tauri::command(rename_all = "snake_case")]
+pub fn list_exes(game_dir: String) -> Result<Vec<ExeCandidate>, KanoError> {
+    let mut dir = PathBuf::from(&game_dir);
+    if dir.is_file() {
+        dir = match dir.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return Err(KanoError::not_found("folder")),
+        };
+    }
+    if !dir.is_dir() {
+        return Err(KanoError::not_found("folder"));
     }
+    let ranker = ExeRanker::from_config(&load_config()?);
+    Ok(list_exe_candidates(&dir, &ranker))
 }
@@ -0,0 +1,660 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use crate::error::KanoError;
+use crate::bangumi_cache::{self, BangumiCache};
+use crate::commands::notes;
+use crate::config::{
+    images_dir_path, load_config, load_games_db, load_sessions, load_token, load_tools_file,
+    replace_games_db, save_config, save_games_db, save_sessions, save_token, save_tools_file,
+};
+use crate::data_dir::{begin_write, data_dir};
+use crate::models::{AppConfig, GamesDB, PlaySession, SyncBackendConfig, ToolEntry};
+
+/// Bump when the archive layout changes; older versions must stay importable
+const ARCHIVE_VERSION: u32 = 1;
+const ARCHIVE_FORMAT: &str = "kano-library";
+
+/// manifest.json at the root of an exported library
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created: String,
+    pub includes_token: bool,
+    pub games: usize,
+    pub notes: usize,
+    pub tools: usize,
+    pub sessions: usize,
+    pub images: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    Merge,   // 保留本地数据，只添加新条目，冲突项列入报告
+    Replace, // 用存档内容整体替换本地数据（替换前自动备份）
+}
+
+/// An entry that exists on both sides and was left as it is locally
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportConflict {
+    pub kind: String, // "game" | "tool" | "note" | "runner" | "image" | "token"
+    pub key: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ImportReport {
+    pub games_added: usize,
+    pub notes_added: usize,
+    pub tools_added: usize,
+    pub sessions_added: usize,
+    pub images_added: usize,
+    pub token_imported: bool,
+    pub conflicts: Vec<ImportConflict>,
+    pub backup_path: Option<String>, // replace 模式下导入前的自动备份
+}
+
+// Export everything under kano_data into one zip. The Bangumi token is left out unless asked for.
+// Without a path a save dialog is shown.
+#[tauri::command]
+pub async fn export_library(path: Option<String>, include_token: bool) -> Result<LibraryManifest, KanoError> {
+    // the dialog and the zip writing block; keep them off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = match path {
+            Some(p) => PathBuf::from(p),
+            None => {
+                let name = format!("kano-library-{}.zip", chrono::Local::now().format("%Y%m%d"));
+                rfd::FileDialog::new()
+                    .add_filter("Kano library", &["zip"])
+                    .set_file_name(&name)
+                    .save_file()
+                    .ok_or(KanoError::Cancelled)?
+            }
+        };
+        write_library(&target, include_token)
+    })
+    .await
+    .map_err(|e| KanoError::Other { message: format!("background task failed: {e}") })?
+}
+
+// Import an archive made by export_library. `mode` is "merge" or "replace".
+// Without a path an open dialog is shown.
+#[tauri::command]
+pub async fn import_library(path: Option<String>, mode: ImportMode) -> Result<ImportReport, KanoError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = match path {
+            Some(p) => PathBuf::from(p),
+            None => rfd::FileDialog::new()
+                .add_filter("Kano library", &["zip"])
+                .pick_file()
+                .ok_or(KanoError::Cancelled)?,
+        };
+        import_from(&source, mode)
+    })
+    .await
+    .map_err(|e| KanoError::Other { message: format!("background task failed: {e}") })?
+}
+
+fn import_from(source: &Path, mode: ImportMode) -> Result<ImportReport, KanoError> {
+    let archive = LibraryArchive::read(source)?;
+    let _writing = begin_write()?;
+    let mut report = ImportReport::default();
+    if mode == ImportMode::Replace {
+        // keep a way back: the current library (token included) goes to kano_data/backups
+        let dir = data_dir().join("backups");
+        fs::create_dir_all(&dir).map_err(|e| KanoError::io("create backups dir", e))?;
+        let backup = dir.join(format!("before-import-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        write_library(&backup, true)?;
+        report.backup_path = Some(backup.to_string_lossy().to_string());
+    }
+    archive.apply(mode, &mut report)?;
+    Ok(report)
+}
+
+fn write_library(target: &Path, include_token: bool) -> Result<LibraryManifest, KanoError> {
+    let db = load_games_db()?;
+    let notes = notes::load_all()?;
+    let tools = load_tools_file()?;
+    let sessions = load_sessions()?;
+    let mut cfg = load_config()?;
+    if !include_token {
+        if let Some(SyncBackendConfig::Webdav { password, .. }) = cfg.sync.backend.as_mut() {
+            *password = None;
+        }
+    }
+    let token = if include_token { load_token() } else { None };
+    let images = list_images();
+
+    let manifest = LibraryManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Utc::now().to_rfc3339(),
+        includes_token: token.is_some(),
+        games: db.games.len(),
+        notes: notes.len(),
+        tools: tools.len(),
+        sessions: sessions.len(),
+        images: images.len(),
+    };
+
+    let tmp = target.with_extension(format!("tmp.{}", std::process::id()));
+    let res = (|| -> Result<(), KanoError> {
+        let file = fs::File::create(&tmp)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        let mut put = |name: &str, data: &[u8]| -> Result<(), KanoError> {
+            zip.start_file(name, options)?;
+            Ok(zip.write_all(data)?)
+        };
+        put("manifest.json", &to_json(&manifest)?)?;
+        put("games_db.json", &to_json(&*db)?)?;
+        put("notes.json", &to_json(&notes)?)?;
+        put("tools.json", &to_json(&tools)?)?;
+        put("sessions.json", &to_json(&sessions)?)?;
+        put("config.json", &to_json(&cfg)?)?;
+        put("bangumi_cache.json", &to_json(&bangumi_cache::snapshot())?)?;
+        if let Some(tok) = &token {
+            put("tokens.json", &to_json(&serde_json::json!({ "access_token": tok }))?)?;
+        }
+        for img in &images {
+            let data = fs::read(img).map_err(|e| KanoError::io(&format!("read {}", img.display()), e))?;
+            let name = img.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            put(&format!("images/{}", name), &data)?;
+        }
+        zip.finish()?;
+        Ok(fs::rename(&tmp, target)?)
+    })();
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res.map(|_| manifest)
+}
+
+fn to_json<T: Serialize>(v: &T) -> Result<Vec<u8>, KanoError> {
+    Ok(serde_json::to_vec_pretty(v)?)
+}
+
+fn list_images() -> Vec<PathBuf> {
+    fs::read_dir(images_dir_path())
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect())
+        .unwrap_or_default()
+}
+
+/// The parsed contents of an exported library
+struct LibraryArchive {
+    db: GamesDB,
+    notes: Vec<notes::Note>,
+    tools: Vec<ToolEntry>,
+    sessions: Vec<PlaySession>,
+    config: Option<AppConfig>,
+    cache: BangumiCache,
+    token: Option<String>,
+    images: Vec<(String, Vec<u8>)>,
+}
+
+impl LibraryArchive {
+    fn read(path: &Path) -> Result<Self, KanoError> {
+        let file = fs::File::open(path)?;
+        let mut zip = zip::ZipArchive::new(file)
+            .map_err(|e| KanoError::invalid("not_a_library_archive", format!("not a library archive: {}", e)))?;
+
+        let manifest: LibraryManifest = read_json(&mut zip, "manifest.json")?
+            .ok_or_else(|| KanoError::invalid("not_a_library_archive", "not a library archive: manifest.json missing"))?;
+        if manifest.format != ARCHIVE_FORMAT {
+            return Err(KanoError::invalid("not_a_library_archive", "not a library archive"));
+        }
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(KanoError::invalid(
+                "archive_too_new",
+                format!("archive version {} is newer than this app supports ({})", manifest.version, ARCHIVE_VERSION),
+            ));
+        }
+
+        let token = read_json::<serde_json::Value>(&mut zip, "tokens.json")?
+            .and_then(|v| v.get("access_token").and_then(|t| t.as_str()).map(|t| t.trim().to_string()))
+            .filter(|t| !t.is_empty());
+        let mut images = Vec::new();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let name = match entry.enclosed_name().as_deref().and_then(image_name) {
+                Some(n) => n,
+                None => continue,
+            };
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            images.push((name, data));
+        }
+
+        Ok(LibraryArchive {
+            db: read_json(&mut zip, "games_db.json")?.unwrap_or_default(),
+            notes: read_json(&mut zip, "notes.json")?.unwrap_or_default(),
+            tools: read_json(&mut zip, "tools.json")?.unwrap_or_default(),
+            sessions: read_json(&mut zip, "sessions.json")?.unwrap_or_default(),
+            config: read_json(&mut zip, "config.json")?,
+            // archives from older versions hold the frontend's cache format, which reads as empty
+            cache: read_json(&mut zip, "bangumi_cache.json")?.unwrap_or_default(),
+            token,
+            images,
+        })
+    }
+
+    fn apply(self, mode: ImportMode, report: &mut ImportReport) -> Result<(), KanoError> {
+        match mode {
+            ImportMode::Replace => self.replace(report),
+            ImportMode::Merge => self.merge(report),
+        }
+    }
+
+    // Everything is written or nothing is: images go to a staging folder first, the data
+    // files are written next (rolled back to the local data if one fails), and only then
+    // the staged images replace the local ones.
+    fn replace(self, report: &mut ImportReport) -> Result<(), KanoError> {
+        let local = LibraryData::load()?;
+        let images_dir = images_dir_path();
+        let staging = images_dir.with_file_name("images.importing");
+        let res = stage_images(&staging, &self.images);
+        if let Err(e) = res {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        // this machine's sync identity and state aren't part of the library
+        let mut config = match self.config {
+            Some(c) => c,
+            None => load_config()?,
+        };
+        config.sync = local.config.sync.clone();
+        // a library exported without the token keeps the local one
+        let token_imported = self.token.is_some();
+        let imported = LibraryData {
+            db: self.db,
+            notes: self.notes,
+            tools: self.tools,
+            sessions: self.sessions,
+            cache: self.cache,
+            config,
+            token: self.token.or_else(|| local.token.clone()),
+        };
+        if let Err(e) = imported.save() {
+            if let Err(re) = local.save() {
+                eprintln!("failed to roll back library import: {}", re);
+            }
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        swap_dir(&staging, &images_dir)?;
+        report.games_added = imported.db.games.len();
+        report.notes_added = imported.notes.len();
+        report.tools_added = imported.tools.len();
+        report.sessions_added = imported.sessions.len();
+        report.images_added = self.images.len();
+        report.token_imported = token_imported;
+        Ok(())
+    }
+
+    fn merge(self, report: &mut ImportReport) -> Result<(), KanoError> {
+        // Games: ids are stable across machines; a different id on the same path is a conflict too
+        let mut db = load_games_db()?;
+        for game in self.db.games {
+            let existing = db.games.iter().find(|g| g.id == game.id || g.path == game.path);
+            match existing {
+                Some(local) => report.conflicts.push(ImportConflict {
+                    kind: "game".into(),
+                    key: game.id.clone(),
+                    message: if local.id == game.id {
+                        format!("{} already in library", game.name)
+                    } else {
+                        format!("{} uses the same path as {}", game.name, local.name)
+                    },
+                }),
+                None => {
+                    db.games.push(game);
+                    report.games_added += 1;
+                }
+            }
+        }
+        for tag in self.db.custom_tags {
+            if !db.custom_tags.contains(&tag) {
+                db.custom_tags.push(tag);
+            }
+        }
+
+        // Notes are per game; standalone notes are added unless an identical one exists
+        let loaded_notes = notes::load_all()?;
+        let mut notes = loaded_notes.clone();
+        for note in self.notes {
+            let clash = notes.iter().find(|n| match (&n.game_id, &note.game_id) {
+                (Some(a), Some(b)) => a == b,
+                _ => n.title == note.title && n.content == note.content,
+            });
+            match clash {
+                Some(n) if n.content != note.content => report.conflicts.push(ImportConflict {
+                    kind: "note".into(),
+                    key: note.game_id.clone().unwrap_or_else(|| note.title.clone()),
+                    message: format!("note for {} differs", note.game_name.clone().unwrap_or_else(|| note.title.clone())),
+                }),
+                Some(_) => {}
+                None => {
+                    notes.push(note);
+                    report.notes_added += 1;
+                }
+            }
+        }
+
+        let mut tools = load_tools_file()?;
+        for tool in self.tools {
+            match tools.iter().find(|t| t.id == tool.id) {
+                Some(t) if t.path != tool.path => report.conflicts.push(ImportConflict {
+                    kind: "tool".into(),
+                    key: tool.id.clone(),
+                    message: format!("{} points to {} locally", tool.name, t.path),
+                }),
+                Some(_) => {}
+                None => {
+                    tools.push(tool);
+                    report.tools_added += 1;
+                }
+            }
+        }
+
+        let mut sessions = load_sessions()?;
+        for s in self.sessions {
+            if !sessions.iter().any(|l| l.id == s.id) {
+                sessions.push(s);
+                report.sessions_added += 1;
+            }
+        }
+
+        // Settings stay local; runners are added when missing
+        let mut cfg = load_config()?;
+        if let Some(imported) = self.config {
+            for r in imported.runners {
+                match cfg.runners.iter().find(|l| l.id == r.id) {
+                    Some(l) if l.command != r.command => report.conflicts.push(ImportConflict {
+                        kind: "runner".into(),
+                        key: r.id.clone(),
+                        message: format!("runner {} differs", r.name),
+                    }),
+                    Some(_) => {}
+                    None => cfg.runners.push(r),
+                }
+            }
+        }
+
+        report.images_added = write_images(&self.images, report)?;
+        if let Some(tok) = self.token {
+            match load_token() {
+                Some(local) if local != tok => report.conflicts.push(ImportConflict {
+                    kind: "token".into(),
+                    key: "access_token".into(),
+                    message: "a different Bangumi token is already set".into(),
+                }),
+                Some(_) => {}
+                None => {
+                    save_token(&tok)?;
+                    report.token_imported = true;
+                }
+            }
+        }
+
+        save_games_db(&db)?;
+        notes::save_changes(&loaded_notes, &notes)?;
+        save_tools_file(&tools)?;
+        save_sessions(&sessions)?;
+        save_config(&cfg)?;
+        // Bangumi cache: local entries win
+        bangumi_cache::import(self.cache, false)
+    }
+}
+
+/// The local data a replace import overwrites, kept so a failed import can be undone
+struct LibraryData {
+    db: GamesDB,
+    notes: Vec<notes::Note>,
+    tools: Vec<ToolEntry>,
+    sessions: Vec<PlaySession>,
+    cache: BangumiCache,
+    config: AppConfig,
+    token: Option<String>,
+}
+
+impl LibraryData {
+    fn load() -> Result<Self, KanoError> {
+        Ok(LibraryData {
+            db: load_games_db()?.into_inner(),
+            notes: notes::load_all()?,
+            tools: load_tools_file()?,
+            sessions: load_sessions()?,
+            cache: bangumi_cache::snapshot(),
+            config: load_config()?,
+            token: load_token(),
+        })
+    }
+
+    fn save(&self) -> Result<(), KanoError> {
+        // saved back as a whole, not as changes since loading
+        replace_games_db(&self.db)?;
+        notes::save_all(&self.notes)?;
+        save_tools_file(&self.tools)?;
+        save_sessions(&self.sessions)?;
+        bangumi_cache::import(self.cache.clone(), true)?;
+        save_config(&self.config)?;
+        // an empty token clears it
+        save_token(self.token.as_deref().unwrap_or_default())
+    }
+}
+
+fn stage_images(staging: &Path, images: &[(String, Vec<u8>)]) -> Result<(), KanoError> {
+    if staging.exists() {
+        fs::remove_dir_all(staging).map_err(|e| KanoError::io("clear staged images", e))?;
+    }
+    fs::create_dir_all(staging).map_err(|e| KanoError::io("create staged images dir", e))?;
+    for (name, data) in images {
+        fs::write(staging.join(name), data).map_err(|e| KanoError::io(&format!("write image {}", name), e))?;
+    }
+    Ok(())
+}
+
+// Put `staged` in place of `target`; the old folder is only deleted once the new one is in
+fn swap_dir(staged: &Path, target: &Path) -> Result<(), KanoError> {
+    let old = target.with_extension("replaced");
+    if old.exists() {
+        fs::remove_dir_all(&old).map_err(|e| KanoError::io("clear replaced images", e))?;
+    }
+    if target.exists() {
+        fs::rename(target, &old).map_err(|e| KanoError::io("move images aside", e))?;
+    }
+    if let Err(e) = fs::rename(staged, target) {
+        let _ = fs::rename(&old, target);
+        return Err(KanoError::io("move imported images in place", e));
+    }
+    let _ = fs::remove_dir_all(&old);
+    Ok(())
+}
+
+// Copy images that don't exist yet; an existing file with other content is a conflict
+fn write_images(images: &[(String, Vec<u8>)], report: &mut ImportReport) -> Result<usize, KanoError> {
+    let dir = images_dir_path();
+    fs::create_dir_all(&dir).map_err(|e| KanoError::io("create images dir", e))?;
+    let mut added = 0;
+    for (name, data) in images {
+        let path = dir.join(name);
+        match fs::read(&path) {
+            Ok(local) if &local != data => report.conflicts.push(ImportConflict {
+                kind: "image".into(),
+                key: name.clone(),
+                message: "a different image with this name exists".into(),
+            }),
+            Ok(_) => {}
+            Err(_) => {
+                fs::write(&path, data)?;
+                added += 1;
+            }
+        }
+    }
+    Ok(added)
+}
+
+// `images/<name>` where the name is a single plain file name. Anything else could land
+// outside the images dir; ':' is refused too (drive prefixes, NTFS streams on Windows).
+fn image_name(path: &Path) -> Option<String> {
+    let name = path.strip_prefix("images").ok()?;
+    let mut parts = name.components();
+    match (parts.next(), parts.next()) {
+        (Some(Component::Normal(n)), None) => n.to_str().filter(|n| !n.contains(':')).map(str::to_string),
+        _ => None,
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(zip: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Option<T>, KanoError> {
+    let mut entry = match zip.by_name(name) {
+        Ok(e) => e,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut s = String::new();
+    entry.read_to_string(&mut s)?;
+    serde_json::from_str(&s)
+        .map(Some)
+        .map_err(|e| KanoError::Parse { message: format!("{} in archive is invalid: {}", name, e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_dir::TestDataDir;
+    use crate::models::GameEntry;
+
+    fn game(id: &str, path: &str) -> GameEntry {
+        serde_json::from_value(serde_json::json!({ "id": id, "name": id, "path": path })).unwrap()
+    }
+
+    fn save_games(games: &[(&str, &str)]) {
+        let db = GamesDB { games: games.iter().map(|(id, path)| game(id, path)).collect(), ..Default::default() };
+        replace_games_db(&db).unwrap();
+    }
+
+    fn game_ids() -> Vec<String> {
+        load_games_db().unwrap().into_inner().games.into_iter().map(|g| g.id).collect()
+    }
+
+    fn put_image(name: &str, data: &[u8]) {
+        fs::create_dir_all(images_dir_path()).unwrap();
+        fs::write(images_dir_path().join(name), data).unwrap();
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn merge_keeps_local_entries_and_reports_conflicts() {
+        let dir = TestDataDir::new("library-merge");
+        let archive = dir.root.join("library.zip");
+        save_games(&[("a", "C:/Games/A/a.exe"), ("b", "C:/Games/B/b.exe")]);
+        put_image("a.jpg", b"cover a");
+        let manifest = write_library(&archive, false).unwrap();
+        assert_eq!((manifest.games, manifest.images, manifest.includes_token), (2, 1, false));
+
+        // locally: b is gone, c is new, and a's cover changed
+        save_games(&[("a", "C:/Games/A/a.exe"), ("c", "C:/Games/C/c.exe")]);
+        put_image("a.jpg", b"new cover a");
+        let report = import_from(&archive, ImportMode::Merge).unwrap();
+
+        assert_eq!(game_ids(), ["a", "c", "b"]);
+        assert_eq!((report.games_added, report.images_added, report.backup_path), (1, 0, None));
+        let conflicts: Vec<_> = report.conflicts.iter().map(|c| (c.kind.as_str(), c.key.as_str())).collect();
+        assert_eq!(conflicts, [("game", "a"), ("image", "a.jpg")]);
+        assert_eq!(fs::read(images_dir_path().join("a.jpg")).unwrap(), b"new cover a");
+    }
+
+    #[test]
+    fn replace_restores_the_archive_and_backs_up_the_local_library() {
+        let dir = TestDataDir::new("library-replace");
+        let archive = dir.root.join("library.zip");
+        save_games(&[("a", "C:/Games/A/a.exe")]);
+        put_image("a.jpg", b"cover a");
+        write_library(&archive, false).unwrap();
+
+        save_games(&[("b", "C:/Games/B/b.exe")]);
+        put_image("b.jpg", b"cover b");
+        let report = import_from(&archive, ImportMode::Replace).unwrap();
+
+        assert_eq!(game_ids(), ["a"]);
+        assert_eq!((report.games_added, report.images_added), (1, 1));
+        assert!(!images_dir_path().join("b.jpg").exists());
+        assert!(!images_dir_path().with_file_name("images.importing").exists());
+        // the library as it was before the import can be brought back the same way
+        let backup = PathBuf::from(report.backup_path.unwrap());
+        import_from(&backup, ImportMode::Replace).unwrap();
+        assert_eq!(game_ids(), ["b"]);
+        assert_eq!(fs::read(images_dir_path().join("b.jpg")).unwrap(), b"cover b");
+    }
+
+    #[test]
+    fn bad_archives_are_rejected() {
+        let dir = TestDataDir::new("library-bad");
+        let reason = |path: &Path| match import_from(path, ImportMode::Merge) {
+            Err(KanoError::InvalidInput { reason, .. }) => reason,
+            other => panic!("{:?}", other),
+        };
+
+        let not_zip = dir.root.join("not-a-zip.zip");
+        fs::write(&not_zip, b"plain text").unwrap();
+        assert_eq!(reason(&not_zip), "not_a_library_archive");
+
+        let no_manifest = dir.root.join("no-manifest.zip");
+        write_zip(&no_manifest, &[("games_db.json", b"{\"games\": []}")]);
+        assert_eq!(reason(&no_manifest), "not_a_library_archive");
+
+        let too_new = dir.root.join("too-new.zip");
+        let manifest = serde_json::json!({
+            "format": ARCHIVE_FORMAT, "version": ARCHIVE_VERSION + 1, "app_version": "9.9.9",
+            "created": "", "includes_token": false, "games": 0, "notes": 0, "tools": 0, "sessions": 0, "images": 0,
+        });
+        write_zip(&too_new, &[("manifest.json", manifest.to_string().as_bytes())]);
+        assert_eq!(reason(&too_new), "archive_too_new");
+        assert!(game_ids().is_empty());
+    }
+
+    #[test]
+    fn only_plain_image_names_are_read() {
+        let dir = TestDataDir::new("library-images");
+        let archive = dir.root.join("library.zip");
+        write_library(&archive, false).unwrap();
+        let manifest = {
+            let mut zip = zip::ZipArchive::new(fs::File::open(&archive).unwrap()).unwrap();
+            read_json::<serde_json::Value>(&mut zip, "manifest.json").unwrap().unwrap().to_string()
+        };
+        let names = [
+            "images/ok.jpg",
+            "images/../evil.dll",
+            "images/C:evil.dll",
+            "images/cover.jpg:stream",
+            "images/sub/nested.jpg",
+            "images/",
+            "../images/escape.jpg",
+            "/images/absolute.jpg",
+            "other/top.jpg",
+        ];
+        let mut files = vec![("manifest.json", manifest.as_bytes())];
+        files.extend(names.iter().map(|n| (*n, &b"data"[..])));
+        write_zip(&archive, &files);
+
+        let read = LibraryArchive::read(&archive).unwrap();
+        let images: Vec<_> = read.images.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(images, ["ok.jpg"]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::error::KanoError;
+use crate::config::{load_config, load_games_db, save_games_db};
+use crate::metadata::{provider_for, GameMetadata, MetadataProvider, MetadataSource};
+use crate::models::{GameEntry, GameKey};
+use crate::vndb::{VndbClient, VndbRelease};
+
+fn provider(source: MetadataSource, refresh: bool) -> Result<Box<dyn MetadataProvider>, KanoError> {
+    Ok(provider_for(source, &load_config()?.metadata, refresh))
+}
+
+// Search one source for games; results are normalized whatever the source.
+// `force_refresh` skips cached responses.
+#[tauri::command]
+pub async fn search_metadata(source: MetadataSource, query: String, limit: Option<u32>, force_refresh: Option<bool>) -> Result<Vec<GameMetadata>, KanoError> {
+    provider(source, force_refresh.unwrap_or(false))?.search(&query, limit.unwrap_or(20)).await
+}
+
+// Fetch one entry: Bangumi subject id, or VNDB id ("v17", "17" or a vndb.org link)
+#[tauri::command]
+pub async fn get_metadata(source: MetadataSource, id: String, force_refresh: Option<bool>) -> Result<GameMetadata, KanoError> {
+    provider(source, force_refresh.unwrap_or(false))?.fetch_details(&id).await
+}
+
+// Download the entry's cover to kano_data/images/{id}.{ext}
+#[tauri::command]
+pub async fn download_metadata_cover(metadata: GameMetadata) -> Result<String, KanoError> {
+    provider(metadata.source, false)?.fetch_cover(&metadata).await
+}
+
+// Editions of a VNDB entry (languages, platforms, publishers); VNDB only
+#[tauri::command]
+pub async fn get_vndb_releases(id: String) -> Result<Vec<VndbRelease>, KanoError> {
+    VndbClient::new(&load_config()?.metadata.vndb_api_url).releases(&id).await
+}
+
+// Store an entry's information on the game and link it; None removes the stored record
+// but keeps the links
+#[tauri::command]
+pub fn set_game_metadata(game_id: &str, metadata: Option<GameMetadata>) -> Result<GameEntry, KanoError> {
+    let mut db = load_games_db()?;
+    let g = db.find_mut(&GameKey::Id(game_id)).ok_or(KanoError::not_found("game"))?;
+    match metadata {
+        Some(meta) => meta.attach_to(g),
+        None => g.metadata = None,
+    }
+    let updated = g.clone();
+    save_games_db(&db)?;
+    Ok(updated)
+}
+
+// Fetch the linked entry again and store it. `source` defaults to the stored record's,
+// then Bangumi, then VNDB, whichever the game is linked to.
+#[tauri::command]
+pub async fn refresh_game_metadata(game_id: String, source: Option<MetadataSource>) -> Result<GameEntry, KanoError> {
+    let game = load_games_db()?.find(&GameKey::Id(&game_id)).cloned().ok_or(KanoError::not_found("game"))?;
+    let bangumi = game.subject_id.map(|id| (MetadataSource::Bangumi, id.to_string()));
+    let vndb = game.vndb_id.clone().map(|id| (MetadataSource::Vndb, id));
+    let linked = match source.or(game.metadata.as_ref().map(|m| m.source)) {
+        Some(MetadataSource::Bangumi) => bangumi,
+        Some(MetadataSource::Vndb) => vndb,
+        None => bangumi.or(vndb),
+    };
+    let (source, id) = linked.ok_or_else(|| KanoError::invalid("not_linked", "game is not linked to a metadata entry"))?;
+    let meta = provider(source, true)?.fetch_details(&id).await?;
+
+    let mut db = load_games_db()?;
+    let g = db.find_mut(&GameKey::Id(&game_id)).ok_or(KanoError::not_found("game"))?;
+    meta.attach_to(g);
+    let updated = g.clone();
+    save_games_db(&db)?;
+    Ok(updated)
+}
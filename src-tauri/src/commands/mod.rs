@@ -4,6 +4,14 @@ pub mod bangumi;
 pub mod settings;
 pub mod tags;
 pub mod notes;
+pub mod relocate;
+pub mod sessions;
+pub mod saves;
+pub mod library_io;
+pub mod sync;
+pub mod storage;
+pub mod metadata;
+pub mod auto_match;
 
 // Re-export all commands for easy access
 pub use game_library::*;
@@ -11,3 +19,11 @@ pub use bangumi::*;
 pub use settings::*;
 pub use tags::*;
 pub use notes::*;
+pub use relocate::*;
+pub use sessions::*;
+pub use saves::*;
+pub use library_io::*;
+pub use sync::*;
+pub use storage::*;
+pub use metadata::*;
+pub use auto_match::*;
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
-use crate::config;
+use crate::error::KanoError;
+use crate::db;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Note {
@@ -16,73 +16,31 @@ pub struct Note {
     pub created_at: String,
     #[serde(default)]
     pub updated_at: String,
+    /// Row in the notes table, set when loaded
+    #[serde(skip)]
+    pub row_id: Option<i64>,
 }
-// Legacy format support: older notes used `game_path`. We'll try to parse the
-// current format first; if it fails, attempt to parse legacy entries and
-// convert them so migration is seamless.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct LegacyNote {
-    #[serde(default)]
-    pub game_path: Option<String>,
-    #[serde(default)]
-    pub title: String,
-    #[serde(default)]
-    pub content: String,
-    #[serde(default)]
-    pub created_at: String,
-    #[serde(default)]
-    pub updated_at: String,
+pub(crate) fn load_all() -> Result<Vec<Note>, KanoError> {
+    Ok(db::load_notes()?)
 }
 
-fn load_all() -> Result<Vec<Note>, String> {
-    let path = config::notes_path();
-    if let Ok(s) = fs::read_to_string(&path) {
-        // try current format
-        if let Ok(v) = serde_json::from_str::<Vec<Note>>(&s) {
-            return Ok(v);
-        }
-        // try legacy format and convert
-        if let Ok(v2) = serde_json::from_str::<Vec<LegacyNote>>(&s) {
-            let converted = v2
-                .into_iter()
-                .map(|ln| Note {
-                    game_id: None,
-                    game_name: None,
-                    title: ln.title,
-                    content: ln.content,
-                    created_at: ln.created_at,
-                    updated_at: ln.updated_at,
-                })
-                .collect();
-            return Ok(converted);
-        }
-        Err(format!("parse notes: invalid format"))
-    } else {
-        Ok(Vec::new())
-    }
+/// Replace all notes
+pub(crate) fn save_all(notes: &[Note]) -> Result<(), KanoError> {
+    db::save_notes(notes, None)
 }
 
-fn save_all(notes: &Vec<Note>) -> Result<(), String> {
-    let path = config::notes_path();
-    if let Some(parent) = path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            return Err(format!("create notes dir: {}", e));
-        }
-    }
-    let s = serde_json::to_string_pretty(notes).map_err(|e| e.to_string())?;
-    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
-    fs::write(&tmp, s).map_err(|e| e.to_string())?;
-    fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
-    Ok(())
+/// Write only what changed since `loaded` was read, so concurrent edits to other notes survive
+pub(crate) fn save_changes(loaded: &[Note], notes: &[Note]) -> Result<(), KanoError> {
+    db::save_notes(notes, Some(loaded))
 }
 
 #[tauri::command]
-pub fn list_notes() -> Result<Vec<Note>, String> {
+pub fn list_notes() -> Result<Vec<Note>, KanoError> {
     load_all()
 }
 
 #[tauri::command]
-pub fn get_note(game_id: &str) -> Result<Option<Note>, String> {
+pub fn get_note(game_id: &str) -> Result<Option<Note>, KanoError> {
     let notes = load_all()?;
     for n in notes.into_iter() {
         if let Some(gid) = &n.game_id {
@@ -95,8 +53,9 @@ pub fn get_note(game_id: &str) -> Result<Option<Note>, String> {
 }
 
 #[tauri::command]
-pub fn save_note(note: Note) -> Result<Note, String> {
-    let mut notes = load_all()?;
+pub fn save_note(note: Note) -> Result<Note, KanoError> {
+    let loaded = load_all()?;
+    let mut notes = loaded.clone();
     let mut new_note = note.clone();
 
     // Ensure timestamps
@@ -135,28 +94,30 @@ pub fn save_note(note: Note) -> Result<Note, String> {
         notes.push(new_note.clone());
     }
 
-    save_all(&notes)?;
+    save_changes(&loaded, &notes)?;
     Ok(new_note)
 }
 
 #[tauri::command]
-pub fn delete_note(game_id: &str) -> Result<(), String> {
-    let mut notes = load_all()?;
+pub fn delete_note(game_id: &str) -> Result<(), KanoError> {
+    let loaded = load_all()?;
+    let mut notes = loaded.clone();
     notes.retain(|n| match &n.game_id {
         Some(gid) => gid != game_id,
         None => true,
     });
-    save_all(&notes)?;
+    save_changes(&loaded, &notes)?;
     Ok(())
 }
 
 #[tauri::command]
-pub fn delete_note_by_index(index: usize) -> Result<(), String> {
-    let mut notes = load_all()?;
+pub fn delete_note_by_index(index: usize) -> Result<(), KanoError> {
+    let loaded = load_all()?;
+    let mut notes = loaded.clone();
     if index >= notes.len() {
-        return Err(format!("index out of range: {}", index));
+        return Err(KanoError::invalid("index_out_of_range", format!("index out of range: {}", index)));
     }
     notes.remove(index);
-    save_all(&notes)?;
+    save_changes(&loaded, &notes)?;
     Ok(())
 }
@@ -0,0 +1,384 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::error::KanoError;
+use crate::config::{load_games_db, save_games_db};
+use crate::db::LoadedGames;
+use crate::models::GameEntry;
+use crate::utils::collect_exe_files_with_path;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MissingGame {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RelocationResult {
+    pub id: String,
+    pub name: String,
+    pub old_path: String,
+    pub new_path: Option<String>,
+    pub exists: bool,     // 新路径下文件是否存在
+    pub applied: bool,    // 是否已写入数据库
+    #[serde(default)]
+    pub skipped: bool,    // 原路径仍存在，未改动
+    pub match_kind: Option<String>, // 扫描匹配方式: "folder_and_exe" | "exe_only"
+    pub conflict: Option<String>,   // 未应用的原因: "path_in_use" | "same_target"
+}
+
+fn is_missing(g: &GameEntry) -> bool {
+    !Path::new(&g.path).is_file()
+}
+
+// Report library entries whose executable no longer exists
+#[tauri::command]
+pub fn find_missing_games() -> Result<Vec<MissingGame>, KanoError> {
+    let db = load_games_db()?;
+    Ok(db
+        .games
+        .iter()
+        .filter(|g| is_missing(g))
+        .map(|g| MissingGame { id: g.id.clone(), name: g.name.clone(), path: g.path.clone() })
+        .collect())
+}
+
+// Rewrite `old_prefix` to `new_root` for missing games under it (e.g. after moving a disk).
+// Only rewrites whose target file exists are saved; `dry_run` reports without saving.
+// Games under the prefix whose exe is still there are reported as `skipped` and left alone.
+#[tauri::command]
+pub fn relocate_games(old_prefix: &str, new_root: &str, dry_run: Option<bool>) -> Result<Vec<RelocationResult>, KanoError> {
+    if old_prefix.trim().is_empty() {
+        return Err(KanoError::invalid("old_prefix_empty", "old prefix is empty"));
+    }
+    let mut db = load_games_db()?;
+    let mut results = Vec::new();
+
+    for g in db.games.iter() {
+        let rest = match strip_path_prefix(&g.path, old_prefix) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let mut new_path = PathBuf::from(new_root);
+        for part in rest {
+            new_path.push(part);
+        }
+        let exists = new_path.is_file();
+        let new_path_str = new_path.to_string_lossy().to_string();
+        let skipped = !is_missing(g);
+        results.push(RelocationResult {
+            id: g.id.clone(),
+            name: g.name.clone(),
+            old_path: g.path.clone(),
+            new_path: Some(new_path_str),
+            exists,
+            applied: exists && !skipped,
+            skipped,
+            match_kind: None,
+            conflict: None,
+        });
+    }
+
+    apply(&mut db, &mut results, dry_run.unwrap_or(false))?;
+    Ok(results)
+}
+
+// Scan a folder (picked with a dialog when not given) and match missing games by their
+// game folder name and exe file name. Unambiguous matches are saved unless `dry_run`.
+#[tauri::command]
+pub fn relocate_games_by_scan(folder: Option<String>, dry_run: Option<bool>) -> Result<Vec<RelocationResult>, KanoError> {
+    let root = match folder {
+        Some(f) => PathBuf::from(f),
+        None => match rfd::FileDialog::new().pick_folder() {
+            Some(p) => p,
+            None => return Err(KanoError::Cancelled),
+        },
+    };
+    if !root.is_dir() {
+        return Err(KanoError::not_found("folder"));
+    }
+    let mut found = Vec::new();
+    collect_exe_files_with_path(&root, &root, &mut found);
+
+    let mut db = load_games_db()?;
+    let mut results = Vec::new();
+
+    for g in db.games.iter().filter(|g| is_missing(g)) {
+        let old = Path::new(&g.path);
+        let exe_name = match old.file_name() {
+            Some(n) => n.to_string_lossy().to_lowercase(),
+            None => continue,
+        };
+        let game_folder = g
+            .folder_path
+            .as_ref()
+            .and_then(|f| f.first().cloned())
+            .or_else(|| old.parent().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string()))
+            .map(|n| n.to_lowercase());
+
+        let same_exe: Vec<&(String, Vec<String>)> = found
+            .iter()
+            .filter(|(p, _)| {
+                Path::new(p)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase() == exe_name)
+                    .unwrap_or(false)
+            })
+            .collect();
+        let in_folder: Vec<&(String, Vec<String>)> = same_exe
+            .iter()
+            .copied()
+            .filter(|(_, folders)| match &game_folder {
+                Some(gf) => folders.iter().any(|f| f.to_lowercase() == *gf),
+                None => false,
+            })
+            .collect();
+
+        // Prefer an exe inside a folder named like the game's folder; otherwise accept a
+        // file-name match only when it is unique in the scanned tree.
+        let (matched, kind) = if in_folder.len() == 1 {
+            (Some(in_folder[0]), "folder_and_exe")
+        } else if in_folder.is_empty() && same_exe.len() == 1 {
+            (Some(same_exe[0]), "exe_only")
+        } else {
+            (None, "")
+        };
+
+        results.push(RelocationResult {
+            id: g.id.clone(),
+            name: g.name.clone(),
+            old_path: g.path.clone(),
+            new_path: matched.map(|(p, _)| p.clone()),
+            exists: matched.is_some(),
+            applied: matched.is_some(),
+            skipped: false,
+            match_kind: matched.map(|_| kind.to_string()),
+            conflict: None,
+        });
+    }
+
+    apply(&mut db, &mut results, dry_run.unwrap_or(false))?;
+    Ok(results)
+}
+
+// `applied` marks the planned moves on entry. A move isn't applied when its target is
+// already another entry's exe or several games would be moved onto it, since that would
+// leave two entries for one game. The rest are saved unless `dry_run`.
+fn apply(db: &mut LoadedGames, results: &mut [RelocationResult], dry_run: bool) -> Result<(), KanoError> {
+    let targets: Vec<String> = results
+        .iter()
+        .filter(|r| r.applied)
+        .filter_map(|r| r.new_path.as_deref().map(path_key))
+        .collect();
+    for r in results.iter_mut().filter(|r| r.applied) {
+        let Some(key) = r.new_path.as_deref().map(path_key) else {
+            continue;
+        };
+        let in_use = db.games.iter().any(|g| g.id != r.id && path_key(&g.path) == key);
+        let shared = targets.iter().filter(|t| **t == key).count() > 1;
+        if in_use || shared {
+            r.conflict = Some(if in_use { "path_in_use" } else { "same_target" }.to_string());
+            r.applied = false;
+        }
+    }
+
+    let mut changed = false;
+    for r in results.iter_mut().filter(|r| r.applied) {
+        if dry_run {
+            r.applied = false;
+            continue;
+        }
+        let (Some(g), Some(new_path)) = (db.games.iter_mut().find(|g| g.id == r.id), &r.new_path) else {
+            continue;
+        };
+        g.folder_path = rebuild_folder_path(&g.folder_path, Path::new(new_path));
+        g.path = new_path.clone();
+        changed = true;
+    }
+    if changed {
+        save_games_db(db)?;
+    }
+    Ok(())
+}
+
+// Compare paths component-wise, ignoring separator style, and case on Windows where paths
+// are case-insensitive. Returns the remaining components of `path` after `prefix`.
+pub(crate) fn strip_path_prefix(path: &str, prefix: &str) -> Option<Vec<String>> {
+    strip_prefix_components(path, prefix, cfg!(windows))
+}
+
+/// strip_path_prefix for paths that may come from another OS than this one
+pub(crate) fn strip_prefix_components(path: &str, prefix: &str, ignore_case: bool) -> Option<Vec<String>> {
+    let split = |s: &str| -> Vec<String> {
+        s.split(['/', '\\'])
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_string())
+            .collect()
+    };
+    let parts = split(path);
+    let prefix_parts = split(prefix);
+    if prefix_parts.is_empty() || prefix_parts.len() >= parts.len() {
+        return None;
+    }
+    let matches = parts
+        .iter()
+        .zip(prefix_parts.iter())
+        .all(|(a, b)| if ignore_case { a.to_lowercase() == b.to_lowercase() } else { a == b });
+    if matches {
+        Some(parts[prefix_parts.len()..].to_vec())
+    } else {
+        None
+    }
+}
+
+// For telling whether two paths name the same file
+fn path_key(path: &str) -> String {
+    let p = path.replace('\\', "/");
+    if cfg!(windows) {
+        p.to_lowercase()
+    } else {
+        p
+    }
+}
+
+// `folder_path` lists the folders from the scanned root down to the exe; keep the same
+// depth but take the names from the new location.
+fn rebuild_folder_path(old: &Option<Vec<String>>, new_exe: &Path) -> Option<Vec<String>> {
+    let depth = old.as_ref()?.len();
+    let dirs: Vec<String> = new_exe
+        .parent()?
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(n) => Some(n.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    let start = dirs.len().saturating_sub(depth);
+    Some(dirs[start..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::config::replace_games_db;
+    use crate::data_dir::TestDataDir;
+    use crate::models::GamesDB;
+
+    fn game(id: &str, path: &Path) -> GameEntry {
+        serde_json::from_value(serde_json::json!({ "id": id, "name": id, "path": path.to_string_lossy() })).unwrap()
+    }
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"MZ").unwrap();
+    }
+
+    fn paths() -> Vec<(String, String)> {
+        load_games_db().unwrap().into_inner().games.into_iter().map(|g| (g.id, g.path)).collect()
+    }
+
+    fn outcome(results: &[RelocationResult], id: &str) -> (bool, bool, Option<String>) {
+        let r = results.iter().find(|r| r.id == id).unwrap();
+        (r.exists, r.applied, r.conflict.clone())
+    }
+
+    #[test]
+    fn strips_prefixes_by_component() {
+        let parts = |v: &[&str]| Some(v.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(strip_path_prefix("D:\\Games\\Foo\\foo.exe", "D:/Games"), parts(&["Foo", "foo.exe"]));
+        assert_eq!(strip_path_prefix("/mnt/games/Foo/foo.exe", "/mnt/games/"), parts(&["Foo", "foo.exe"]));
+        // whole components only, and something has to be left
+        assert_eq!(strip_path_prefix("D:/GamesOld/foo.exe", "D:/Games"), None);
+        assert_eq!(strip_path_prefix("D:/Games", "D:/Games"), None);
+        assert_eq!(strip_path_prefix("D:/Games/foo.exe", ""), None);
+        let other_case = strip_path_prefix("D:/GAMES/foo.exe", "d:/games");
+        assert_eq!(other_case, if cfg!(windows) { parts(&["foo.exe"]) } else { None });
+    }
+
+    #[test]
+    fn relocate_by_prefix() {
+        let dir = TestDataDir::new("relocate-prefix");
+        let old = dir.root.join("old");
+        let new = dir.root.join("new");
+        touch(&new.join("A/a.exe"));
+        touch(&new.join("C/c.exe"));
+        let kept = dir.root.join("kept/c.exe");
+        touch(&kept);
+        let db = GamesDB {
+            games: vec![
+                game("a", &old.join("A/a.exe")),
+                game("b", &old.join("B/b.exe")), // not in the new location
+                game("c", &old.join("C/c.exe")), // its new path is taken by d
+                game("d", &new.join("C/c.exe")),
+                game("e", &kept),
+            ],
+            ..Default::default()
+        };
+        replace_games_db(&db).unwrap();
+        let old_s = old.to_string_lossy().to_string();
+        let new_s = new.to_string_lossy().to_string();
+
+        let before = paths();
+        let results = relocate_games(&old_s, &new_s, Some(true)).unwrap();
+        assert_eq!(paths(), before);
+        assert_eq!(outcome(&results, "a"), (true, false, None));
+        assert_eq!(outcome(&results, "b"), (false, false, None));
+        assert_eq!(outcome(&results, "c"), (true, false, Some("path_in_use".into())));
+        assert!(results.iter().all(|r| r.id != "e"));
+
+        let results = relocate_games(&old_s, &new_s, None).unwrap();
+        assert_eq!(outcome(&results, "a"), (true, true, None));
+        assert_eq!(outcome(&results, "c"), (true, false, Some("path_in_use".into())));
+        let after = paths();
+        assert_eq!(after[0].1, new.join("A").join("a.exe").to_string_lossy());
+        assert_eq!(after[1..], before[1..]);
+    }
+
+    #[test]
+    fn relocate_by_scan() {
+        let dir = TestDataDir::new("relocate-scan");
+        let scan = dir.root.join("scan");
+        touch(&scan.join("Alpha/game.exe"));
+        touch(&scan.join("Beta/game.exe"));
+        touch(&scan.join("Gamma/gamma.exe"));
+        touch(&scan.join("Delta/shared.exe"));
+        let gone = dir.root.join("gone");
+        let mut alpha = game("alpha", &gone.join("Alpha/game.exe"));
+        alpha.folder_path = Some(vec!["Alpha".into()]);
+        let db = GamesDB {
+            games: vec![
+                alpha,
+                game("gamma", &gone.join("Renamed/gamma.exe")),
+                // two missing games that only match by a file name found once
+                game("d1", &gone.join("D1/shared.exe")),
+                game("d2", &gone.join("D2/shared.exe")),
+                // game.exe exists twice and neither folder is "Other"
+                game("other", &gone.join("Other/game.exe")),
+            ],
+            ..Default::default()
+        };
+        replace_games_db(&db).unwrap();
+
+        let folder = Some(scan.to_string_lossy().to_string());
+        let results = relocate_games_by_scan(folder.clone(), Some(true)).unwrap();
+        assert!(results.iter().all(|r| !r.applied));
+        let results = relocate_games_by_scan(folder, None).unwrap();
+        let kinds: Vec<_> = results.iter().map(|r| (r.id.as_str(), r.match_kind.as_deref(), r.applied, r.conflict.as_deref())).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("alpha", Some("folder_and_exe"), true, None),
+                ("gamma", Some("exe_only"), true, None),
+                ("d1", Some("exe_only"), false, Some("same_target")),
+                ("d2", Some("exe_only"), false, Some("same_target")),
+                ("other", None, false, None),
+            ]
+        );
+        let games = load_games_db().unwrap().into_inner().games;
+        assert_eq!(games[0].path, scan.join("Alpha").join("game.exe").to_string_lossy());
+        assert_eq!(games[0].folder_path, Some(vec!["Alpha".to_string()]));
+        assert_eq!(games[1].path, scan.join("Gamma").join("gamma.exe").to_string_lossy());
+        assert_eq!(games[2].path, gone.join("D1/shared.exe").to_string_lossy());
+    }
+}
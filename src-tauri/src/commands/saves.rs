@@ -0,0 +1,67 @@
+use crate::error::KanoError;
+use crate::config::{load_config, load_games_db, save_games_db};
+use crate::data_dir;
+use crate::models::{GameEntry, GameKey, SaveSnapshot};
+use crate::saves;
+
+fn find_game(game_id: &str) -> Result<GameEntry, KanoError> {
+    load_games_db()?
+        .find(&GameKey::Id(game_id))
+        .cloned()
+        .ok_or(KanoError::not_found("game"))
+}
+
+// Set where a game keeps its saves; returns the directories as they expand on this machine
+#[tauri::command]
+pub fn set_game_save_dirs(game_id: &str, dirs: Vec<String>) -> Result<Vec<String>, KanoError> {
+    let dirs: Vec<String> = dirs
+        .into_iter()
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+        .collect();
+    let mut db = load_games_db()?;
+    let expanded = match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            for d in &dirs {
+                saves::check_save_dir(d, g)?;
+            }
+            g.save_dirs = dirs;
+            g.save_dirs
+                .iter()
+                .map(|d| saves::expand_save_path(d, g).to_string_lossy().to_string())
+                .collect()
+        }
+        None => return Err(KanoError::not_found("game")),
+    };
+    save_games_db(&db)?;
+    Ok(expanded)
+}
+
+// Take a snapshot of the game's save directories now
+#[tauri::command]
+pub async fn backup_game_saves(game_id: String) -> Result<SaveSnapshot, KanoError> {
+    let _writing = data_dir::begin_write()?;
+    let game = find_game(&game_id)?;
+    saves::create_snapshot(&game, "manual", load_config()?.save_backup.keep_last)
+}
+
+#[tauri::command]
+pub fn list_save_snapshots(game_id: &str) -> Result<Vec<SaveSnapshot>, KanoError> {
+    let game = find_game(game_id)?;
+    saves::list_snapshots(&game.id)
+}
+
+// Restore a snapshot over the current saves (the current state is snapshotted first)
+#[tauri::command]
+pub async fn restore_save_snapshot(game_id: String, snapshot_id: String) -> Result<(), KanoError> {
+    let _writing = data_dir::begin_write()?;
+    let game = find_game(&game_id)?;
+    saves::restore_snapshot(&game, &snapshot_id, load_config()?.save_backup.keep_last)
+}
+
+#[tauri::command]
+pub fn delete_save_snapshot(game_id: &str, snapshot_id: &str) -> Result<(), KanoError> {
+    let game = find_game(game_id)?;
+    let path = saves::snapshot_path(&game.id, snapshot_id)?;
+    Ok(std::fs::remove_file(path)?)
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use crate::error::KanoError;
+use crate::config::{load_games_db, load_sessions, save_games_db};
+use crate::db;
+use crate::models::{GameKey, PlaySession};
+
+/// Store a finished session
+pub(crate) fn record_session(session: PlaySession) -> Result<(), KanoError> {
+    db::upsert_session(&session)
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, KanoError> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| KanoError::invalid("invalid_time", format!("invalid time {}: {}", s, e)))
+}
+
+// Times may carry any offset, so they're compared as instants rather than as text;
+// unreadable ones sort before everything else
+fn instant(s: &str) -> Option<DateTime<Utc>> {
+    parse_time(s).ok()
+}
+
+// List recorded sessions, newest first; all games when `game_id` is not given
+#[tauri::command]
+pub fn list_sessions(game_id: Option<String>) -> Result<Vec<PlaySession>, KanoError> {
+    let mut sessions = load_sessions()?;
+    if let Some(gid) = game_id {
+        sessions.retain(|s| s.game_id == gid);
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(instant(&s.start)));
+    Ok(sessions)
+}
+
+// Fix a bogus session. When start/end change without an explicit duration,
+// the duration is recomputed from the new timestamps minus recorded idle time.
+#[tauri::command]
+pub fn update_session(id: &str, start: Option<String>, end: Option<String>, duration: Option<i64>) -> Result<PlaySession, KanoError> {
+    let mut sessions = load_sessions()?;
+    let session = sessions
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or(KanoError::not_found("session"))?;
+
+    let times_changed = start.is_some() || end.is_some();
+    if let Some(st) = start {
+        parse_time(&st)?;
+        session.start = st;
+    }
+    if let Some(en) = end {
+        parse_time(&en)?;
+        session.end = en;
+    }
+    match duration {
+        Some(d) => session.duration = d.max(0),
+        None if times_changed => {
+            let st = parse_time(&session.start)?;
+            let en = parse_time(&session.end)?;
+            if en < st {
+                return Err(KanoError::invalid("session_end_before_start", "session end is before start"));
+            }
+            session.duration = ((en - st).num_seconds() - session.idle_seconds).max(0);
+        }
+        None => {}
+    }
+
+    db::upsert_session(session)?;
+    Ok(session.clone())
+}
+
+#[tauri::command]
+pub fn delete_session(id: &str) -> Result<(), KanoError> {
+    if !db::remove_session(id)? {
+        return Err(KanoError::not_found("session"));
+    }
+    Ok(())
+}
+
+// Rebuild a game's cumulative playtime and last_played from its session history
+#[tauri::command]
+pub fn recompute_playtime(game_id: &str) -> Result<i64, KanoError> {
+    let sessions: Vec<PlaySession> = load_sessions()?
+        .into_iter()
+        .filter(|s| s.game_id == game_id)
+        .collect();
+    let total: i64 = sessions.iter().map(|s| s.duration).sum();
+    let latest_end = sessions.iter().max_by_key(|s| instant(&s.end)).map(|s| s.end.clone());
+
+    let mut db = load_games_db()?;
+    match db.find_mut(&GameKey::Id(game_id)) {
+        Some(g) => {
+            g.playtime = total;
+            if latest_end.is_some() {
+                g.last_played = latest_end;
+            }
+        }
+        None => return Err(KanoError::not_found("game")),
+    }
+    save_games_db(&db)?;
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::replace_games_db;
+    use crate::data_dir::TestDataDir;
+    use crate::models::GamesDB;
+
+    fn session(id: &str, game_id: &str, start: &str, end: &str, duration: i64) -> PlaySession {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "game_id": game_id, "start": start, "end": end, "duration": duration,
+        }))
+        .unwrap()
+    }
+
+    fn setup() -> TestDataDir {
+        let dir = TestDataDir::new("sessions");
+        let game = serde_json::from_value(serde_json::json!({ "id": "g", "name": "G", "path": "C:/G/g.exe" })).unwrap();
+        replace_games_db(&GamesDB { games: vec![game], ..Default::default() }).unwrap();
+        // as text "10:00+08:00" sorts after "05:00Z", but it is 02:00Z
+        record_session(session("east", "g", "2024-01-01T09:00:00+08:00", "2024-01-01T10:00:00+08:00", 3600)).unwrap();
+        record_session(session("utc", "g", "2024-01-01T05:00:00+00:00", "2024-01-01T05:30:00+00:00", 1800)).unwrap();
+        record_session(session("other", "h", "2024-01-02T00:00:00Z", "2024-01-02T01:00:00Z", 3600)).unwrap();
+        dir
+    }
+
+    fn ids(sessions: &[PlaySession]) -> Vec<&str> {
+        sessions.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn sessions_are_ordered_by_instant() {
+        let _dir = setup();
+        assert_eq!(ids(&list_sessions(Some("g".into())).unwrap()), ["utc", "east"]);
+        assert_eq!(ids(&list_sessions(None).unwrap()), ["other", "utc", "east"]);
+    }
+
+    #[test]
+    fn recompute_takes_the_latest_end_as_last_played() {
+        let _dir = setup();
+        assert_eq!(recompute_playtime("g").unwrap(), 5400);
+        let g = load_games_db().unwrap().games.remove(0);
+        assert_eq!((g.playtime, g.last_played.as_deref()), (5400, Some("2024-01-01T05:30:00+00:00")));
+        assert!(matches!(recompute_playtime("missing"), Err(KanoError::NotFound { .. })));
+    }
+
+    #[test]
+    fn update_recomputes_the_duration_from_new_times() {
+        let _dir = setup();
+        let s = update_session("utc", None, Some("2024-01-01T06:00:00+01:00".into()), None).unwrap();
+        // 05:00Z to 05:00Z
+        assert_eq!(s.duration, 0);
+        let s = update_session("utc", None, Some("2024-01-01T08:00:00+01:00".into()), None).unwrap();
+        assert_eq!(s.duration, 7200);
+        let s = update_session("utc", None, None, Some(-5)).unwrap();
+        assert_eq!(s.duration, 0);
+
+        let err = |r: Result<PlaySession, KanoError>| match r {
+            Err(KanoError::InvalidInput { reason, .. }) => reason,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(err(update_session("utc", Some("yesterday".into()), None, None)), "invalid_time");
+        assert_eq!(err(update_session("utc", Some("2024-01-01T09:00:00Z".into()), None, None)), "session_end_before_start");
+        assert!(matches!(update_session("missing", None, None, Some(1)), Err(KanoError::NotFound { .. })));
+        // a refused change isn't stored
+        assert_eq!(list_sessions(Some("g".into())).unwrap()[0].start, "2024-01-01T05:00:00+00:00");
+    }
+
+    #[test]
+    fn delete_removes_one_session() {
+        let _dir = setup();
+        delete_session("east").unwrap();
+        assert_eq!(ids(&list_sessions(None).unwrap()), ["other", "utc"]);
+        assert!(matches!(delete_session("east"), Err(KanoError::NotFound { .. })));
+    }
+}
@@ -1,9 +1,11 @@
-use crate::config::{load_tools_file, save_tools_file, load_token, save_token};
-use crate::models::ToolEntry;
+use crate::error::KanoError;
+use crate::config::{load_tools_file, save_tools_file, load_token, save_token, load_config, save_config, load_games_db, save_games_db};
+use crate::idle;
+use crate::models::{IdlePolicy, KeywordWeight, MetadataSettings, RunnerEntry, SaveBackupPolicy, ToolEntry};
 use uuid::Uuid;
 
 #[tauri::command]
-pub fn set_access_token(token: &str) -> Result<(), String> {
+pub fn set_access_token(token: &str) -> Result<(), KanoError> {
     if token.is_empty() {
         // clear token file by writing empty string
         return save_token("");
@@ -12,18 +14,141 @@ pub fn set_access_token(token: &str) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn get_access_token() -> Result<Option<String>, String> {
+pub fn get_access_token() -> Result<Option<String>, KanoError> {
     Ok(load_token())
 }
 
+// Keyword weights used when ranking executables during folder scans
 #[tauri::command]
-pub fn get_tools() -> Result<Vec<ToolEntry>, String> {
-    Ok(load_tools_file())
+pub fn get_exe_keyword_weights() -> Result<Vec<KeywordWeight>, KanoError> {
+    Ok(load_config()?.exe_keyword_weights)
 }
 
 #[tauri::command]
-pub fn add_tool(name: &str, path: &str) -> Result<ToolEntry, String> {
-    let mut tools = load_tools_file();
+pub fn set_exe_keyword_weights(weights: Vec<KeywordWeight>) -> Result<(), KanoError> {
+    let mut cfg = load_config()?;
+    cfg.exe_keyword_weights = weights
+        .into_iter()
+        .filter(|w| !w.keyword.trim().is_empty())
+        .collect();
+    save_config(&cfg)
+}
+
+// Idle detection thresholds used by the playtime tracker
+#[tauri::command]
+pub fn get_idle_policy() -> Result<IdlePolicy, KanoError> {
+    Ok(load_config()?.idle_policy)
+}
+
+#[tauri::command]
+pub fn set_idle_policy(policy: IdlePolicy) -> Result<(), KanoError> {
+    // 0 minutes would count every sample as idle; treat it as disabled
+    let policy = IdlePolicy {
+        unfocused_minutes: policy.unfocused_minutes.filter(|m| *m > 0),
+        no_input_minutes: policy.no_input_minutes.filter(|m| *m > 0),
+    };
+    if !idle::SUPPORTED && (policy.unfocused_minutes.is_some() || policy.no_input_minutes.is_some()) {
+        return Err(KanoError::invalid("idle_detection_unsupported", "idle detection is only supported on Windows"));
+    }
+    let mut cfg = load_config()?;
+    cfg.idle_policy = policy;
+    save_config(&cfg)
+}
+
+// Automatic save backups and how many snapshots to keep per game
+#[tauri::command]
+pub fn get_save_backup_policy() -> Result<SaveBackupPolicy, KanoError> {
+    Ok(load_config()?.save_backup)
+}
+
+#[tauri::command]
+pub fn set_save_backup_policy(policy: SaveBackupPolicy) -> Result<(), KanoError> {
+    let mut cfg = load_config()?;
+    cfg.save_backup = policy;
+    save_config(&cfg)
+}
+
+// Metadata sources (Bangumi / VNDB API addresses)
+#[tauri::command]
+pub fn get_metadata_settings() -> Result<MetadataSettings, KanoError> {
+    Ok(load_config()?.metadata)
+}
+
+#[tauri::command]
+pub fn set_metadata_settings(settings: MetadataSettings) -> Result<(), KanoError> {
+    for url in [&settings.bangumi_api_url, &settings.vndb_api_url] {
+        let url = url.trim();
+        if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(KanoError::invalid("api_url", "API url must start with http:// or https://"));
+        }
+    }
+    let mut cfg = load_config()?;
+    cfg.metadata = settings;
+    save_config(&cfg)
+}
+
+// Runners wrap game launches (Locale Emulator, Wine/Proton, ...)
+#[tauri::command]
+pub fn get_runners() -> Result<Vec<RunnerEntry>, KanoError> {
+    Ok(load_config()?.runners)
+}
+
+#[tauri::command]
+pub fn add_runner(name: &str, command: &str) -> Result<RunnerEntry, KanoError> {
+    if command.trim().is_empty() {
+        return Err(KanoError::invalid("runner_command_empty", "runner command is empty"));
+    }
+    let mut cfg = load_config()?;
+    let entry = RunnerEntry { id: Uuid::new_v4().to_string(), name: name.to_string(), command: command.to_string() };
+    cfg.runners.push(entry.clone());
+    save_config(&cfg)?;
+    Ok(entry)
+}
+
+#[tauri::command]
+pub fn update_runner(id: &str, name: &str, command: &str) -> Result<RunnerEntry, KanoError> {
+    if command.trim().is_empty() {
+        return Err(KanoError::invalid("runner_command_empty", "runner command is empty"));
+    }
+    let mut cfg = load_config()?;
+    let updated = match cfg.runners.iter_mut().find(|r| r.id == id) {
+        Some(r) => {
+            r.name = name.to_string();
+            r.command = command.to_string();
+            r.clone()
+        }
+        None => return Err(KanoError::not_found("runner")),
+    };
+    save_config(&cfg)?;
+    Ok(updated)
+}
+
+// Removing a runner also unassigns it from games so they launch directly again
+#[tauri::command]
+pub fn remove_runner(id: &str) -> Result<(), KanoError> {
+    let mut cfg = load_config()?;
+    cfg.runners.retain(|r| r.id != id);
+    save_config(&cfg)?;
+    let mut db = load_games_db()?;
+    let mut changed = false;
+    for g in db.games.iter_mut().filter(|g| g.runner_id.as_deref() == Some(id)) {
+        g.runner_id = None;
+        changed = true;
+    }
+    if changed {
+        save_games_db(&db)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_tools() -> Result<Vec<ToolEntry>, KanoError> {
+    Ok(load_tools_file()?)
+}
+
+#[tauri::command]
+pub fn add_tool(name: &str, path: &str) -> Result<ToolEntry, KanoError> {
+    let mut tools = load_tools_file()?;
     let id = Uuid::new_v4().to_string();
     let entry = ToolEntry { id: id.clone(), name: name.to_string(), path: path.to_string() };
     tools.push(entry.clone());
@@ -32,46 +157,43 @@ pub fn add_tool(name: &str, path: &str) -> Result<ToolEntry, String> {
 }
 
 #[tauri::command]
-pub fn remove_tool(id: &str) -> Result<(), String> {
-    let mut tools = load_tools_file();
+pub fn remove_tool(id: &str) -> Result<(), KanoError> {
+    let mut tools = load_tools_file()?;
     tools.retain(|t| t.id != id);
     save_tools_file(&tools)?;
     Ok(())
 }
 
 #[tauri::command]
-pub fn launch_tool(id: &str) -> Result<(), String> {
-    let tools = load_tools_file();
+pub fn launch_tool(id: &str) -> Result<(), KanoError> {
+    let tools = load_tools_file()?;
     if let Some(tool) = tools.iter().find(|t| t.id == id) {
         let mut cmd = std::process::Command::new(&tool.path);
         if let Some(parent) = std::path::Path::new(&tool.path).parent() {
             cmd.current_dir(parent);
         }
-        cmd.spawn().map_err(|e| e.to_string())?;
+        cmd.spawn()?;
         Ok(())
     } else {
-        Err("tool not found".into())
+        Err(KanoError::not_found("tool"))
     }
 }
 
-// Get the project root directory (parent of src-tauri)
+// Get the project root directory (the executable's folder). Data files are in
+// `get_data_dir`, which is only the `kano_data` folder here in portable mode.
 #[tauri::command]
-pub fn get_project_root() -> Result<String, String> {
-    // Use the app-local project root (prefer executable parent). This keeps
-    // behavior consistent with functions in `config.rs` such as
-    // `games_db_path()` / `images_dir_path()` which use `app_base_dir()`.
+pub fn get_project_root() -> Result<String, KanoError> {
     let root = crate::config::project_root();
     Ok(root.to_string_lossy().to_string())
 }
 
 // Test network connectivity to bgm.tv and return latency (async to avoid blocking runtime)
 #[tauri::command]
-pub async fn test_network_connection() -> Result<serde_json::Value, String> {
+pub async fn test_network_connection() -> Result<serde_json::Value, KanoError> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(5))
         .user_agent("testGalManager/1.0")
-        .build()
-        .map_err(|e| e.to_string())?;
+        .build()?;
 
     // 获取 access token
     let token = load_token();
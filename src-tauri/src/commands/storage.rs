@@ -0,0 +1,137 @@
+use std::fs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::State;
+use crate::error::KanoError;
+use crate::auto_match::review_store;
+use crate::bangumi_cache;
+use crate::config::{config_store, kano_db_path, token_store};
+use crate::data_dir::{self, DataDirInfo};
+use crate::db;
+use crate::state::RunningProcesses;
+use crate::store::{self, Store};
+use crate::sync::state_store;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StorageFileHealth {
+    pub name: String,               // "config" | "tokens" | "bangumi_cache" | "sync_state" | "match_reviews" | "database"
+    pub path: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub quarantined: Option<String>, // where the unreadable file was moved
+    pub blocked: bool,               // writes are refused until restored or dismissed
+    pub backup: Option<String>,      // newest good backup, what restore would use
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StorageHealth {
+    pub ok: bool,
+    pub files: Vec<StorageFileHealth>,
+    // every `*.corrupt-*` file in kano_data, including ones from earlier runs
+    pub quarantined_files: Vec<String>,
+}
+
+fn store_health<T: Serialize + DeserializeOwned + Default>(name: &str, store: Store<T>) -> StorageFileHealth {
+    let error = store.load().err().map(|e| e.to_string());
+    file_health(name, store.path(), error, store.latest_backup())
+}
+
+fn database_health() -> StorageFileHealth {
+    // the integrity check catches a damaged file, the loads catch records that don't parse
+    let res = db::check_integrity()
+        .and_then(|_| db::load_games_db().map(|_| ()))
+        .and_then(|_| db::load_notes().map(|_| ()))
+        .and_then(|_| db::load_tools_file().map(|_| ()))
+        .and_then(|_| db::load_sessions().map(|_| ()));
+    file_health("database", &kano_db_path(), res.err().map(|e| e.to_string()), db::latest_backup())
+}
+
+fn file_health(name: &str, path: &std::path::Path, error: Option<String>, backup: Option<std::path::PathBuf>) -> StorageFileHealth {
+    let issue = store::issue_for(path);
+    StorageFileHealth {
+        name: name.to_string(),
+        path: path.display().to_string(),
+        ok: error.is_none() && issue.is_none(),
+        error: error.or_else(|| issue.as_ref().map(|i| i.error.clone())),
+        quarantined: issue.as_ref().and_then(|i| i.quarantined.clone()),
+        blocked: issue.is_some(),
+        backup: backup.map(|b| b.display().to_string()),
+    }
+}
+
+// Check every data file; files that fail are quarantined as a side effect
+#[tauri::command]
+pub fn get_storage_health() -> Result<StorageHealth, KanoError> {
+    let files = vec![
+        store_health("config", config_store()),
+        store_health("tokens", token_store()),
+        store_health("bangumi_cache", bangumi_cache::store()),
+        store_health("sync_state", state_store()),
+        store_health("match_reviews", review_store()),
+        database_health(),
+    ];
+    let mut quarantined_files = Vec::new();
+    if let Some(dir) = kano_db_path().parent() {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().contains(".corrupt-") {
+                    quarantined_files.push(entry.path().display().to_string());
+                }
+            }
+        }
+    }
+    quarantined_files.sort();
+    Ok(StorageHealth { ok: files.iter().all(|f| f.ok), files, quarantined_files })
+}
+
+// Put the newest good backup of a file back in place; returns the backup used
+#[tauri::command]
+pub fn restore_storage_backup(name: &str) -> Result<String, KanoError> {
+    let backup = match name {
+        "config" => config_store().restore_latest_backup()?,
+        "tokens" => token_store().restore_latest_backup()?,
+        "bangumi_cache" => {
+            let backup = bangumi_cache::store().restore_latest_backup()?;
+            bangumi_cache::reload();
+            backup
+        }
+        "sync_state" => state_store().restore_latest_backup()?,
+        "match_reviews" => review_store().restore_latest_backup()?,
+        "database" => db::restore_latest_backup()?,
+        _ => return Err(KanoError::invalid("unknown_storage_file", format!("unknown storage file: {}", name))),
+    };
+    Ok(backup.display().to_string())
+}
+
+// Accept starting over: allow writing the file again. The quarantined copy is kept.
+#[tauri::command]
+pub fn dismiss_storage_issue(name: &str) -> Result<(), KanoError> {
+    let path = match name {
+        "config" => config_store().path().to_path_buf(),
+        "tokens" => token_store().path().to_path_buf(),
+        "bangumi_cache" => bangumi_cache::store().path().to_path_buf(),
+        "sync_state" => state_store().path().to_path_buf(),
+        "match_reviews" => review_store().path().to_path_buf(),
+        "database" => kano_db_path(),
+        _ => return Err(KanoError::invalid("unknown_storage_file", format!("unknown storage file: {}", name))),
+    };
+    store::dismiss_issue(&path);
+    Ok(())
+}
+
+// Where kano_data is and how that was decided (flag, env, portable or installed)
+#[tauri::command]
+pub fn get_data_dir() -> Result<DataDirInfo, KanoError> {
+    Ok(data_dir::info())
+}
+
+// Move kano_data with everything in it; used from the next access on and after restarts.
+// Refused while a sync, import, auto-match or any other write is in progress.
+#[tauri::command]
+pub fn move_data_dir(target: String, state: State<'_, RunningProcesses>) -> Result<DataDirInfo, KanoError> {
+    // a running game's monitor would write its session into the old location
+    if !state.processes.lock().unwrap_or_else(|e| e.into_inner()).is_empty() {
+        return Err(KanoError::Busy { reason: "games_running", message: "close running games before moving the data directory".into() });
+    }
+    data_dir::move_to(std::path::Path::new(target.trim()))
+}
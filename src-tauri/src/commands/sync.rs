@@ -0,0 +1,61 @@
+use uuid::Uuid;
+use crate::error::KanoError;
+use crate::config::{load_config, save_config};
+use crate::data_dir;
+use crate::models::{PathMapping, SyncBackendConfig, SyncSettings};
+use crate::sync::{self, SyncReport};
+
+// Sync settings, with this machine's id created on first use
+#[tauri::command]
+pub fn get_sync_settings() -> Result<SyncSettings, KanoError> {
+    let mut cfg = load_config()?;
+    if cfg.sync.machine_id.is_empty() {
+        cfg.sync.machine_id = Uuid::new_v4().to_string();
+        save_config(&cfg)?;
+    }
+    Ok(cfg.sync)
+}
+
+// Choose the sync target (None turns sync off) and how other machines' paths map to this one
+#[tauri::command]
+pub fn set_sync_settings(backend: Option<SyncBackendConfig>, machine_name: Option<String>, path_mappings: Vec<PathMapping>) -> Result<SyncSettings, KanoError> {
+    match &backend {
+        Some(SyncBackendConfig::Folder { path }) if !std::path::Path::new(path).is_dir() => {
+            return Err(KanoError::not_found("folder"));
+        }
+        Some(SyncBackendConfig::Webdav { url, .. }) if !(url.starts_with("http://") || url.starts_with("https://")) => {
+            return Err(KanoError::invalid("webdav_url", "WebDAV url must start with http:// or https://"));
+        }
+        _ => {}
+    }
+    let mut cfg = load_config()?;
+    if cfg.sync.machine_id.is_empty() {
+        cfg.sync.machine_id = Uuid::new_v4().to_string();
+    }
+    cfg.sync.backend = backend;
+    if let Some(name) = machine_name {
+        cfg.sync.machine_name = name.trim().to_string();
+    }
+    cfg.sync.path_mappings = path_mappings
+        .into_iter()
+        .filter(|m| !m.from.trim().is_empty() && !m.to.trim().is_empty())
+        .collect();
+    save_config(&cfg)?;
+    Ok(cfg.sync)
+}
+
+// Push and pull the library (games, tags, notes, sessions) to the configured backend
+#[tauri::command]
+pub async fn sync_now() -> Result<SyncReport, KanoError> {
+    // the data directory must not move between reading and writing the library
+    let _writing = data_dir::begin_write()?;
+    let settings = get_sync_settings()?;
+    // the WebDAV backend uses blocking HTTP, keep it off the async runtime
+    let report = tauri::async_runtime::spawn_blocking(move || sync::run_sync(&settings))
+        .await
+        .map_err(|e| KanoError::Other { message: format!("background task failed: {e}") })??;
+    let mut cfg = load_config()?;
+    cfg.sync.last_sync = Some(report.synced_at.clone());
+    save_config(&cfg)?;
+    Ok(report)
+}
@@ -1,9 +1,11 @@
+use crate::error::KanoError;
 use crate::config::{load_games_db, save_games_db};
+use crate::models::GameKey;
 
 // 获取所有可用标签
 #[tauri::command]
-pub fn get_all_tags() -> Result<Vec<String>, String> {
-    let mut db = load_games_db();
+pub fn get_all_tags() -> Result<Vec<String>, KanoError> {
+    let mut db = load_games_db()?;
     
     // 如果数据库中没有标签，初始化默认标签
     if db.custom_tags.is_empty() {
@@ -20,16 +22,16 @@ pub fn get_all_tags() -> Result<Vec<String>, String> {
 
 // 添加自定义标签
 #[tauri::command]
-pub fn add_custom_tag(tag: &str) -> Result<(), String> {
+pub fn add_custom_tag(tag: &str) -> Result<(), KanoError> {
     if tag.trim().is_empty() {
-        return Err("标签名称不能为空".to_string());
+        return Err(KanoError::invalid("tag_name_empty", "标签名称不能为空"));
     }
     
-    let mut db = load_games_db();
+    let mut db = load_games_db()?;
     
     // 检查是否已存在
     if db.custom_tags.contains(&tag.to_string()) {
-        return Err("标签已存在".to_string());
+        return Err(KanoError::invalid("tag_exists", "标签已存在"));
     }
     
     db.custom_tags.push(tag.to_string());
@@ -39,8 +41,8 @@ pub fn add_custom_tag(tag: &str) -> Result<(), String> {
 
 // 删除标签（包括默认标签和自定义标签）
 #[tauri::command]
-pub fn remove_custom_tag(tag: &str) -> Result<(), String> {
-    let mut db = load_games_db();
+pub fn remove_custom_tag(tag: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
     
     // 从自定义标签列表中移除（如果存在）
     db.custom_tags.retain(|t| t != tag);
@@ -56,43 +58,58 @@ pub fn remove_custom_tag(tag: &str) -> Result<(), String> {
 
 // 为游戏添加标签
 #[tauri::command]
-pub fn add_tag_to_game(path: &str, tag: &str) -> Result<(), String> {
-    let mut db = load_games_db();
+pub fn add_tag_to_game(path: &str, tag: &str) -> Result<(), KanoError> {
+    add_tag(GameKey::Path(path), tag)
+}
+
+#[tauri::command]
+pub fn add_tag_to_game_by_id(id: &str, tag: &str) -> Result<(), KanoError> {
+    add_tag(GameKey::Id(id), tag)
+}
+
+fn add_tag(key: GameKey, tag: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
     
-    for game in db.games.iter_mut() {
-        if game.path == path {
+    match db.find_mut(&key) {
+        Some(game) => {
             if !game.tags.contains(&tag.to_string()) {
                 game.tags.push(tag.to_string());
                 save_games_db(&db)?;
-                return Ok(());
             }
-            return Ok(());
+            Ok(())
         }
+        None => Err(KanoError::not_found("game")),
     }
-    
-    Err("游戏不存在".to_string())
 }
 
 // 从游戏移除标签
 #[tauri::command]
-pub fn remove_tag_from_game(path: &str, tag: &str) -> Result<(), String> {
-    let mut db = load_games_db();
+pub fn remove_tag_from_game(path: &str, tag: &str) -> Result<(), KanoError> {
+    remove_tag(GameKey::Path(path), tag)
+}
+
+#[tauri::command]
+pub fn remove_tag_from_game_by_id(id: &str, tag: &str) -> Result<(), KanoError> {
+    remove_tag(GameKey::Id(id), tag)
+}
+
+fn remove_tag(key: GameKey, tag: &str) -> Result<(), KanoError> {
+    let mut db = load_games_db()?;
     
-    for game in db.games.iter_mut() {
-        if game.path == path {
+    match db.find_mut(&key) {
+        Some(game) => {
             game.tags.retain(|t| t != tag);
             save_games_db(&db)?;
-            return Ok(());
+            Ok(())
         }
+        None => Err(KanoError::not_found("game")),
     }
-    
-    Err("游戏不存在".to_string())
 }
 
 // 获取指定标签的游戏数量
 #[tauri::command]
-pub fn get_games_count_by_tag(tag: &str) -> Result<usize, String> {
-    let db = load_games_db();
+pub fn get_games_count_by_tag(tag: &str) -> Result<usize, KanoError> {
+    let db = load_games_db()?;
     let count = db.games.iter()
         .filter(|game| game.tags.contains(&tag.to_string()))
         .count();
@@ -0,0 +1,460 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use serde::Serialize;
+use crate::error::KanoError;
+
+// Where kano_data lives, in order of precedence:
+// 1. `--data-dir <path>` on the command line
+// 2. the KANO_DATA_DIR environment variable
+// 3. portable mode: a `kano_portable` marker next to the exe (or a kano_data folder there
+//    from before this option existed) keeps everything beside the exe
+// 4. installed mode: kano_data in the OS app-data dir from Tauri, or wherever it was moved to
+
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+pub const DATA_DIR_ENV: &str = "KANO_DATA_DIR";
+/// Empty, or a path (relative to the exe dir) to the data directory
+pub const PORTABLE_MARKER: &str = "kano_portable";
+// installed mode only: records a data dir moved away from the default
+const LOCATION_FILE: &str = "data_dir.txt";
+
+// What Kano keeps in the data directory. Only these are moved (and removed afterwards):
+// in portable mode the data directory can be a folder that holds other things too.
+const OWNED_DIRS: [&str; 5] = ["images", "saves", "backups", "images.importing", "images.replaced"];
+// files named `<stem>.json`, plus their backups (`.json.1`), quarantined copies
+// (`.corrupt-*`), locks and leftovers of interrupted writes
+const OWNED_STEMS: [&str; 10] = [
+    "games_db", "tauri_config", "bangumi_cache", "tools", "notes", "sessions",
+    "sync_state", "match_reviews", "tokens", "kano",
+];
+// written by the startup migrations (migrations.rs); without them the moved data would be migrated again
+const OWNED_FILES: [&str; 2] = [crate::migrations::SCHEMA_VERSION_FILE, crate::migrations::LOG_FILE];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    Flag,
+    Env,
+    Portable,
+    Installed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DataDirInfo {
+    pub path: String,
+    pub source: DataDirSource,
+}
+
+struct Resolved {
+    path: PathBuf,
+    source: DataDirSource,
+    config_dir: Option<PathBuf>, // Tauri's app config dir, for LOCATION_FILE
+}
+
+static DATA_DIR: RwLock<Option<Resolved>> = RwLock::new(None);
+
+/// The directory holding the executable
+pub fn exe_dir() -> PathBuf {
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(parent) = exe.parent() {
+            return parent.to_path_buf();
+        }
+    }
+    // Fallback to parent(current_dir()) as before
+    let cwd = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir());
+    match cwd.parent() {
+        Some(parent) => parent.to_path_buf(),
+        None => cwd,
+    }
+}
+
+/// Resolve the data directory once at startup. `app_data` / `app_config` come from Tauri's
+/// path API and are only used in installed mode.
+pub fn init(app_data: Option<PathBuf>, app_config: Option<PathBuf>) -> DataDirInfo {
+    let args: Vec<String> = std::env::args().collect();
+    let env = std::env::var(DATA_DIR_ENV).ok();
+    let (path, source) = resolve(&args, env.as_deref(), &exe_dir(), app_data.as_deref(), app_config.as_deref());
+    eprintln!("data directory: {} ({:?})", path.display(), source);
+    let info = DataDirInfo { path: path.display().to_string(), source };
+    if let Ok(mut d) = DATA_DIR.write() {
+        *d = Some(Resolved { path, source, config_dir: app_config });
+    }
+    info
+}
+
+/// Pick the data directory from the command line, environment and files on disk
+fn resolve(
+    args: &[String],
+    env: Option<&str>,
+    exe_dir: &Path,
+    app_data: Option<&Path>,
+    app_config: Option<&Path>,
+) -> (PathBuf, DataDirSource) {
+    if let Some(p) = flag_value(args) {
+        return (PathBuf::from(p), DataDirSource::Flag);
+    }
+    if let Some(p) = env.map(str::trim).filter(|p| !p.is_empty()) {
+        return (PathBuf::from(p), DataDirSource::Env);
+    }
+    let legacy = exe_dir.join("kano_data");
+    match fs::read_to_string(exe_dir.join(PORTABLE_MARKER)) {
+        Ok(content) => {
+            let p = content.trim();
+            let path = if p.is_empty() { legacy } else { exe_dir.join(p) };
+            return (path, DataDirSource::Portable);
+        }
+        Err(_) if legacy.is_dir() => return (legacy, DataDirSource::Portable),
+        Err(_) => {}
+    }
+    if let Some(moved) = app_config
+        .and_then(|c| fs::read_to_string(c.join(LOCATION_FILE)).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    {
+        return (PathBuf::from(moved), DataDirSource::Installed);
+    }
+    match app_data {
+        Some(dir) => (dir.join("kano_data"), DataDirSource::Installed),
+        // no app-data dir on this platform: behave as before
+        None => (legacy, DataDirSource::Portable),
+    }
+}
+
+fn flag_value(args: &[String]) -> Option<String> {
+    let prefix = format!("{}=", DATA_DIR_FLAG);
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        if a == DATA_DIR_FLAG {
+            return it.next().cloned();
+        }
+        if let Some(v) = a.strip_prefix(&prefix) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+/// The resolved data directory (kano_data)
+pub fn data_dir() -> PathBuf {
+    if let Ok(d) = DATA_DIR.read() {
+        if let Some(r) = d.as_ref() {
+            return r.path.clone();
+        }
+    }
+    // not initialized (e.g. before setup): resolve without Tauri's app dirs
+    let args: Vec<String> = std::env::args().collect();
+    let env = std::env::var(DATA_DIR_ENV).ok();
+    resolve(&args, env.as_deref(), &exe_dir(), None, None).0
+}
+
+pub fn info() -> DataDirInfo {
+    let resolved = DATA_DIR.read().ok().and_then(|d| d.as_ref().map(|r| (r.path.clone(), r.source)));
+    let (path, source) = resolved.unwrap_or_else(|| (data_dir(), DataDirSource::Portable));
+    DataDirInfo { path: path.display().to_string(), source }
+}
+
+struct Writes {
+    active: usize,
+    moving: bool,
+}
+
+static WRITES: Mutex<Writes> = Mutex::new(Writes { active: 0, moving: false });
+
+/// Held while writing to the data directory; it can't be moved until every guard is dropped
+pub struct WriteGuard(());
+
+/// Register a write. Refused while the data directory is being moved.
+pub fn begin_write() -> Result<WriteGuard, KanoError> {
+    let mut w = WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    if w.moving {
+        return Err(KanoError::Busy { reason: "data_dir_moving", message: "the data directory is being moved".into() });
+    }
+    w.active += 1;
+    Ok(WriteGuard(()))
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        let mut w = WRITES.lock().unwrap_or_else(|e| e.into_inner());
+        w.active -= 1;
+    }
+}
+
+// The other side of `begin_write`: fails while anything is writing, and blocks new
+// writes until dropped
+struct MoveGuard(());
+
+fn begin_move() -> Result<MoveGuard, KanoError> {
+    let mut w = WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    if w.active > 0 || w.moving {
+        return Err(KanoError::Busy { reason: "data_dir_in_use", message: "wait for running syncs, imports and downloads to finish".into() });
+    }
+    w.moving = true;
+    Ok(MoveGuard(()))
+}
+
+impl Drop for MoveGuard {
+    fn drop(&mut self) {
+        WRITES.lock().unwrap_or_else(|e| e.into_inner()).moving = false;
+    }
+}
+
+fn is_owned(name: &str, is_dir: bool) -> bool {
+    if is_dir {
+        return OWNED_DIRS.contains(&name);
+    }
+    if OWNED_FILES.contains(&name) {
+        return true;
+    }
+    OWNED_STEMS.iter().any(|stem| {
+        name.strip_prefix(stem).is_some_and(|rest| {
+            [".json", ".db", ".corrupt-", ".lock", ".tmp."].iter().any(|s| rest.starts_with(s))
+        })
+    })
+}
+
+/// Copy Kano's files to `target`, switch to it and remove them from the old location.
+/// Writes are refused for the duration; the old folder itself is only removed if nothing
+/// else is left in it.
+pub fn move_to(target: &Path) -> Result<DataDirInfo, KanoError> {
+    let _moving = begin_move()?;
+    let (current, source, config_dir) = {
+        let d = DATA_DIR.read().unwrap_or_else(|e| e.into_inner());
+        let r = d.as_ref().ok_or(KanoError::not_found("data_dir"))?;
+        (r.path.clone(), r.source, r.config_dir.clone())
+    };
+    match source {
+        DataDirSource::Flag => {
+            return Err(KanoError::invalid("data_dir_from_flag", format!("the data directory is set with {}; change it there", DATA_DIR_FLAG)))
+        }
+        DataDirSource::Env => {
+            return Err(KanoError::invalid("data_dir_from_env", format!("the data directory is set with {}; change it there", DATA_DIR_ENV)))
+        }
+        _ => {}
+    }
+    if !target.is_absolute() {
+        return Err(KanoError::invalid("path_not_absolute", "target must be an absolute path"));
+    }
+    if target.exists() && fs::read_dir(target).map_err(|e| KanoError::io("read target", e))?.next().is_some() {
+        return Err(KanoError::invalid("folder_not_empty", "target folder is not empty"));
+    }
+    let current_abs = fs::canonicalize(&current).unwrap_or_else(|_| current.clone());
+    let target_abs = match target.parent().and_then(|p| fs::canonicalize(p).ok()) {
+        Some(p) => p.join(target.file_name().unwrap_or_default()),
+        None => target.to_path_buf(),
+    };
+    if target_abs.starts_with(&current_abs) || current_abs.starts_with(&target_abs) {
+        return Err(KanoError::invalid("nested_data_dir", "target can't be inside the current data directory or contain it"));
+    }
+
+    // fold the WAL into kano.db so the copy is a single consistent file
+    crate::db::checkpoint()?;
+    fs::create_dir_all(target).map_err(|e| KanoError::io(&format!("create {}", target.display()), e))?;
+    let copied = match copy_owned(&current, target) {
+        Ok(c) => c,
+        Err(e) => {
+            let _ = fs::remove_dir_all(target);
+            return Err(KanoError::io("copy failed, nothing was moved", e));
+        }
+    };
+
+    // remember the new location where the next start will look for it
+    match source {
+        DataDirSource::Portable => {
+            fs::write(exe_dir().join(PORTABLE_MARKER), target.display().to_string())
+                .map_err(|e| KanoError::io(&format!("write {}", PORTABLE_MARKER), e))?;
+        }
+        _ => {
+            let dir = config_dir.ok_or(KanoError::not_found("config_dir"))?;
+            fs::create_dir_all(&dir).map_err(|e| KanoError::io(&format!("create {}", dir.display()), e))?;
+            fs::write(dir.join(LOCATION_FILE), target.display().to_string())
+                .map_err(|e| KanoError::io(&format!("write {}", LOCATION_FILE), e))?;
+        }
+    }
+    if let Ok(mut d) = DATA_DIR.write() {
+        if let Some(r) = d.as_mut() {
+            r.path = target.to_path_buf();
+        }
+    }
+    for entry in &copied {
+        let res = if entry.is_dir() { fs::remove_dir_all(entry) } else { fs::remove_file(entry) };
+        if let Err(e) = res {
+            eprintln!("moved data to {}, but removing {} failed: {}", target.display(), entry.display(), e);
+        }
+    }
+    // only succeeds when the folder is empty now
+    let _ = fs::remove_dir(&current);
+    Ok(info())
+}
+
+// Copy Kano's entries at the top of `from`; returns the copied source paths
+fn copy_owned(from: &Path, to: &Path) -> Result<Vec<PathBuf>, KanoError> {
+    let mut copied = Vec::new();
+    let read = format!("read {}", from.display());
+    for entry in fs::read_dir(from).map_err(|e| KanoError::io(&read, e))? {
+        let entry = entry.map_err(|e| KanoError::io(&read, e))?;
+        let is_dir = entry.file_type().map_err(|e| KanoError::io(&read, e))?.is_dir();
+        if !is_owned(&entry.file_name().to_string_lossy(), is_dir) {
+            continue;
+        }
+        let src = entry.path();
+        let dst = to.join(entry.file_name());
+        if is_dir {
+            fs::create_dir_all(&dst).map_err(|e| KanoError::io(&format!("create {}", dst.display()), e))?;
+            copy_dir(&src, &dst)?;
+        } else if src.extension().is_some_and(|e| e == "lock") {
+            continue;
+        } else {
+            copy_file(&src, &dst)?;
+        }
+        copied.push(src);
+    }
+    Ok(copied)
+}
+
+// Lock files belong to writers of the old location and are left behind
+fn copy_dir(from: &Path, to: &Path) -> Result<(), KanoError> {
+    let read = format!("read {}", from.display());
+    for entry in fs::read_dir(from).map_err(|e| KanoError::io(&read, e))? {
+        let entry = entry.map_err(|e| KanoError::io(&read, e))?;
+        let src = entry.path();
+        let dst = to.join(entry.file_name());
+        let ft = entry.file_type().map_err(|e| KanoError::io(&read, e))?;
+        if ft.is_dir() {
+            fs::create_dir_all(&dst).map_err(|e| KanoError::io(&format!("create {}", dst.display()), e))?;
+            copy_dir(&src, &dst)?;
+        } else if src.extension().is_some_and(|e| e == "lock") {
+            continue;
+        } else {
+            copy_file(&src, &dst)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(src: &Path, dst: &Path) -> Result<(), KanoError> {
+    let context = format!("copy {}", src.display());
+    let len = fs::copy(src, dst).map_err(|e| KanoError::io(&context, e))?;
+    let expected = fs::metadata(src).map(|m| m.len()).unwrap_or(len);
+    if len != expected {
+        return Err(KanoError::io(&context, "size mismatch"));
+    }
+    Ok(())
+}
+
+/// A temporary data directory that tests going through `data_dir()` switch to. Only one
+/// exists at a time; it is removed (and the global one unset) when dropped.
+#[cfg(test)]
+pub(crate) struct TestDataDir {
+    pub root: PathBuf,
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+static TEST_DATA_DIR: Mutex<()> = Mutex::new(());
+
+#[cfg(test)]
+impl TestDataDir {
+    pub fn new(name: &str) -> Self {
+        let lock = TEST_DATA_DIR.lock().unwrap_or_else(|e| e.into_inner());
+        let root = std::env::temp_dir().join(format!("kano-{}-{}-{}", name, std::process::id(), uuid::Uuid::new_v4()));
+        let path = root.join("kano_data");
+        fs::create_dir_all(&path).unwrap();
+        // nothing to migrate in an empty directory; this records the current version
+        crate::migrations::run(&crate::migrations::MigrationContext { data_dir: path.clone(), legacy_dirs: vec![] }).unwrap();
+        *DATA_DIR.write().unwrap_or_else(|e| e.into_inner()) =
+            Some(Resolved { path, source: DataDirSource::Installed, config_dir: Some(root.join("config")) });
+        TestDataDir { root, _lock: lock }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        *DATA_DIR.write().unwrap_or_else(|e| e.into_inner()) = None;
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_order() {
+        let exe = std::env::temp_dir().join(format!("kano-exe-{}", std::process::id()));
+        let app_data = Path::new("/appdata/kano");
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let (p, src) = resolve(&args(&["kano", "--data-dir", "/d"]), Some("/e"), &exe, Some(app_data), None);
+        assert_eq!((p, src), (PathBuf::from("/d"), DataDirSource::Flag));
+        let (p, src) = resolve(&args(&["kano", "--data-dir=/d2"]), None, &exe, Some(app_data), None);
+        assert_eq!((p, src), (PathBuf::from("/d2"), DataDirSource::Flag));
+        let (p, src) = resolve(&args(&["kano"]), Some(" /e "), &exe, Some(app_data), None);
+        assert_eq!((p, src), (PathBuf::from("/e"), DataDirSource::Env));
+        // installed mode keeps its files in a folder of their own
+        let (p, src) = resolve(&args(&["kano"]), None, &exe, Some(app_data), None);
+        assert_eq!((p, src), (app_data.join("kano_data"), DataDirSource::Installed));
+        let (p, src) = resolve(&args(&["kano"]), None, &exe, None, None);
+        assert_eq!((p, src), (exe.join("kano_data"), DataDirSource::Portable));
+    }
+
+    #[test]
+    fn only_kano_files_are_owned() {
+        let owned = [
+            ("kano.db", false),
+            ("kano.db-wal", false),
+            ("kano.db.2", false),
+            ("kano.corrupt-20240101-120000.db", false),
+            ("games_db.json.imported", false),
+            ("tauri_config.json", false),
+            ("tauri_config.json.1", false),
+            ("tauri_config.lock", false),
+            ("bangumi_cache.tmp.1234", false),
+            ("images", true),
+            ("saves", true),
+            ("schema_version", false),
+            ("migrations.log", false),
+        ];
+        for (name, is_dir) in owned {
+            assert!(is_owned(name, is_dir), "{}", name);
+        }
+        let foreign = [
+            ("kano.exe", false),
+            ("kano_portable", false),
+            ("notes.txt", false),
+            ("readme.md", false),
+            ("images", false),
+            ("kano.db", true),
+            ("resources", true),
+            ("schema_version", true),
+            ("migrations.log.old", false),
+        ];
+        for (name, is_dir) in foreign {
+            assert!(!is_owned(name, is_dir), "{}", name);
+        }
+    }
+
+    #[test]
+    fn moved_data_is_not_migrated_again() {
+        let dir = TestDataDir::new("data-dir-move");
+        let db = crate::models::GamesDB {
+            games: vec![serde_json::from_value(serde_json::json!({ "id": "a", "name": "A", "path": "C:/A/a.exe" })).unwrap()],
+            ..Default::default()
+        };
+        crate::config::replace_games_db(&db).unwrap();
+        let old = data_dir();
+        fs::write(old.join("notes.txt"), "not ours").unwrap();
+
+        let target = dir.root.join("moved");
+        move_to(&target).unwrap();
+        assert_eq!(data_dir(), target);
+        assert!(!crate::migrations::is_pending(&target));
+        assert!(target.join(crate::migrations::LOG_FILE).exists());
+        assert_eq!(crate::config::load_games_db().unwrap().games.len(), 1);
+        // only Kano's files left the old folder
+        let left: Vec<_> = fs::read_dir(&old).unwrap().flatten().map(|e| e.file_name()).collect();
+        assert_eq!(left, ["notes.txt"]);
+        assert_eq!(fs::read_to_string(dir.root.join("config").join(LOCATION_FILE)).unwrap(), target.display().to_string());
+    }
+}
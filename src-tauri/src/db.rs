@@ -0,0 +1,738 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use rusqlite::{params, Connection, ErrorCode, OpenFlags, OptionalExtension, Transaction, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use crate::commands::notes::Note;
+use crate::error::KanoError;
+use crate::data_dir::{self, WriteGuard};
+use crate::migrations;
+use crate::config::{games_db_path, notes_path, sessions_path, tools_path, kano_db_path};
+use crate::models::{GameEntry, GamesDB, PlaySession, ToolEntry};
+use crate::store::{self, backup_path, rotate_backups, LoadError};
+
+// Games, custom tags, notes, tools and sessions live in kano_data/kano.db.
+// Records are stored as JSON next to the columns we query by, so adding a field to
+// a model doesn't need a schema change.
+
+/// Schema steps, applied in order; the index + 1 is stored in `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema (the JSON files are imported right after)
+    "CREATE TABLE games (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        path TEXT NOT NULL,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX games_path ON games(path);
+    CREATE TABLE custom_tags (
+        name TEXT PRIMARY KEY,
+        position INTEGER NOT NULL
+    );
+    CREATE TABLE notes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        game_id TEXT,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE tools (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE sessions (
+        id TEXT PRIMARY KEY,
+        game_id TEXT NOT NULL,
+        start TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX sessions_game ON sessions(game_id);",
+];
+
+fn err(e: rusqlite::Error) -> KanoError {
+    match e.sqlite_error_code() {
+        // busy_timeout ran out while another writer held the database
+        Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked) => {
+            KanoError::LockTimeout { path: kano_db_path().display().to_string() }
+        }
+        _ => KanoError::Io { message: format!("database error: {}", e) },
+    }
+}
+
+/// A connection for writing; the data directory isn't moved while one is open
+pub struct Writer {
+    conn: Connection,
+    _writing: WriteGuard,
+}
+
+impl std::ops::Deref for Writer {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl std::ops::DerefMut for Writer {
+    fn deref_mut(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+}
+
+/// Open the database for writing, creating and migrating it as needed
+pub fn open() -> Result<Writer, KanoError> {
+    let writing = data_dir::begin_write()?;
+    store::check_writable(&kano_db_path())?;
+    Ok(Writer { conn: open_read()?, _writing: writing })
+}
+
+// Reads are refused too while the database is flagged: a missing or half-readable
+// database would show up as an empty library.
+fn open_read() -> Result<Connection, LoadError> {
+    let path = kano_db_path();
+    if let Some(issue) = store::issue_for(&path) {
+        return Err(LoadError::Corrupt { path: issue.path, error: issue.error, quarantined: issue.quarantined });
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_err(&path, format!("create db dir: {}", e)))?;
+    }
+    let mut conn = connect(&path).map_err(|e| load_err(&path, e))?;
+    migrate(&mut conn).map_err(|e| io_err(&path, e.to_string()))?;
+    Ok(conn)
+}
+
+fn connect(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    // concurrent writers (monitor thread, commands) wait instead of failing
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    Ok(conn)
+}
+
+fn io_err(path: &Path, error: String) -> LoadError {
+    LoadError::Io { path: path.display().to_string(), error }
+}
+
+fn load_err(path: &Path, e: rusqlite::Error) -> LoadError {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseCorrupt) | Some(ErrorCode::NotADatabase) => quarantine_db(path, e.to_string()),
+        _ => io_err(path, format!("database error: {}", e)),
+    }
+}
+
+// Like store::quarantine, but the -wal/-shm files go along so the moved copy stays complete
+fn quarantine_db(path: &Path, error: String) -> LoadError {
+    let err = store::quarantine(path, error);
+    if let LoadError::Corrupt { quarantined: Some(q), .. } = &err {
+        move_sidecars(path, Path::new(q));
+    }
+    err
+}
+
+fn move_sidecars(from: &Path, to: &Path) {
+    for suffix in ["-wal", "-shm"] {
+        let mut src = from.as_os_str().to_os_string();
+        src.push(suffix);
+        let mut dst = to.as_os_str().to_os_string();
+        dst.push(suffix);
+        if Path::new(&src).exists() {
+            let _ = fs::rename(&src, &dst);
+        }
+    }
+}
+
+// Run a read; corruption reported by SQLite quarantines the database. The connection is
+// closed before that so the file can be moved.
+fn load_with<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, LoadError> {
+    let conn = open_read()?;
+    let res = f(&conn);
+    drop(conn);
+    res.map_err(|e| load_err(&kano_db_path(), e))
+}
+
+/// `(key, data)` pairs from a `SELECT key, data ...` query
+fn load_rows(sql: &str) -> Result<Vec<(String, String)>, LoadError> {
+    load_with(|conn| {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    })
+}
+
+// A record that doesn't parse blocks writes like a corrupt file would; the database
+// itself is fine, so it isn't moved.
+fn parse_row<T: DeserializeOwned>(what: &str, key: &str, data: &str) -> Result<T, LoadError> {
+    serde_json::from_str(data).map_err(|e| {
+        let path = kano_db_path();
+        let error = format!("{} {}: {}", what, key, e);
+        eprintln!("{}: {}", path.display(), error);
+        store::report_issue(&path, error.clone(), None);
+        LoadError::Corrupt { path: path.display().to_string(), error, quarantined: None }
+    })
+}
+
+/// `PRAGMA quick_check`; a database that fails it is quarantined
+pub fn check_integrity() -> Result<(), LoadError> {
+    let res = load_with(|conn| conn.query_row("PRAGMA quick_check", [], |r| r.get::<_, String>(0)))?;
+    if res == "ok" {
+        Ok(())
+    } else {
+        Err(quarantine_db(&kano_db_path(), format!("integrity check failed: {}", res)))
+    }
+}
+
+const DB_BACKUPS: usize = 3;
+
+/// Keep `kano.db.1..N` copies, taken once per app start. `VACUUM INTO` writes a consistent
+/// snapshot even while other connections are open.
+pub fn backup_database() -> Result<(), KanoError> {
+    let path = kano_db_path();
+    if !path.exists() {
+        return Ok(());
+    }
+    // a broken database must not push the good backups out
+    check_integrity()?;
+    let conn = open()?;
+    rotate_backups(&path, DB_BACKUPS);
+    let target = backup_path(&path, 1);
+    conn.execute("VACUUM INTO ?1", params![target.to_string_lossy()]).map_err(err)?;
+    Ok(())
+}
+
+/// Write the WAL back into kano.db, so the file alone holds everything.
+/// Used while moving the data directory, when `open` refuses.
+pub fn checkpoint() -> Result<(), KanoError> {
+    let conn = open_read()?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(())).map_err(err)
+}
+
+/// Newest `kano.db.N` that opens and passes the integrity check
+pub fn latest_backup() -> Option<PathBuf> {
+    let path = kano_db_path();
+    (1..=DB_BACKUPS).map(|i| backup_path(&path, i)).find(|b| {
+        b.exists()
+            && Connection::open_with_flags(b, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .and_then(|c| c.query_row("PRAGMA quick_check", [], |r| r.get::<_, String>(0)))
+                .map(|s| s == "ok")
+                .unwrap_or(false)
+    })
+}
+
+/// Replace the database with its newest good backup. The current file, if any, is kept
+/// aside as `kano.corrupt-<timestamp>.db`.
+pub fn restore_latest_backup() -> Result<PathBuf, KanoError> {
+    let path = kano_db_path();
+    let backup = latest_backup().ok_or(KanoError::not_found("backup"))?;
+    if path.exists() {
+        let q = store::quarantine_path(&path);
+        fs::rename(&path, &q).map_err(|e| KanoError::io(&format!("move {} aside", path.display()), e))?;
+        move_sidecars(&path, &q);
+    }
+    let data = fs::read(&backup).map_err(|e| KanoError::io(&format!("read {}", backup.display()), e))?;
+    store::write_atomic(&path, &data)?;
+    store::dismiss_issue(&path);
+    Ok(backup)
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), KanoError> {
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |r| r.get::<_, i64>(0))
+        .map_err(err)? as usize;
+    if version >= MIGRATIONS.len() {
+        return Ok(());
+    }
+    let mut imported = Vec::new();
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(err)?;
+    // another process may have migrated while we waited for the write lock
+    let version: usize = tx
+        .pragma_query_value(None, "user_version", |r| r.get::<_, i64>(0))
+        .map_err(err)? as usize;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(sql).map_err(err)?;
+        if i == 0 {
+            imported = import_json_files(&tx)?;
+        }
+        tx.pragma_update(None, "user_version", (i + 1) as i64).map_err(err)?;
+    }
+    tx.commit().map_err(err)?;
+
+    // Keep the old files around (renamed) in case something went wrong with the import
+    for path in imported {
+        let mut done = path.clone().into_os_string();
+        done.push(".imported");
+        if let Err(e) = fs::rename(&path, PathBuf::from(done)) {
+            eprintln!("failed to rename {}: {}", path.display(), e);
+        }
+    }
+    Ok(())
+}
+
+// One-time import of games_db.json, notes.json, tools.json and sessions.json.
+// Returns the files that were imported; unreadable ones are left in place untouched.
+fn import_json_files(tx: &Transaction) -> Result<Vec<PathBuf>, KanoError> {
+    // the files have to be in their current format first; a failed startup migration is
+    // retried on the next start, and the import waits until then
+    if migrations::is_pending(&data_dir::data_dir()) {
+        return Err(KanoError::Busy {
+            reason: "migrations_pending",
+            message: format!("data migrations haven't completed (see {}); not importing into the database", migrations::LOG_FILE),
+        });
+    }
+    let mut imported = Vec::new();
+
+    let path = games_db_path();
+    if let Some(db) = read_legacy::<GamesDB>(&path) {
+        write_games(tx, &db, None)?;
+        imported.push(path);
+    }
+
+    let path = notes_path();
+    if let Some(notes) = read_legacy::<Vec<Note>>(&path) {
+        write_notes(tx, &notes, None)?;
+        imported.push(path);
+    }
+
+    let path = tools_path();
+    if let Some(tools) = read_legacy::<Vec<ToolEntry>>(&path) {
+        write_tools(tx, &tools)?;
+        imported.push(path);
+    }
+
+    let path = sessions_path();
+    if let Some(sessions) = read_legacy::<Vec<PlaySession>>(&path) {
+        write_sessions(tx, &sessions)?;
+        imported.push(path);
+    }
+    Ok(imported)
+}
+
+fn read_legacy<T: DeserializeOwned>(path: &PathBuf) -> Option<T> {
+    let s = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&s) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            eprintln!("not importing {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn to_json<T: serde::Serialize>(v: &T) -> Result<String, KanoError> {
+    Ok(serde_json::to_string(v)?)
+}
+
+// ---- games ----
+
+/// What the games and custom tags were when a copy of the library was loaded
+struct Snapshot {
+    rows: HashMap<String, String>, // game id -> JSON
+    tags: Vec<String>,
+}
+
+impl Snapshot {
+    fn of(db: &GamesDB) -> Self {
+        // compare against the re-serialized form so fields added since don't count as changes
+        let rows = db.games.iter().map(|g| (g.id.clone(), serde_json::to_string(g).unwrap_or_default())).collect();
+        Snapshot { rows, tags: db.custom_tags.clone() }
+    }
+}
+
+/// The library as loaded from the database. Saving it with `save_games_db` only writes
+/// the games and tags it changed, added or removed, so two commands editing different
+/// games don't undo each other.
+pub struct LoadedGames {
+    db: GamesDB,
+    loaded: Snapshot,
+}
+
+impl LoadedGames {
+    pub fn into_inner(self) -> GamesDB {
+        self.db
+    }
+}
+
+impl std::ops::Deref for LoadedGames {
+    type Target = GamesDB;
+    fn deref(&self) -> &GamesDB {
+        &self.db
+    }
+}
+
+impl std::ops::DerefMut for LoadedGames {
+    fn deref_mut(&mut self) -> &mut GamesDB {
+        &mut self.db
+    }
+}
+
+pub fn load_games_db() -> Result<LoadedGames, LoadError> {
+    let mut games = Vec::new();
+    for (id, data) in load_rows("SELECT id, data FROM games ORDER BY position")? {
+        games.push(parse_row::<GameEntry>("game", &id, &data)?);
+    }
+    let custom_tags = load_with(|conn| {
+        let mut stmt = conn.prepare("SELECT name FROM custom_tags ORDER BY position")?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+        rows.collect::<Result<Vec<_>, _>>()
+    })?;
+    let db = GamesDB { games, custom_tags };
+    Ok(LoadedGames { loaded: Snapshot::of(&db), db })
+}
+
+/// Save in one transaction, writing only what changed since loading
+pub fn save_games_db(db: &LoadedGames) -> Result<(), KanoError> {
+    commit_games(&db.db, Some(&db.loaded))
+}
+
+/// Save what changed between `loaded` (as returned by `load_games_db`) and `db`
+pub fn save_games_changes(loaded: &GamesDB, db: &GamesDB) -> Result<(), KanoError> {
+    commit_games(db, Some(&Snapshot::of(loaded)))
+}
+
+/// Replace all games and custom tags with `db`
+pub fn replace_games_db(db: &GamesDB) -> Result<(), KanoError> {
+    commit_games(db, None)
+}
+
+fn commit_games(db: &GamesDB, loaded: Option<&Snapshot>) -> Result<(), KanoError> {
+    let mut conn = open()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(err)?;
+    write_games(&tx, db, loaded)?;
+    tx.commit().map_err(err)
+}
+
+// Without a snapshot both tables are replaced
+fn write_games(tx: &Transaction, db: &GamesDB, snapshot: Option<&Snapshot>) -> Result<(), KanoError> {
+    let empty = HashMap::new();
+    let loaded = match snapshot {
+        Some(s) => &s.rows,
+        None => {
+            tx.execute("DELETE FROM games", []).map_err(err)?;
+            &empty
+        }
+    };
+    let mut next_pos: i64 = tx
+        .query_row("SELECT COALESCE(MAX(position), -1) + 1 FROM games", [], |r| r.get(0))
+        .map_err(err)?;
+    for g in &db.games {
+        let data = to_json(g)?;
+        if loaded.get(&g.id) == Some(&data) {
+            continue;
+        }
+        let exists = tx
+            .query_row("SELECT 1 FROM games WHERE id = ?1", params![g.id], |_| Ok(()))
+            .optional()
+            .map_err(err)?
+            .is_some();
+        if exists {
+            tx.execute(
+                "UPDATE games SET path = ?2, name = ?3, data = ?4 WHERE id = ?1",
+                params![g.id, g.path, g.name, data],
+            )
+            .map_err(err)?;
+        } else {
+            tx.execute(
+                "INSERT INTO games (id, position, path, name, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![g.id, next_pos, g.path, g.name, data],
+            )
+            .map_err(err)?;
+            next_pos += 1;
+        }
+    }
+    for id in loaded.keys() {
+        if !db.games.iter().any(|g| &g.id == id) {
+            tx.execute("DELETE FROM games WHERE id = ?1", params![id]).map_err(err)?;
+        }
+    }
+
+    // Tags the same way: only those added, removed or moved since loading are written
+    let loaded_tags = match snapshot {
+        Some(s) => s.tags.as_slice(),
+        None => {
+            tx.execute("DELETE FROM custom_tags", []).map_err(err)?;
+            &[]
+        }
+    };
+    for t in loaded_tags {
+        if !db.custom_tags.contains(t) {
+            tx.execute("DELETE FROM custom_tags WHERE name = ?1", params![t]).map_err(err)?;
+        }
+    }
+    for (i, t) in db.custom_tags.iter().enumerate() {
+        if loaded_tags.iter().position(|l| l == t) != Some(i) {
+            tx.execute(
+                "INSERT OR REPLACE INTO custom_tags (name, position) VALUES (?1, ?2)",
+                params![t, i as i64],
+            )
+            .map_err(err)?;
+        }
+    }
+    Ok(())
+}
+
+// ---- notes ----
+
+pub fn load_notes() -> Result<Vec<Note>, LoadError> {
+    load_rows("SELECT CAST(id AS TEXT), data FROM notes ORDER BY id")?
+        .iter()
+        .map(|(id, data)| {
+            let mut note: Note = parse_row("note", id, data)?;
+            note.row_id = id.parse().ok();
+            Ok(note)
+        })
+        .collect()
+}
+
+/// With `loaded` (the notes as read before the change) only the rows that changed are
+/// written; without it the table is replaced.
+pub fn save_notes(notes: &[Note], loaded: Option<&[Note]>) -> Result<(), KanoError> {
+    let mut conn = open()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(err)?;
+    write_notes(&tx, notes, loaded)?;
+    tx.commit().map_err(err)
+}
+
+// Rows keep their id when edited, so the list order (by id) stays as it was
+fn write_notes(tx: &Transaction, notes: &[Note], loaded: Option<&[Note]>) -> Result<(), KanoError> {
+    let mut before = HashMap::new();
+    match loaded {
+        Some(l) => {
+            for n in l {
+                if let Some(id) = n.row_id {
+                    before.insert(id, to_json(n)?);
+                }
+            }
+        }
+        None => {
+            tx.execute("DELETE FROM notes", []).map_err(err)?;
+        }
+    }
+    for n in notes {
+        let data = to_json(n)?;
+        if let Some(old) = n.row_id.and_then(|id| before.get(&id)) {
+            if *old == data {
+                continue;
+            }
+            let updated = tx
+                .execute("UPDATE notes SET game_id = ?2, data = ?3 WHERE id = ?1", params![n.row_id, n.game_id, data])
+                .map_err(err)?;
+            if updated > 0 {
+                continue;
+            }
+        }
+        // new, or removed by someone else meanwhile
+        tx.execute("INSERT INTO notes (game_id, data) VALUES (?1, ?2)", params![n.game_id, data])
+            .map_err(err)?;
+    }
+    for id in before.keys() {
+        if !notes.iter().any(|n| n.row_id == Some(*id)) {
+            tx.execute("DELETE FROM notes WHERE id = ?1", params![id]).map_err(err)?;
+        }
+    }
+    Ok(())
+}
+
+// ---- tools ----
+
+pub fn load_tools_file() -> Result<Vec<ToolEntry>, LoadError> {
+    load_rows("SELECT id, data FROM tools ORDER BY position")?
+        .iter()
+        .map(|(id, data)| parse_row("tool", id, data))
+        .collect()
+}
+
+pub fn save_tools_file(tools: &[ToolEntry]) -> Result<(), KanoError> {
+    let mut conn = open()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(err)?;
+    write_tools(&tx, tools)?;
+    tx.commit().map_err(err)
+}
+
+fn write_tools(tx: &Transaction, tools: &[ToolEntry]) -> Result<(), KanoError> {
+    tx.execute("DELETE FROM tools", []).map_err(err)?;
+    for (i, t) in tools.iter().enumerate() {
+        tx.execute(
+            "INSERT OR REPLACE INTO tools (id, position, data) VALUES (?1, ?2, ?3)",
+            params![t.id, i as i64, to_json(t)?],
+        )
+        .map_err(err)?;
+    }
+    Ok(())
+}
+
+// ---- sessions ----
+
+pub fn load_sessions() -> Result<Vec<PlaySession>, LoadError> {
+    load_rows("SELECT id, data FROM sessions ORDER BY start")?
+        .iter()
+        .map(|(id, data)| parse_row("session", id, data))
+        .collect()
+}
+
+pub fn save_sessions(sessions: &[PlaySession]) -> Result<(), KanoError> {
+    let mut conn = open()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(err)?;
+    write_sessions(&tx, sessions)?;
+    tx.commit().map_err(err)
+}
+
+fn write_sessions(tx: &Transaction, sessions: &[PlaySession]) -> Result<(), KanoError> {
+    tx.execute("DELETE FROM sessions", []).map_err(err)?;
+    for s in sessions {
+        put_session(tx, s)?;
+    }
+    Ok(())
+}
+
+/// Add or replace a single session without touching the others
+pub fn upsert_session(session: &PlaySession) -> Result<(), KanoError> {
+    let conn = open()?;
+    put_session(&conn, session)
+}
+
+/// Remove a single session; false when it didn't exist
+pub fn remove_session(id: &str) -> Result<bool, KanoError> {
+    let conn = open()?;
+    let n = conn.execute("DELETE FROM sessions WHERE id = ?1", params![id]).map_err(err)?;
+    Ok(n > 0)
+}
+
+fn put_session(conn: &Connection, s: &PlaySession) -> Result<(), KanoError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO sessions (id, game_id, start, data) VALUES (?1, ?2, ?3, ?4)",
+        params![s.id, s.game_id, s.start, to_json(s)?],
+    )
+    .map_err(err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in MIGRATIONS {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn
+    }
+
+    fn tags(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT name FROM custom_tags ORDER BY position, name").unwrap();
+        stmt.query_map([], |r| r.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    fn note(title: &str) -> Note {
+        Note {
+            game_id: None,
+            game_name: None,
+            title: title.into(),
+            content: String::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            row_id: None,
+        }
+    }
+
+    fn notes(conn: &Connection) -> Vec<Note> {
+        let mut stmt = conn.prepare("SELECT id, data FROM notes ORDER BY id").unwrap();
+        stmt.query_map([], |r| {
+            let mut n: Note = serde_json::from_str(&r.get::<_, String>(1)?).unwrap();
+            n.row_id = Some(r.get(0)?);
+            Ok(n)
+        })
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+    }
+
+    fn save_tags(conn: &mut Connection, db: &GamesDB, loaded: Option<&Snapshot>) {
+        let tx = conn.transaction().unwrap();
+        write_games(&tx, db, loaded).unwrap();
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn tags_are_written_as_changes() {
+        let mut conn = memory_db();
+        save_tags(&mut conn, &GamesDB { custom_tags: vec!["a".into(), "b".into()], ..Default::default() }, None);
+
+        // two copies loaded at the same time; one adds a tag, the other removes one
+        let loaded = Snapshot { rows: HashMap::new(), tags: tags(&conn) };
+        let adds = GamesDB { custom_tags: vec!["a".into(), "b".into(), "c".into()], ..Default::default() };
+        let removes = GamesDB { custom_tags: vec!["b".into()], ..Default::default() };
+        save_tags(&mut conn, &adds, Some(&loaded));
+        save_tags(&mut conn, &removes, Some(&loaded));
+        let mut got = tags(&conn);
+        got.sort();
+        assert_eq!(got, vec!["b", "c"]);
+
+        // without a snapshot the list is replaced
+        save_tags(&mut conn, &GamesDB { custom_tags: vec!["z".into()], ..Default::default() }, None);
+        assert_eq!(tags(&conn), vec!["z"]);
+    }
+
+    #[test]
+    fn notes_are_written_as_changes() {
+        let mut conn = memory_db();
+        let tx = conn.transaction().unwrap();
+        write_notes(&tx, &[note("one"), note("two")], None).unwrap();
+        tx.commit().unwrap();
+        let loaded = notes(&conn);
+
+        // another writer adds a note after we loaded
+        let tx = conn.transaction().unwrap();
+        write_notes(&tx, &[note("three")], Some(&[])).unwrap();
+        tx.commit().unwrap();
+
+        // edit "one" in place, delete "two"
+        let mut edited = loaded[0].clone();
+        edited.content = "edited".into();
+        let tx = conn.transaction().unwrap();
+        write_notes(&tx, &[edited], Some(&loaded)).unwrap();
+        tx.commit().unwrap();
+
+        let after = notes(&conn);
+        let got: Vec<(&str, &str)> = after.iter().map(|n| (n.title.as_str(), n.content.as_str())).collect();
+        assert_eq!(got, vec![("one", "edited"), ("three", "")]);
+        assert_eq!(after[0].row_id, loaded[0].row_id);
+    }
+
+    #[test]
+    fn imports_a_games_db_json_from_older_versions() {
+        let _dir = crate::data_dir::TestDataDir::new("db-import");
+        let data = data_dir::data_dir();
+        // as the first versions wrote it: no ids, local image paths, no newer fields
+        let legacy = serde_json::json!({
+            "games": [
+                { "name": "Summer Pockets", "path": "D:\\Games\\SP\\SiglusEngine.exe", "image": "kano_data/images/245665.jpg",
+                  "subject_id": 245665, "playtime": 3600, "last_played": "2024-01-01T10:00:00+08:00",
+                  "folder_path": ["SP"], "tags": ["Key"] },
+                { "name": "Other", "path": "D:\\Games\\Other\\other.exe", "image": null, "subject_id": null },
+            ],
+            "custom_tags": ["Key", "积压"],
+        });
+        fs::write(games_db_path(), serde_json::to_vec_pretty(&legacy).unwrap()).unwrap();
+        fs::remove_file(data.join(migrations::SCHEMA_VERSION_FILE)).unwrap();
+        migrations::run(&migrations::MigrationContext { data_dir: data.clone(), legacy_dirs: vec![] }).unwrap();
+
+        let db = load_games_db().unwrap();
+        let names: Vec<&str> = db.games.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["Summer Pockets", "Other"]);
+        assert!(db.games.iter().all(|g| !g.id.is_empty()));
+        let sp = &db.games[0];
+        assert_eq!((sp.playtime, sp.subject_id, sp.tags.clone()), (3600, Some(245665), vec!["Key".to_string()]));
+        assert_eq!(sp.last_played.as_deref(), Some("2024-01-01T10:00:00+08:00"));
+        assert_eq!(db.custom_tags, ["Key", "积压"]);
+        // imported once; the file is kept under another name
+        assert!(!games_db_path().exists());
+        assert!(data.join("games_db.json.imported").exists());
+
+        let ids: Vec<String> = db.games.iter().map(|g| g.id.clone()).collect();
+        let mut db = db;
+        db.games[1].playtime = 60;
+        save_games_db(&db).unwrap();
+        let again = load_games_db().unwrap();
+        assert_eq!(again.games.iter().map(|g| g.id.clone()).collect::<Vec<_>>(), ids);
+        assert_eq!(again.games[1].playtime, 60);
+    }
+}
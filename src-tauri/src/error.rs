@@ -0,0 +1,150 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use crate::store::LoadError;
+
+/// Error returned by every command. It reaches the frontend as
+/// `{ code, key, message, params }`: `code` is stable and meant for branching, `key` is the
+/// i18n message key (`errors.<code>`, or `errors.<code>.<reason>` when there's a reason),
+/// `params` fills the placeholders and `message` is an English fallback.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KanoError {
+    /// `what`: game, tool, session, runner, launch_profile, snapshot, file, folder, process ...
+    NotFound { what: &'static str },
+    /// `reason` is a snake_case code, e.g. `tag_exists`
+    InvalidInput { reason: &'static str, message: String },
+    /// the operation can't run right now, e.g. while a game is running
+    Busy { reason: &'static str, message: String },
+    Io { message: String },
+    Network { message: String },
+    Http { status: u16, message: String },
+    Parse { message: String },
+    /// a data file is corrupt, or was and hasn't been restored yet
+    Corrupt { path: String, message: String },
+    LockTimeout { path: String },
+    /// the user closed a dialog
+    Cancelled,
+    /// errors from helpers that don't classify them
+    Other { message: String },
+}
+
+impl KanoError {
+    pub fn not_found(what: &'static str) -> Self {
+        KanoError::NotFound { what }
+    }
+
+    pub fn invalid(reason: &'static str, message: impl Into<String>) -> Self {
+        KanoError::InvalidInput { reason, message: message.into() }
+    }
+
+    /// I/O error with what was being done, e.g. `KanoError::io("create images dir", e)`
+    pub fn io(context: &str, e: impl std::fmt::Display) -> Self {
+        KanoError::Io { message: format!("{}: {}", context, e) }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            KanoError::NotFound { .. } => "not_found",
+            KanoError::InvalidInput { .. } => "invalid_input",
+            KanoError::Busy { .. } => "busy",
+            KanoError::Io { .. } => "io",
+            KanoError::Network { .. } => "network",
+            KanoError::Http { .. } => "http",
+            KanoError::Parse { .. } => "parse",
+            KanoError::Corrupt { .. } => "corrupt",
+            KanoError::LockTimeout { .. } => "lock_timeout",
+            KanoError::Cancelled => "cancelled",
+            KanoError::Other { .. } => "other",
+        }
+    }
+
+    pub fn message_key(&self) -> String {
+        match self {
+            KanoError::NotFound { what } => format!("errors.not_found.{}", what),
+            KanoError::InvalidInput { reason, .. } | KanoError::Busy { reason, .. } => {
+                format!("errors.{}.{}", self.code(), reason)
+            }
+            _ => format!("errors.{}", self.code()),
+        }
+    }
+
+    fn params(&self) -> serde_json::Value {
+        match self {
+            KanoError::NotFound { what } => serde_json::json!({ "what": what }),
+            KanoError::Http { status, .. } => serde_json::json!({ "status": status }),
+            KanoError::Corrupt { path, .. } | KanoError::LockTimeout { path } => serde_json::json!({ "path": path }),
+            _ => serde_json::json!({}),
+        }
+    }
+}
+
+impl std::fmt::Display for KanoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KanoError::NotFound { what } => write!(f, "{} not found", what.replace('_', " ")),
+            KanoError::InvalidInput { message, .. }
+            | KanoError::Busy { message, .. }
+            | KanoError::Io { message }
+            | KanoError::Network { message }
+            | KanoError::Parse { message }
+            | KanoError::Corrupt { message, .. }
+            | KanoError::Other { message } => f.write_str(message),
+            KanoError::Http { status, message } if message.is_empty() => write!(f, "HTTP {}", status),
+            KanoError::Http { status, message } => write!(f, "HTTP {}: {}", status, message),
+            KanoError::LockTimeout { path } => write!(f, "timed out waiting for {}", path),
+            KanoError::Cancelled => f.write_str("cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for KanoError {}
+
+impl Serialize for KanoError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("KanoError", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("key", &self.message_key())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("params", &self.params())?;
+        s.end()
+    }
+}
+
+impl From<std::io::Error> for KanoError {
+    fn from(e: std::io::Error) -> Self {
+        KanoError::Io { message: e.to_string() }
+    }
+}
+
+impl From<serde_json::Error> for KanoError {
+    fn from(e: serde_json::Error) -> Self {
+        KanoError::Parse { message: e.to_string() }
+    }
+}
+
+impl From<reqwest::Error> for KanoError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => KanoError::Http { status: status.as_u16(), message: String::new() },
+            None if e.is_decode() => KanoError::Parse { message: e.to_string() },
+            None => KanoError::Network { message: e.to_string() },
+        }
+    }
+}
+
+impl From<zip::result::ZipError> for KanoError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => KanoError::Io { message: e.to_string() },
+            e => KanoError::Parse { message: e.to_string() },
+        }
+    }
+}
+
+impl From<LoadError> for KanoError {
+    fn from(e: LoadError) -> Self {
+        match &e {
+            LoadError::Io { .. } => KanoError::Io { message: e.to_string() },
+            LoadError::Corrupt { path, .. } => KanoError::Corrupt { path: path.clone(), message: e.to_string() },
+        }
+    }
+}
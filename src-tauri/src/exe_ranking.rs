@@ -0,0 +1,283 @@
+use std::path::Path;
+use crate::models::{AppConfig, KeywordWeight};
+
+// Known non-game binaries. Prefixes are matched against the lowercase file stem.
+const DENY_PREFIXES: [&str; 14] = [
+    "unins", "uninst", "setup", "install", "vcredist", "vc_redist", "dxsetup", "dxwebsetup",
+    "directx", "dotnetfx", "oalinst", "ue4prereq", "unitycrashhandler", "notification_helper",
+];
+// Whole words: matched against a token or a run of adjacent tokens of the stem, so
+// "GameConfig" and "Crash_Handler" are denied but "Dispatch" and "Patchouli" are not
+const DENY_WORDS: [&str; 10] = [
+    "config", "configtool", "crashreport", "crashreporter", "crashpad", "crashhandler", "patch", "updater",
+    "register", "regist",
+];
+
+const DENY_PENALTY: i64 = -100;
+const DEPTH_PENALTY: i64 = -10;
+const SIZE_BONUS_MAX: i64 = 30;
+const SIMILARITY_BONUS_MAX: i64 = 40;
+
+/// Default keyword weights: prefer localized (Chinese) builds
+pub fn default_keyword_weights() -> Vec<KeywordWeight> {
+    ["ch", "chs", "cht", "cn", "中文", "chinese", "简体", "繁体", "汉化"]
+        .iter()
+        .map(|k| KeywordWeight { keyword: k.to_string(), weight: 30 })
+        .collect()
+}
+
+/// Scores exe files found while scanning a game folder; higher means more likely the game itself
+pub struct ExeRanker {
+    keyword_weights: Vec<KeywordWeight>,
+}
+
+impl ExeRanker {
+    pub fn new(keyword_weights: Vec<KeywordWeight>) -> Self {
+        ExeRanker { keyword_weights }
+    }
+
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self::new(cfg.exe_keyword_weights.clone())
+    }
+
+    /// `folder_path` is the list of folders between the game folder and the exe,
+    /// `game_folder_name` is the name of the game folder itself.
+    pub fn score(&self, exe_path: &Path, folder_path: &[String], size: u64, game_folder_name: &str) -> i64 {
+        let stem = exe_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut score = 0;
+        score += denylist_score(&stem);
+        score += DEPTH_PENALTY * folder_path.len() as i64;
+        score += size_score(size);
+        score += similarity_score(&stem, game_folder_name);
+        score += self.keyword_score(&stem);
+        score
+    }
+
+    fn keyword_score(&self, stem: &str) -> i64 {
+        let lower = stem.to_lowercase();
+        let tokens = tokenize(stem);
+        self.keyword_weights
+            .iter()
+            .filter(|kw| {
+                let k = kw.keyword.trim().to_lowercase();
+                if k.is_empty() {
+                    false
+                } else if k.is_ascii() {
+                    // short ascii keywords like "ch" must match a whole token,
+                    // otherwise they hit "launcher", "crashhandler", ...
+                    tokens.contains(&k)
+                } else {
+                    lower.contains(&k)
+                }
+            })
+            .map(|kw| kw.weight)
+            .sum()
+    }
+}
+
+fn denylist_score(stem: &str) -> i64 {
+    let lower = stem.to_lowercase();
+    let tokens = tokenize(stem);
+    let denied = DENY_PREFIXES.iter().any(|p| lower.starts_with(p))
+        || DENY_WORDS.iter().any(|w| has_word(&tokens, w));
+    if denied { DENY_PENALTY } else { 0 }
+}
+
+// `word` equals tokens[i..j] joined for some i < j
+fn has_word(tokens: &[String], word: &str) -> bool {
+    (0..tokens.len()).any(|i| {
+        let mut joined = String::new();
+        for t in &tokens[i..] {
+            joined.push_str(t);
+            if joined.len() >= word.len() {
+                return joined == word;
+            }
+        }
+        false
+    })
+}
+
+// Bigger executables are more likely to be the game: 1 KB -> 0, 1 MB -> 20, 32 MB and above -> 30
+fn size_score(size: u64) -> i64 {
+    if size < 1024 {
+        return 0;
+    }
+    let kb = size as f64 / 1024.0;
+    ((kb.log2() * 2.0) as i64).min(SIZE_BONUS_MAX)
+}
+
+fn similarity_score(stem: &str, folder_name: &str) -> i64 {
+    let a = normalize(stem);
+    let b = normalize(folder_name);
+    if a.is_empty() || b.is_empty() {
+        return 0;
+    }
+    if a.contains(&b) || b.contains(&a) {
+        // 按长度比例给分，免得 "game" 这种短而泛的名字蹭到 "Game Launcher" 的满分
+        let (la, lb) = (a.chars().count() as i64, b.chars().count() as i64);
+        return SIMILARITY_BONUS_MAX * la.min(lb) / la.max(lb);
+    }
+    (similarity(&a, &b) * SIMILARITY_BONUS_MAX as f64).round() as i64
+}
+
+/// Lowercase and keep only letters/digits (including CJK) so punctuation and spacing don't matter
+pub fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Normalized Levenshtein similarity in [0, 1]
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j + 1] + 1).min(cur[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+// Split a file stem into lowercase tokens on punctuation, case changes and letter/digit boundaries:
+// "GameCHS_v1.2" -> ["game", "chs", "v", "1", "2"]
+fn tokenize(stem: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let mut prev: Option<char> = None;
+    for c in stem.chars() {
+        if !c.is_alphanumeric() {
+            if !cur.is_empty() {
+                tokens.push(std::mem::take(&mut cur));
+            }
+            prev = None;
+            continue;
+        }
+        if let Some(p) = prev {
+            let boundary = (p.is_lowercase() && c.is_uppercase())
+                || (p.is_ascii_digit() != c.is_ascii_digit());
+            if boundary && !cur.is_empty() {
+                tokens.push(std::mem::take(&mut cur));
+            }
+        }
+        cur.extend(c.to_lowercase());
+        prev = Some(c);
+    }
+    if !cur.is_empty() {
+        tokens.push(cur);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranker() -> ExeRanker {
+        ExeRanker::new(default_keyword_weights())
+    }
+
+    #[test]
+    fn tokenize_splits_on_case_digits_and_punctuation() {
+        let cases: &[(&str, &[&str])] = &[
+            ("GameCHS_v1.2", &["game", "chs", "v", "1", "2"]),
+            ("UnityCrashHandler64", &["unity", "crash", "handler", "64"]),
+            ("dispatch", &["dispatch"]),
+            ("白色相簿2", &["白色相簿", "2"]),
+        ];
+        for (stem, want) in cases {
+            assert_eq!(tokenize(stem), *want, "{}", stem);
+        }
+    }
+
+    #[test]
+    fn denylist_matches_words_not_substrings() {
+        let cases = [
+            ("unins000", true),
+            ("setup", true),
+            ("vc_redist.x64", true),
+            ("GameConfig", true),
+            ("config", true),
+            ("UnityCrashHandler64", true),
+            ("CrashReporter", true),
+            ("crash_report", true),
+            ("Patch", true),
+            ("GameUpdater", true),
+            ("Dispatch", false),
+            ("Patchouli", false),
+            ("Registry", false),
+            ("Reconfigure", false),
+            ("SummerPockets", false),
+        ];
+        for (stem, denied) in cases {
+            assert_eq!(denylist_score(stem) == DENY_PENALTY, denied, "{}", stem);
+        }
+    }
+
+    #[test]
+    fn keywords_match_whole_ascii_tokens() {
+        let r = ranker();
+        assert_eq!(r.keyword_score("GameCHS"), 30);
+        assert_eq!(r.keyword_score("game_ch"), 30);
+        assert_eq!(r.keyword_score("launcher"), 0);
+        assert_eq!(r.keyword_score("Crashhandler"), 0);
+        // non-ascii keywords match anywhere
+        assert_eq!(r.keyword_score("游戏中文版"), 30);
+    }
+
+    #[test]
+    fn size_bonus_grows_with_size_and_is_capped() {
+        assert_eq!(size_score(0), 0);
+        assert_eq!(size_score(1023), 0);
+        assert_eq!(size_score(1024), 0);
+        assert_eq!(size_score(1024 * 1024), 20);
+        assert_eq!(size_score(32 * 1024 * 1024), SIZE_BONUS_MAX);
+        assert_eq!(size_score(4 * 1024 * 1024 * 1024), SIZE_BONUS_MAX);
+    }
+
+    #[test]
+    fn similarity_is_normalized_levenshtein() {
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert!((similarity("kitten", "sitting") - (1.0 - 3.0 / 7.0)).abs() < 1e-9);
+        assert_eq!(normalize("Summer Pockets!"), "summerpockets");
+    }
+
+    #[test]
+    fn containment_bonus_scales_with_length_ratio() {
+        assert_eq!(similarity_score("SummerPockets", "Summer Pockets"), SIMILARITY_BONUS_MAX);
+        // "game" is 4 of the 12 chars in "gamelauncher"
+        assert_eq!(similarity_score("game", "Game Launcher"), SIMILARITY_BONUS_MAX / 3);
+        assert!(similarity_score("SummerPockets", "Summer Pockets Reflection Blue") > similarity_score("game", "Game Launcher"));
+        assert_eq!(similarity_score("", "Game"), 0);
+    }
+
+    #[test]
+    fn game_exe_outranks_helpers() {
+        let r = ranker();
+        let mb = 1024 * 1024;
+        let game = r.score(Path::new("D:/SP/SummerPockets.exe"), &[], 40 * mb, "Summer Pockets");
+        let config = r.score(Path::new("D:/SP/GameConfig.exe"), &[], 2 * mb, "Summer Pockets");
+        let nested = r.score(Path::new("D:/SP/bin/x64/SummerPockets.exe"), &["bin".into(), "x64".into()], 40 * mb, "Summer Pockets");
+        let dispatch = r.score(Path::new("D:/Dispatch/Dispatch.exe"), &[], 40 * mb, "Dispatch");
+        assert!(game > config);
+        assert!(game > nested);
+        assert_eq!(game - nested, 2 * -DEPTH_PENALTY);
+        assert!(dispatch > 0);
+    }
+}
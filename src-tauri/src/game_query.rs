@@ -0,0 +1,89 @@
+use serde::Deserialize;
+use crate::metadata::{iso_date, GameMetadata};
+use crate::models::GameEntry;
+
+// Library search over names, user tags and the metadata stored on each game. Text
+// conditions are case-insensitive substring matches, like the library's search box;
+// every condition that is set has to hold.
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GameQuery {
+    pub keyword: Option<String>,       // 名称、各语言标题、别名、开发商、发行商、制作人员
+    pub tags: Vec<String>,             // 用户标签，须全部包含
+    pub metadata_tags: Vec<String>,    // 条目标签或游戏类型（如 "ADV"），须全部包含
+    pub developer: Option<String>,     // 开发商/品牌
+    pub staff: Option<String>,         // 制作人员姓名
+    pub platform: Option<String>,
+    pub released_from: Option<String>, // "2004"、"2004-08" 或 "2004-08-29"，含当天
+    pub released_to: Option<String>,
+}
+
+type MetadataCheck = fn(&GameMetadata, &str) -> bool;
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(needle)
+}
+
+// Blank conditions count as unset
+fn needle(s: &Option<String>) -> Option<String> {
+    s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_lowercase)
+}
+
+impl GameQuery {
+    pub fn matches(&self, game: &GameEntry) -> bool {
+        let meta = game.metadata.as_ref();
+        if !self.tags.iter().all(|t| game.tags.contains(t)) {
+            return false;
+        }
+        if !self.metadata_tags.is_empty() {
+            let Some(m) = meta else { return false };
+            let has = |t: &String| {
+                m.tags.iter().any(|mt| mt.eq_ignore_ascii_case(t))
+                    || m.genre.as_deref().is_some_and(|g| contains(g, &t.to_lowercase()))
+            };
+            if !self.metadata_tags.iter().all(has) {
+                return false;
+            }
+        }
+        if let Some(k) = needle(&self.keyword) {
+            if !contains(&game.name, &k) && !meta.is_some_and(|m| keyword_in(m, &k)) {
+                return false;
+            }
+        }
+        let checks: [(Option<String>, MetadataCheck); 3] = [
+            (needle(&self.developer), |m, d| m.developers.iter().any(|x| contains(x, d))),
+            (needle(&self.staff), |m, s| m.staff.iter().any(|x| contains(&x.name, s))),
+            (needle(&self.platform), |m, p| m.platforms.iter().any(|x| contains(x, p))),
+        ];
+        for (want, check) in checks {
+            if let Some(want) = want {
+                if !meta.is_some_and(|m| check(m, &want)) {
+                    return false;
+                }
+            }
+        }
+        self.released_in_range(meta.and_then(|m| m.release_date.as_deref()))
+    }
+
+    // Dates may be partial; "2004-08" lies within ["2004", "2004"]
+    fn released_in_range(&self, date: Option<&str>) -> bool {
+        let from = needle(&self.released_from);
+        let to = needle(&self.released_to);
+        if from.is_none() && to.is_none() {
+            return true;
+        }
+        // normalized again for records stored before dates were
+        let Some(date) = date.and_then(iso_date) else { return false };
+        let date = date.as_str();
+        let from_ok = from.is_none_or(|f| date >= f.as_str());
+        let to_ok = to.is_none_or(|t| date.get(..t.len()).unwrap_or(date) <= t.as_str());
+        from_ok && to_ok
+    }
+}
+
+fn keyword_in(m: &GameMetadata, k: &str) -> bool {
+    m.all_titles().any(|t| contains(t, k))
+        || m.developers.iter().chain(&m.publishers).any(|x| contains(x, k))
+        || m.staff.iter().any(|s| contains(&s.name, k))
+}
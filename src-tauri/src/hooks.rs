@@ -0,0 +1,220 @@
+use std::path::Path;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::error::KanoError;
+use crate::launch::split_command_line;
+use crate::models::{GameHook, ToolEntry};
+
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+/// Payload of the `hook-failed` event
+#[derive(Serialize, Clone, Debug)]
+pub struct HookFailure {
+    pub game_id: String,
+    pub stage: String, // "pre_launch" | "post_exit" | "companion"
+    pub index: usize,
+    pub hook: String,  // command line or tool name, for display
+    pub error: String,
+}
+
+/// Run hooks in order. Failures are passed to `on_failure`; a failing `required` hook
+/// stops the remaining ones and is returned as `required_hook_failed`.
+pub fn run_hooks(
+    hooks: &[GameHook],
+    stage: &str,
+    game_id: &str,
+    exe: &Path,
+    tools: &[ToolEntry],
+    on_failure: &dyn Fn(HookFailure),
+) -> Result<(), KanoError> {
+    for (index, hook) in hooks.iter().enumerate() {
+        if let Err(error) = run_hook(hook, exe, tools) {
+            let failure = HookFailure {
+                game_id: game_id.to_string(),
+                stage: stage.to_string(),
+                index,
+                hook: describe(hook, tools),
+                error: error.to_string(),
+            };
+            let message = format!("{} hook failed: {} ({})", stage, failure.hook, failure.error);
+            on_failure(failure);
+            if hook.required {
+                return Err(KanoError::invalid("required_hook_failed", message));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Start the game's companion tools; the returned children can be closed when the game exits
+pub fn launch_companions(tool_ids: &[String], game_id: &str, tools: &[ToolEntry], on_failure: &dyn Fn(HookFailure)) -> Vec<Child> {
+    let mut children = Vec::new();
+    for (index, tid) in tool_ids.iter().enumerate() {
+        let result = tools
+            .iter()
+            .find(|t| &t.id == tid)
+            .ok_or(KanoError::not_found("tool"))
+            .and_then(|tool| tool_command(tool).spawn().map_err(|e| KanoError::io("start companion tool", e)));
+        match result {
+            Ok(child) => children.push(child),
+            Err(error) => on_failure(HookFailure {
+                game_id: game_id.to_string(),
+                stage: "companion".to_string(),
+                index,
+                hook: tools.iter().find(|t| &t.id == tid).map(|t| t.name.clone()).unwrap_or_else(|| tid.clone()),
+                error: error.to_string(),
+            }),
+        }
+    }
+    children
+}
+
+fn run_hook(hook: &GameHook, exe: &Path, tools: &[ToolEntry]) -> Result<(), KanoError> {
+    let mut cmd = match (&hook.tool_id, &hook.command) {
+        (Some(tid), _) => {
+            let tool = tools.iter().find(|t| &t.id == tid).ok_or(KanoError::not_found("tool"))?;
+            tool_command(tool)
+        }
+        (None, Some(line)) => {
+            // `{exe}` and `{dir}` refer to the game, e.g. `robocopy "{dir}\\save" D:\\backup`
+            let exe_str = exe.to_string_lossy().to_string();
+            let dir_str = exe.parent().map(|d| d.to_string_lossy().to_string()).unwrap_or_default();
+            let parts: Vec<String> = split_command_line(line)
+                .into_iter()
+                .map(|t| t.replace("{exe}", &exe_str).replace("{dir}", &dir_str))
+                .collect();
+            let (program, args) = parts.split_first().ok_or(KanoError::invalid("hook_command_empty", "hook command is empty"))?;
+            let mut c = Command::new(program);
+            c.args(args);
+            if let Some(dir) = exe.parent() {
+                c.current_dir(dir);
+            }
+            c
+        }
+        (None, None) => return Err(KanoError::invalid("hook_command_empty", "hook has no command or tool")),
+    };
+
+    let mut child = cmd.spawn().map_err(|e| KanoError::io("start hook", e))?;
+    if !hook.wait {
+        return Ok(());
+    }
+    let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS));
+    let started = Instant::now();
+    loop {
+        match child.try_wait().map_err(|e| KanoError::io("wait for hook", e))? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(KanoError::Other { message: format!("exited with {}", status) }),
+            None if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(KanoError::Other { message: format!("timed out after {}s", timeout.as_secs()) });
+            }
+            None => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+// Tools launch the same way as from the Toolbox: bare exe, its folder as cwd
+fn tool_command(tool: &ToolEntry) -> Command {
+    let mut cmd = Command::new(&tool.path);
+    if let Some(parent) = Path::new(&tool.path).parent() {
+        cmd.current_dir(parent);
+    }
+    cmd
+}
+
+fn describe(hook: &GameHook, tools: &[ToolEntry]) -> String {
+    match (&hook.tool_id, &hook.command) {
+        (Some(tid), _) => tools
+            .iter()
+            .find(|t| &t.id == tid)
+            .map(|t| t.name.clone())
+            .unwrap_or_else(|| tid.clone()),
+        (None, Some(line)) => line.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn hook(command: &str, required: bool) -> GameHook {
+        GameHook { command: Some(command.into()), tool_id: None, wait: true, timeout_secs: None, required }
+    }
+
+    // runs the hooks for a game in a temp folder; returns the failures and the folder
+    fn run(hooks: &[GameHook], tools: &[ToolEntry]) -> (Result<(), KanoError>, Vec<HookFailure>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("kano-hooks-{}-{}", std::process::id(), uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let failures = RefCell::new(Vec::new());
+        let res = run_hooks(hooks, "pre_launch", "g", &dir.join("game.exe"), tools, &|f| failures.borrow_mut().push(f));
+        (res, failures.into_inner(), dir)
+    }
+
+    fn indexes(failures: &[HookFailure]) -> Vec<usize> {
+        failures.iter().map(|f| f.index).collect()
+    }
+
+    #[test]
+    fn hooks_without_a_command_fail() {
+        let empty = GameHook { command: Some("  ".into()), ..hook("", false) };
+        let none = GameHook { command: None, ..hook("", false) };
+        let missing_tool = GameHook { tool_id: Some("t1".into()), ..hook("", false) };
+        let (res, failures, dir) = run(&[empty, none, missing_tool], &[]);
+        assert!(res.is_ok());
+        assert_eq!(indexes(&failures), [0, 1, 2]);
+        assert_eq!(failures[2].hook, "t1");
+        assert!(failures.iter().all(|f| f.stage == "pre_launch" && f.game_id == "g"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hooks_run_in_order_in_the_game_folder() {
+        let hooks: Vec<GameHook> = (1..=3).map(|i| hook(&format!("sh -c \"echo {} >> order.log\"", i), false)).collect();
+        let (res, failures, dir) = run(&hooks, &[]);
+        assert!(res.is_ok() && failures.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("order.log")).unwrap(), "1\n2\n3\n");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_failing_required_hook_stops_the_rest() {
+        let hooks = [
+            hook("sh -c \"echo 1 >> order.log\"", false),
+            hook("sh -c \"exit 3\"", false),
+            hook("sh -c \"echo {exe} >> order.log\"", true),
+            hook("sh -c \"exit 4\"", true),
+            hook("sh -c \"echo 5 >> order.log\"", false),
+        ];
+        let (res, failures, dir) = run(&hooks, &[]);
+        assert!(matches!(res, Err(KanoError::InvalidInput { reason: "required_hook_failed", .. })), "{:?}", res);
+        assert_eq!(indexes(&failures), [1, 3]);
+        assert_eq!(failures[1].hook, "sh -c \"exit 4\"");
+        let expected = format!("1\n{}\n", dir.join("game.exe").display());
+        assert_eq!(fs::read_to_string(dir.join("order.log")).unwrap(), expected);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn slow_hooks_time_out() {
+        let slow = GameHook { timeout_secs: Some(0), ..hook("sleep 5", true) };
+        let started = Instant::now();
+        let (res, failures, dir) = run(&[slow], &[]);
+        assert!(res.is_err());
+        assert_eq!(failures[0].error, "timed out after 0s");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // a hook that isn't waited for can't fail after it started
+        let _ = fs::remove_dir_all(dir);
+        let (res, failures, dir) = run(&[GameHook { wait: false, ..hook("sh -c \"exit 1\"", true) }], &[]);
+        assert!(res.is_ok() && failures.is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+use crate::models::{IdlePolicy, IdleSpan};
+
+/// Focus and input state can only be read on Windows; elsewhere no policy is accepted
+pub const SUPPORTED: bool = cfg!(target_os = "windows");
+
+/// What the OS reports about the player at one sample
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputState {
+    pub foreground_pid: Option<u32>,
+    pub seconds_since_input: Option<u64>,
+}
+
+impl InputState {
+    pub fn current() -> Self {
+        InputState { foreground_pid: foreground_pid(), seconds_since_input: seconds_since_input() }
+    }
+}
+
+/// Watches focus and input while a game runs and collects the spans the player was away
+pub struct IdleTracker {
+    policy: IdlePolicy,
+    session_start: DateTime<Utc>,
+    unfocused_since: Option<DateTime<Utc>>,
+    current: Option<DateTime<Utc>>, // start of the open idle span
+    spans: Vec<IdleSpan>,
+}
+
+impl IdleTracker {
+    pub fn new(policy: IdlePolicy, session_start: DateTime<Utc>) -> Self {
+        IdleTracker { policy, session_start, unfocused_since: None, current: None, spans: Vec::new() }
+    }
+
+    /// Sample focus/input state; `game_pids` are the processes that count as the game
+    pub fn tick(&mut self, game_pids: &[u32]) {
+        self.tick_at(game_pids, InputState::current(), Utc::now())
+    }
+
+    pub fn tick_at(&mut self, game_pids: &[u32], input: InputState, now: DateTime<Utc>) {
+        let mut idle_from: Option<DateTime<Utc>> = None;
+
+        if let Some(minutes) = self.policy.unfocused_minutes {
+            match input.foreground_pid {
+                Some(pid) if !game_pids.contains(&pid) => {
+                    let since = *self.unfocused_since.get_or_insert(now);
+                    if now - since >= Duration::minutes(minutes as i64) {
+                        idle_from = Some(since);
+                    }
+                }
+                _ => self.unfocused_since = None,
+            }
+        }
+        if let Some(minutes) = self.policy.no_input_minutes {
+            if let Some(secs) = input.seconds_since_input {
+                if secs >= minutes * 60 {
+                    let since = now - Duration::seconds(secs as i64);
+                    idle_from = Some(idle_from.map_or(since, |t| t.min(since)));
+                }
+            }
+        }
+
+        match (idle_from, self.current) {
+            (Some(from), None) => {
+                // The span starts when the player actually left, not when the threshold hit,
+                // but never before the session or overlapping the previous span.
+                let mut start = from.max(self.session_start);
+                if let Some(last) = self.spans.last() {
+                    if let Ok(last_end) = DateTime::parse_from_rfc3339(&last.end) {
+                        start = start.max(last_end.with_timezone(&Utc));
+                    }
+                }
+                self.current = Some(start);
+            }
+            (None, Some(_)) => self.close(now),
+            _ => {}
+        }
+    }
+
+    /// Close any open span at `end` and return all idle spans with their total length in
+    /// seconds. Spans are cut off at `end`: time after it (e.g. waiting for a relaunch that
+    /// never came) isn't playtime anyway and mustn't be subtracted twice.
+    pub fn finish(mut self, end: DateTime<Utc>) -> (Vec<IdleSpan>, i64) {
+        if self.current.is_some_and(|start| start < end) {
+            self.close(end);
+        }
+        self.current = None;
+        let mut spans = Vec::new();
+        for mut span in self.spans {
+            let (Ok(start), Ok(span_end)) = (DateTime::parse_from_rfc3339(&span.start), DateTime::parse_from_rfc3339(&span.end)) else {
+                continue;
+            };
+            if start >= end {
+                continue;
+            }
+            if span_end > end {
+                span.end = end.to_rfc3339();
+                span.seconds = (end - start.with_timezone(&Utc)).num_seconds().max(0);
+            }
+            spans.push(span);
+        }
+        let total = spans.iter().map(|s| s.seconds).sum();
+        (spans, total)
+    }
+
+    fn close(&mut self, end: DateTime<Utc>) {
+        if let Some(start) = self.current.take() {
+            let seconds = (end - start).num_seconds().max(0);
+            self.spans.push(IdleSpan { start: start.to_rfc3339(), end: end.to_rfc3339(), seconds });
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn foreground_pid() -> Option<u32> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pid == 0 { None } else { Some(pid) }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn seconds_since_input() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+    unsafe {
+        let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+        if GetLastInputInfo(&mut info) == 0 {
+            return None;
+        }
+        // both are 32-bit millisecond tick counts that wrap after ~49 days
+        Some(GetTickCount().wrapping_sub(info.dwTime) as u64 / 1000)
+    }
+}
+
+// Focus and input state are only available on Windows; elsewhere the policy never triggers
+#[cfg(not(target_os = "windows"))]
+fn foreground_pid() -> Option<u32> {
+    None
+}
+
+#[cfg(not(target_os = "windows"))]
+fn seconds_since_input() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: u32 = 10;
+
+    fn at(start: DateTime<Utc>, secs: i64) -> DateTime<Utc> {
+        start + Duration::seconds(secs)
+    }
+
+    fn focused(pid: u32) -> InputState {
+        InputState { foreground_pid: Some(pid), seconds_since_input: Some(0) }
+    }
+
+    fn spans(spans: &[IdleSpan]) -> Vec<(String, String, i64)> {
+        spans.iter().map(|s| (s.start.clone(), s.end.clone(), s.seconds)).collect()
+    }
+
+    #[test]
+    fn unfocused_span_starts_when_focus_was_lost() {
+        let start = Utc::now();
+        let policy = IdlePolicy { unfocused_minutes: Some(5), no_input_minutes: None };
+        let mut t = IdleTracker::new(policy, start);
+        t.tick_at(&[GAME], focused(GAME), at(start, 60));
+        t.tick_at(&[GAME], focused(99), at(start, 120));
+        // just below the threshold: not idle yet
+        t.tick_at(&[GAME], focused(99), at(start, 120 + 299));
+        assert!(t.current.is_none());
+        t.tick_at(&[GAME], focused(99), at(start, 120 + 300));
+        assert_eq!(t.current, Some(at(start, 120)));
+        t.tick_at(&[GAME], focused(GAME), at(start, 1000));
+        let (s, total) = t.finish(at(start, 2000));
+        assert_eq!(spans(&s), [(at(start, 120).to_rfc3339(), at(start, 1000).to_rfc3339(), 880)]);
+        assert_eq!(total, 880);
+    }
+
+    #[test]
+    fn no_input_span_starts_at_the_last_input() {
+        let start = Utc::now();
+        let policy = IdlePolicy { unfocused_minutes: None, no_input_minutes: Some(10) };
+        let mut t = IdleTracker::new(policy, start);
+        let quiet = |secs| InputState { foreground_pid: Some(GAME), seconds_since_input: Some(secs) };
+        t.tick_at(&[GAME], quiet(599), at(start, 700));
+        assert!(t.current.is_none());
+        t.tick_at(&[GAME], quiet(600), at(start, 701));
+        assert_eq!(t.current, Some(at(start, 101)));
+        // input again: the span ends, a later one can't reach back into it
+        t.tick_at(&[GAME], quiet(0), at(start, 800));
+        t.tick_at(&[GAME], quiet(900), at(start, 1500));
+        let (s, total) = t.finish(at(start, 1600));
+        assert_eq!(
+            spans(&s),
+            [
+                (at(start, 101).to_rfc3339(), at(start, 800).to_rfc3339(), 699),
+                (at(start, 800).to_rfc3339(), at(start, 1600).to_rfc3339(), 800),
+            ]
+        );
+        assert_eq!(total, 1499);
+    }
+
+    #[test]
+    fn idle_before_the_session_is_not_counted() {
+        let start = Utc::now();
+        let policy = IdlePolicy { unfocused_minutes: None, no_input_minutes: Some(1) };
+        let mut t = IdleTracker::new(policy, start);
+        t.tick_at(&[GAME], InputState { foreground_pid: None, seconds_since_input: Some(3600) }, at(start, 30));
+        assert_eq!(t.current, Some(start));
+    }
+
+    #[test]
+    fn finish_cuts_spans_off_at_the_end() {
+        let start = Utc::now();
+        let policy = IdlePolicy { unfocused_minutes: Some(1), no_input_minutes: None };
+        let mut t = IdleTracker::new(policy, start);
+        t.tick_at(&[GAME], focused(99), at(start, 100));
+        t.tick_at(&[GAME], focused(99), at(start, 200));
+        t.tick_at(&[GAME], focused(GAME), at(start, 300));
+        // the game is gone and the tracker waits for a relaunch from 400 on
+        t.tick_at(&[], focused(99), at(start, 400));
+        t.tick_at(&[], focused(99), at(start, 500));
+        t.tick_at(&[], focused(99), at(start, 900));
+        let (s, total) = t.finish(at(start, 350));
+        assert_eq!(spans(&s), [(at(start, 100).to_rfc3339(), at(start, 300).to_rfc3339(), 200)]);
+        assert_eq!(total, 200);
+
+        let mut t = IdleTracker::new(IdlePolicy { unfocused_minutes: Some(1), no_input_minutes: None }, start);
+        t.tick_at(&[GAME], focused(99), at(start, 100));
+        t.tick_at(&[GAME], focused(99), at(start, 200));
+        let (s, total) = t.finish(at(start, 150));
+        assert_eq!(spans(&s), [(at(start, 100).to_rfc3339(), at(start, 150).to_rfc3339(), 50)]);
+        assert_eq!(total, 50);
+    }
+}
@@ -0,0 +1,284 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::error::KanoError;
+use crate::models::{GameEntry, LaunchProfile, RunnerEntry};
+
+/// Pick the profile to launch with: the requested one, else the game's default, else none
+/// (plain exe, parent dir as cwd).
+pub fn resolve_profile<'a>(game: Option<&'a GameEntry>, profile_id: Option<&str>) -> Result<Option<&'a LaunchProfile>, KanoError> {
+    let game = match game {
+        Some(g) => g,
+        None if profile_id.is_some() => return Err(KanoError::invalid("profile_needs_library_game", "launch profiles need a library game")),
+        None => return Ok(None),
+    };
+    match profile_id.or(game.default_profile.as_deref()) {
+        Some(pid) => match game.launch_profiles.iter().find(|p| p.id == pid) {
+            Some(p) => Ok(Some(p)),
+            // a stale default shouldn't block launching; an explicit id should
+            None if profile_id.is_none() => Ok(None),
+            None => Err(KanoError::not_found("launch_profile")),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Resolve the game's runner (compatibility wrapper) from the configured runners
+pub fn resolve_runner<'a>(game: Option<&GameEntry>, runners: &'a [RunnerEntry]) -> Result<Option<&'a RunnerEntry>, KanoError> {
+    match game.and_then(|g| g.runner_id.as_deref()) {
+        Some(rid) => runners
+            .iter()
+            .find(|r| r.id == rid)
+            .map(Some)
+            .ok_or(KanoError::not_found("runner")),
+        None => Ok(None),
+    }
+}
+
+/// Build the command for an exe with an optional profile and runner applied.
+/// A profile's own `run_as` wrapper takes precedence over the game's runner.
+pub fn build_command(exe: &Path, profile: Option<&LaunchProfile>, runner: Option<&RunnerEntry>) -> Result<Command, KanoError> {
+    let exe_dir = exe.parent().map(|p| p.to_path_buf());
+    let args: Vec<String> = profile.map(|p| p.args.clone()).unwrap_or_default();
+
+    let template = profile
+        .and_then(|p| p.run_as.as_deref())
+        .filter(|t| !t.trim().is_empty())
+        .or(runner.map(|r| r.command.as_str()));
+    let wrapped = template
+        .map(|t| expand_template(t, exe, &args))
+        .unwrap_or_default();
+    let mut cmd = match wrapped.split_first() {
+        Some((program, rest)) => {
+            let mut c = Command::new(program);
+            c.args(rest);
+            c
+        }
+        None => {
+            let mut c = Command::new(exe);
+            c.args(&args);
+            c
+        }
+    };
+
+    // Default working directory is the exe's parent so games that use relative
+    // paths for resources can find them; a relative profile cwd is resolved from there.
+    let mut cwd = exe_dir.clone();
+    if let Some(p) = profile {
+        cmd.envs(&p.env);
+        if let Some(dir) = p.cwd.as_deref().filter(|d| !d.trim().is_empty()) {
+            let dir = PathBuf::from(dir);
+            cwd = Some(match &exe_dir {
+                Some(base) if dir.is_relative() => base.join(dir),
+                _ => dir,
+            });
+        }
+    }
+    if let Some(dir) = cwd {
+        if !dir.is_dir() {
+            return Err(KanoError::not_found("working_dir"));
+        }
+        cmd.current_dir(dir);
+    }
+    Ok(cmd)
+}
+
+/// Expand a wrapper template such as `"C:\LE\LEProc.exe" -run {exe} {args}` or `wine`.
+/// `{exe}` is the game path, `{dir}` its folder and `{args}` the profile arguments;
+/// when `{exe}` / `{args}` are missing they are appended.
+pub fn expand_template(template: &str, exe: &Path, args: &[String]) -> Vec<String> {
+    let mut tokens = split_command_line(template);
+    if tokens.is_empty() {
+        return tokens;
+    }
+    if !tokens.iter().any(|t| t.contains("{exe}")) {
+        tokens.push("{exe}".into());
+    }
+    if !tokens.iter().any(|t| t == "{args}") {
+        tokens.push("{args}".into());
+    }
+    let exe_str = exe.to_string_lossy().to_string();
+    let dir_str = exe.parent().map(|d| d.to_string_lossy().to_string()).unwrap_or_default();
+    let mut out = Vec::new();
+    for t in tokens {
+        if t == "{args}" {
+            out.extend(args.iter().cloned());
+        } else {
+            out.push(t.replace("{exe}", &exe_str).replace("{dir}", &dir_str));
+        }
+    }
+    out
+}
+
+/// Split a command line on whitespace, keeping double-quoted parts together:
+/// `"C:\Program Files\LE\LEProc.exe" -run` -> [`C:\Program Files\LE\LEProc.exe`, `-run`]
+pub fn split_command_line(s: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut cur = String::new();
+    let mut in_quotes = false;
+    let mut has_part = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_part = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_part {
+                    parts.push(std::mem::take(&mut cur));
+                    has_part = false;
+                }
+            }
+            c => {
+                cur.push(c);
+                has_part = true;
+            }
+        }
+    }
+    if has_part {
+        parts.push(cur);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn game(default_profile: Option<&str>, runner_id: Option<&str>) -> GameEntry {
+        serde_json::from_value(serde_json::json!({
+            "id": "g", "name": "G", "path": "C:/G/g.exe",
+            "default_profile": default_profile,
+            "runner_id": runner_id,
+            "launch_profiles": [
+                { "id": "windowed", "name": "Windowed", "args": ["-windowed"] },
+                { "id": "debug", "name": "Debug", "args": ["-debug"], "env": { "LOG": "1" } },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn temp_game_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kano-launch-{}-{}", std::process::id(), uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        dir
+    }
+
+    fn args(cmd: &Command) -> Vec<&OsStr> {
+        cmd.get_args().collect()
+    }
+
+    #[test]
+    fn profile_resolution() {
+        let plain = game(None, None);
+        let with_default = game(Some("debug"), None);
+        let stale_default = game(Some("removed"), None);
+        let id = |p: Result<Option<&LaunchProfile>, KanoError>| p.unwrap().map(|p| p.id.clone());
+
+        assert_eq!(id(resolve_profile(Some(&plain), None)), None);
+        assert_eq!(id(resolve_profile(Some(&plain), Some("windowed"))), Some("windowed".into()));
+        assert_eq!(id(resolve_profile(Some(&with_default), None)), Some("debug".into()));
+        // the requested profile wins over the default
+        assert_eq!(id(resolve_profile(Some(&with_default), Some("windowed"))), Some("windowed".into()));
+        assert_eq!(id(resolve_profile(Some(&stale_default), None)), None);
+        assert!(matches!(resolve_profile(Some(&stale_default), Some("removed")), Err(KanoError::NotFound { .. })));
+        assert_eq!(id(resolve_profile(None, None)), None);
+        assert!(matches!(resolve_profile(None, Some("windowed")), Err(KanoError::InvalidInput { reason: "profile_needs_library_game", .. })));
+    }
+
+    #[test]
+    fn command_for_a_profile() {
+        let dir = temp_game_dir();
+        let exe = dir.join("g.exe");
+
+        let cmd = build_command(&exe, None, None).unwrap();
+        assert_eq!(cmd.get_program(), exe.as_os_str());
+        assert!(args(&cmd).is_empty());
+        assert_eq!(cmd.get_current_dir(), Some(dir.as_path()));
+
+        let g = game(None, None);
+        let debug = g.launch_profiles.iter().find(|p| p.id == "debug").unwrap();
+        let cmd = build_command(&exe, Some(debug), None).unwrap();
+        assert_eq!(args(&cmd), ["-debug"]);
+        assert_eq!(cmd.get_envs().collect::<Vec<_>>(), [(OsStr::new("LOG"), Some(OsStr::new("1")))]);
+
+        // a relative cwd is taken from the exe's folder; one that doesn't exist is refused
+        let mut profile = debug.clone();
+        profile.cwd = Some("data".into());
+        let cmd = build_command(&exe, Some(&profile), None).unwrap();
+        assert_eq!(cmd.get_current_dir(), Some(dir.join("data").as_path()));
+        profile.cwd = Some("missing".into());
+        assert!(matches!(build_command(&exe, Some(&profile), None), Err(KanoError::NotFound { .. })));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn runner(id: &str, command: &str) -> RunnerEntry {
+        RunnerEntry { id: id.into(), name: id.into(), command: command.into() }
+    }
+
+    #[test]
+    fn runner_resolution() {
+        let runners = [runner("le", "LEProc.exe -run"), runner("wine", "wine")];
+        let id = |r: Result<Option<&RunnerEntry>, KanoError>| r.unwrap().map(|r| r.id.clone());
+        assert_eq!(id(resolve_runner(Some(&game(None, Some("wine"))), &runners)), Some("wine".into()));
+        assert_eq!(id(resolve_runner(Some(&game(None, None)), &runners)), None);
+        assert_eq!(id(resolve_runner(None, &runners)), None);
+        assert!(matches!(resolve_runner(Some(&game(None, Some("gone"))), &runners), Err(KanoError::NotFound { .. })));
+    }
+
+    #[test]
+    fn command_lines_keep_quoted_parts_together() {
+        let cases: [(&str, &[&str]); 6] = [
+            (r#""C:\Program Files\LE\LEProc.exe" -run"#, &[r"C:\Program Files\LE\LEProc.exe", "-run"]),
+            ("  wine   --bottle  x ", &["wine", "--bottle", "x"]),
+            (r#"tool --name="a b" c"#, &["tool", "--name=a b", "c"]),
+            (r#"tool "" c"#, &["tool", "", "c"]),
+            (r#"tool "unterminated part"#, &["tool", "unterminated part"]),
+            ("", &[]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(split_command_line(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn template_substitution() {
+        let exe = Path::new("/games/My Game/game.exe");
+        let args = vec!["-a".to_string(), "b c".to_string()];
+        let cases: [(&str, &[&str]); 5] = [
+            // {exe} and {args} are appended when the template doesn't place them
+            ("wine", &["wine", "/games/My Game/game.exe", "-a", "b c"]),
+            (r#""/opt/le/LEProc.exe" -run {exe} {args}"#, &["/opt/le/LEProc.exe", "-run", "/games/My Game/game.exe", "-a", "b c"]),
+            ("proton run {args} {exe}", &["proton", "run", "-a", "b c", "/games/My Game/game.exe"]),
+            ("wrap --game={exe} --cwd={dir}", &["wrap", "--game=/games/My Game/game.exe", "--cwd=/games/My Game", "-a", "b c"]),
+            ("   ", &[]),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(expand_template(template, exe, &args), expected, "{:?}", template);
+        }
+    }
+
+    #[test]
+    fn profile_wrapper_takes_precedence_over_the_runner() {
+        let dir = temp_game_dir();
+        let exe = dir.join("g.exe");
+        let wine = runner("wine", "wine");
+        let g = game(None, Some("wine"));
+        let mut profile = g.launch_profiles[0].clone();
+
+        let cmd = build_command(&exe, Some(&profile), Some(&wine)).unwrap();
+        assert_eq!(cmd.get_program(), "wine");
+        assert_eq!(args(&cmd), [exe.as_os_str(), OsStr::new("-windowed")]);
+        assert_eq!(cmd.get_current_dir(), Some(dir.as_path()));
+
+        profile.run_as = Some("env LANG=ja_JP.UTF-8 {exe}".into());
+        let cmd = build_command(&exe, Some(&profile), Some(&wine)).unwrap();
+        assert_eq!(cmd.get_program(), "env");
+        assert_eq!(args(&cmd), [OsStr::new("LANG=ja_JP.UTF-8"), exe.as_os_str(), OsStr::new("-windowed")]);
+
+        // a blank wrapper falls back to the runner
+        profile.run_as = Some(" ".into());
+        assert_eq!(build_command(&exe, Some(&profile), Some(&wine)).unwrap().get_program(), "wine");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,48 +1,137 @@
 // Tauri backend with modular structure
 mod models;
 mod config;
+mod data_dir;
+mod migrations;
+mod db;
+mod store;
+mod error;
 mod state;
 mod utils;
+mod exe_ranking;
+mod process_tree;
+mod idle;
+mod launch;
+mod hooks;
+mod saves;
+mod sync;
+mod metadata;
+mod bangumi;
+mod bangumi_cache;
+mod vndb;
+mod auto_match;
+mod game_query;
 mod commands;
 
 use state::RunningProcesses;
 use commands::*;
+use tauri::Manager;
 
 use std::sync::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            data_dir::init(app.path().app_data_dir().ok(), app.path().app_config_dir().ok());
+            // on failure the database won't import the old files until a later start
+            // gets through (db::import_json_files), so nothing is read in an outdated format
+            if let Err(e) = migrations::run(&migrations::MigrationContext::current()) {
+                eprintln!("data migration failed (see {}): {}", migrations::LOG_FILE, e);
+            }
+            if let Err(e) = db::backup_database() {
+                eprintln!("database backup failed: {}", e);
+            }
+            Ok(())
+        })
         .manage(RunningProcesses {
             processes: Mutex::new(HashMap::new()),
+            killed: Mutex::new(HashSet::new()),
         })
         .invoke_handler(tauri::generate_handler![
             greet,
             launch_exe,
+            launch_game_by_id,
             kill_game,
+            kill_game_by_id,
+            set_game_watch_process,
+            save_launch_profile,
+            remove_launch_profile,
+            set_default_launch_profile,
+            set_game_runner,
+            set_game_hooks,
+            set_game_companions,
+            set_game_vndb_id,
             pick_exe,
             pick_folder_and_scan,
+            list_exes,
+            find_missing_games,
+            relocate_games,
+            relocate_games_by_scan,
             add_game,
             list_games,
+            query_games,
             remove_game,
-            search_bangumi,
-            get_bangumi_subject,
+            remove_game_by_id,
             update_game_image,
+            update_game_image_by_id,
             update_game_info,
+            update_game_info_by_id,
+            update_game_path,
             update_game_playtime,
+            update_game_playtime_by_id,
             set_access_token,
             get_access_token,
-            load_cache,
-            save_cache,
-            download_image,
+            clear_bangumi_cache,
+            get_bangumi_cache_stats,
+            list_sessions,
+            update_session,
+            delete_session,
+            recompute_playtime,
+            set_game_save_dirs,
+            backup_game_saves,
+            list_save_snapshots,
+            restore_save_snapshot,
+            delete_save_snapshot,
+            export_library,
+            import_library,
+            get_sync_settings,
+            set_sync_settings,
+            sync_now,
+            get_storage_health,
+            restore_storage_backup,
+            dismiss_storage_issue,
+            get_data_dir,
+            move_data_dir,
+            search_metadata,
+            get_metadata,
+            download_metadata_cover,
+            get_vndb_releases,
+            set_game_metadata,
+            refresh_game_metadata,
+            auto_match_games,
+            list_match_reviews,
+            resolve_match_review,
             list_notes,
             get_note,
             save_note,
             delete_note,
             delete_note_by_index,
             get_project_root,
+            get_exe_keyword_weights,
+            set_exe_keyword_weights,
+            get_idle_policy,
+            set_idle_policy,
+            get_save_backup_policy,
+            set_save_backup_policy,
+            get_metadata_settings,
+            set_metadata_settings,
+            get_runners,
+            add_runner,
+            update_runner,
+            remove_runner,
             get_tools,
             add_tool,
             remove_tool,
@@ -53,9 +142,19 @@ pub fn run() {
             add_custom_tag,
             remove_custom_tag,
             add_tag_to_game,
+            add_tag_to_game_by_id,
             remove_tag_from_game,
+            remove_tag_from_game_by_id,
             get_games_count_by_tag
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            // responses still waiting for the delayed cache write
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = bangumi_cache::flush() {
+                    eprintln!("failed to save bangumi cache: {}", e);
+                }
+            }
+        });
 }
@@ -0,0 +1,277 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::bangumi_cache::CachePolicy;
+use crate::config::images_dir_path;
+use crate::error::KanoError;
+use crate::models::{GameEntry, MetadataSettings};
+
+// Game information sources (Bangumi, VNDB) behind one interface. Providers translate
+// their own responses into GameMetadata so the frontend never sees provider JSON.
+
+/// Identifies the app to the APIs; Bangumi asks for `user/app/version (url)`
+pub const USER_AGENT: &str = concat!("shiodd/kano/", env!("CARGO_PKG_VERSION"), " (https://github.com/shiodd/testGalManager)");
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataSource {
+    #[default]
+    Bangumi,
+    Vndb,
+}
+
+impl MetadataSource {
+    /// Whether `id` has this source's format: digits for Bangumi, "v17" for VNDB.
+    /// Ids end up in file names, so anything else is refused.
+    pub fn is_valid_id(self, id: &str) -> bool {
+        let digits = match self {
+            MetadataSource::Bangumi => id,
+            MetadataSource::Vndb => id.strip_prefix('v').unwrap_or_default(),
+        };
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+    }
+}
+
+/// A game's information as any provider describes it
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GameMetadata {
+    pub source: MetadataSource,
+    pub id: String,                 // 来源内的 id：Bangumi 为数字，VNDB 为 "v17"
+    pub url: String,                // 条目网页
+    pub title: String,              // 原名
+    pub titles: Vec<LocalizedTitle>, // 各语言标题（含官方译名）
+    pub aliases: Vec<String>,
+    pub developers: Vec<String>,
+    pub publishers: Vec<String>,
+    pub release_date: Option<String>, // "2004-08-29"，可能只有年或年月
+    pub platforms: Vec<String>,
+    pub genre: Option<String>,        // 游戏类型，如 "AVG"
+    pub summary: Option<String>,
+    pub staff: Vec<StaffCredit>,      // 制作人员
+    pub cover: Option<CoverUrls>,
+    pub tags: Vec<String>,            // 来源的分类标签，按相关度排序
+    pub rating: Option<f64>,          // 0-10
+    pub external_ids: ExternalIds,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LocalizedTitle {
+    pub lang: String, // "zh-Hans"、"ja"、"en" ...
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CoverUrls {
+    pub large: String,
+    pub medium: Option<String>,
+    pub small: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StaffCredit {
+    pub role: String, // "剧本"、"原画"、"音乐" ...
+    pub name: String,
+}
+
+/// The entry's ids on every source that is known
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ExternalIds {
+    pub bangumi: Option<i64>,
+    pub vndb: Option<String>,
+}
+
+impl GameMetadata {
+    /// The Chinese title when there is one, else the original
+    pub fn display_title(&self) -> &str {
+        self.titles
+            .iter()
+            .find(|t| t.lang == "zh-Hans" || t.lang == "zh-Hant")
+            .map(|t| t.title.as_str())
+            .unwrap_or(&self.title)
+    }
+
+    /// Store the record on `game` and link the game to the entry. Ids from other
+    /// sources only fill in links the game doesn't have yet.
+    pub fn attach_to(self, game: &mut GameEntry) {
+        match self.source {
+            MetadataSource::Bangumi => game.subject_id = self.id.parse().ok(),
+            MetadataSource::Vndb => game.vndb_id = Some(self.id.clone()),
+        }
+        if game.subject_id.is_none() {
+            game.subject_id = self.external_ids.bangumi;
+        }
+        if game.vndb_id.is_none() {
+            game.vndb_id = self.external_ids.vndb.clone();
+        }
+        game.metadata = Some(self);
+    }
+
+    /// Every title and alias, for matching
+    pub fn all_titles(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.title.as_str())
+            .chain(self.titles.iter().map(|t| t.title.as_str()))
+            .chain(self.aliases.iter().map(String::as_str))
+            .filter(|t| !t.is_empty())
+    }
+}
+
+pub type MetadataFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, KanoError>> + Send + 'a>>;
+
+pub trait MetadataProvider: Send + Sync {
+    /// Games matching `query`, best match first
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> MetadataFuture<'a, Vec<GameMetadata>>;
+
+    fn fetch_details<'a>(&'a self, id: &'a str) -> MetadataFuture<'a, GameMetadata>;
+
+    /// Download the cover to kano_data/images/{id}.{ext}; returns the path the way
+    /// GameEntry.image stores it
+    fn fetch_cover<'a>(&'a self, metadata: &'a GameMetadata) -> MetadataFuture<'a, String> {
+        Box::pin(async move {
+            if !metadata.source.is_valid_id(&metadata.id) {
+                return Err(KanoError::invalid("metadata_id", format!("invalid {:?} id: {}", metadata.source, metadata.id)));
+            }
+            let url = metadata.cover.as_ref().map(|c| c.large.as_str()).filter(|u| !u.is_empty());
+            let url = url.ok_or(KanoError::not_found("cover"))?;
+            save_image(url, &metadata.id).await
+        })
+    }
+}
+
+/// The provider for `source`, pointed at the configured API addresses. With `refresh`,
+/// cached responses are revalidated even while they are fresh.
+pub fn provider_for(source: MetadataSource, settings: &MetadataSettings, refresh: bool) -> Box<dyn MetadataProvider> {
+    match source {
+        MetadataSource::Bangumi => Box::new(
+            crate::bangumi::BangumiClient::new(&settings.bangumi_api_url, crate::config::load_token())
+                .with_cache(CachePolicy::from_settings(settings, refresh)),
+        ),
+        MetadataSource::Vndb => Box::new(crate::vndb::VndbClient::new(&settings.vndb_api_url)),
+    }
+}
+
+/// One client for all metadata requests, so connections are reused
+pub fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// A release date as ISO `YYYY[-MM[-DD]]`, so dates compare as strings:
+/// "2019年6月28日" -> "2019-06-28", "2019/6" -> "2019-06". Text after the date
+/// ("（初回版）") is ignored; None without a four-digit year.
+pub fn iso_date(raw: &str) -> Option<String> {
+    let mut groups = Vec::new();
+    let mut rest = raw.trim();
+    while groups.len() < 3 {
+        let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if end == 0 {
+            break;
+        }
+        groups.push(&rest[..end]);
+        let mut chars = rest[end..].chars();
+        match chars.next() {
+            Some(c) if "-/.年月".contains(c) => rest = chars.as_str(),
+            _ => break,
+        }
+    }
+    let year = groups.first().filter(|y| y.len() == 4)?;
+    let mut date = year.to_string();
+    for (part, max) in groups.iter().skip(1).zip([12, 31]) {
+        match part.parse::<u32>() {
+            Ok(n) if part.len() <= 2 && (1..=max).contains(&n) => date.push_str(&format!("-{:02}", n)),
+            _ => break,
+        }
+    }
+    Some(date)
+}
+
+/// Download an image into kano_data/images as `{stem}.{ext}`; returns the relative path
+pub async fn save_image(url: &str, stem: &str) -> Result<String, KanoError> {
+    let resp = http_client().get(url).send().await?;
+    if !resp.status().is_success() {
+        return Err(KanoError::Http { status: resp.status().as_u16(), message: String::new() });
+    }
+    let bytes = resp.bytes().await?;
+
+    // Determine file extension from URL
+    let ext = if url.contains(".jpg") || url.contains(".jpeg") {
+        "jpg"
+    } else if url.contains(".png") {
+        "png"
+    } else if url.contains(".webp") {
+        "webp"
+    } else {
+        "jpg" // default
+    };
+
+    // async so the runtime isn't blocked
+    let _writing = crate::data_dir::begin_write()?;
+    let images_dir = images_dir_path();
+    tokio::fs::create_dir_all(&images_dir).await.map_err(|e| KanoError::io("create images dir", e))?;
+    let filename = format!("{}.{}", stem, ext);
+    tokio::fs::write(images_dir.join(&filename), bytes).await?;
+
+    // Return the relative path for easier portability
+    Ok(format!("kano_data/images/{}", filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_must_match_their_source() {
+        assert!(MetadataSource::Bangumi.is_valid_id("12345"));
+        assert!(MetadataSource::Vndb.is_valid_id("v17"));
+        for id in ["", "v17", "12a", "../../x", "1/2", "1.jpg"] {
+            assert!(!MetadataSource::Bangumi.is_valid_id(id), "{:?}", id);
+        }
+        for id in ["", "v", "17", "V17", "v1/..", "v17.png", "r17"] {
+            assert!(!MetadataSource::Vndb.is_valid_id(id), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn release_dates_become_iso() {
+        let cases = [
+            ("2019-06-28", Some("2019-06-28")),
+            ("2019年6月28日", Some("2019-06-28")),
+            ("2019年6月", Some("2019-06")),
+            ("2019年", Some("2019")),
+            ("2019/6/8", Some("2019-06-08")),
+            ("2019.06.28", Some("2019-06-28")),
+            ("2019-06-28（初回限定版）", Some("2019-06-28")),
+            (" 2004-08 ", Some("2004-08")),
+            ("2019-13-01", Some("2019")),
+            ("2019-06-32", Some("2019-06")),
+            ("TBA", None),
+            ("19-06-28", None),
+            ("", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(iso_date(input).as_deref(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn covers_with_bad_ids_are_not_downloaded() {
+        let meta = GameMetadata {
+            source: MetadataSource::Vndb,
+            id: "../../escape".into(),
+            cover: Some(CoverUrls { large: "http://127.0.0.1:9/x.jpg".into(), ..Default::default() }),
+            ..Default::default()
+        };
+        let provider = crate::vndb::VndbClient::new("");
+        let err = tauri::async_runtime::block_on(provider.fetch_cover(&meta)).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+    }
+}
@@ -0,0 +1,557 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde_json::{Map, Value};
+use crate::data_dir::{data_dir, exe_dir};
+use crate::error::KanoError;
+use crate::store::{self, write_atomic, LoadError};
+
+// Startup migrations of the data directory. Each runs once, in order, before anything
+// else touches kano_data; the last one applied is recorded in kano_data/schema_version
+// and what they did is appended to kano_data/migrations.log.
+// The SQLite schema is versioned separately (db.rs).
+
+pub const SCHEMA_VERSION_FILE: &str = "schema_version";
+pub const LOG_FILE: &str = "migrations.log";
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    run: fn(&MigrationContext, &mut Vec<String>) -> Result<(), KanoError>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "move files from legacy locations", run: move_legacy_files },
+    Migration { version: 2, name: "assign ids to games", run: assign_game_ids },
+    Migration { version: 3, name: "link legacy notes to games", run: convert_legacy_notes },
+    Migration { version: 4, name: "move tools out of tauri_config.json", run: move_tools_out_of_config },
+    Migration { version: 5, name: "move the access token out of tauri_config.json", run: move_token_out_of_config },
+    Migration { version: 6, name: "store cached Bangumi details on games", run: copy_cached_metadata },
+];
+
+/// Where migrations look; tests point it at temp dirs
+pub struct MigrationContext {
+    pub data_dir: PathBuf,
+    /// Folders older versions kept data in, most preferred first
+    pub legacy_dirs: Vec<PathBuf>,
+}
+
+impl MigrationContext {
+    /// The real locations: the exe's folder and, as very old versions did, the cwd's parent
+    pub fn current() -> Self {
+        let mut legacy_dirs = vec![exe_dir()];
+        if let Some(parent) = std::env::current_dir().ok().and_then(|d| d.parent().map(Path::to_path_buf)) {
+            if !legacy_dirs.contains(&parent) {
+                legacy_dirs.push(parent);
+            }
+        }
+        MigrationContext { data_dir: data_dir(), legacy_dirs }
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+}
+
+pub fn schema_version(data_dir: &Path) -> u32 {
+    fs::read_to_string(data_dir.join(SCHEMA_VERSION_FILE))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Whether the data directory still needs migrations that haven't run (or failed)
+pub fn is_pending(data_dir: &Path) -> bool {
+    MIGRATIONS.iter().any(|m| m.version > schema_version(data_dir))
+}
+
+/// Apply the pending migrations and return the version reached. A file a migration can't
+/// parse is quarantined like Store::load does and the migration carries on without it;
+/// any other failure stops the run (logged with the migration it came from) and that
+/// migration is tried again on the next start.
+pub fn run(ctx: &MigrationContext) -> Result<u32, KanoError> {
+    let mut version = schema_version(&ctx.data_dir);
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    if pending.is_empty() {
+        return Ok(version);
+    }
+    fs::create_dir_all(&ctx.data_dir).map_err(|e| KanoError::io(&format!("create {}", ctx.data_dir.display()), e))?;
+    for m in pending {
+        let mut log = Vec::new();
+        let res = loop {
+            let res = (m.run)(ctx, &mut log);
+            // an unreadable file would fail this step on every start
+            let Err(KanoError::Corrupt { path, message }) = &res else {
+                break res;
+            };
+            let path = PathBuf::from(path);
+            match store::quarantine(&path, message.clone()) {
+                LoadError::Corrupt { quarantined: Some(q), .. } => log.push(format!("quarantined {}: moved to {}", path.display(), q)),
+                _ => break res,
+            }
+        };
+        if let Err(e) = &res {
+            log.push(format!("failed: {}", e));
+        }
+        write_log(ctx, m, &log);
+        res?;
+        version = m.version;
+        write_atomic(&ctx.file(SCHEMA_VERSION_FILE), version.to_string().as_bytes())?;
+    }
+    Ok(version)
+}
+
+fn write_log(ctx: &MigrationContext, m: &Migration, lines: &[String]) {
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let mut text = format!("{} migration {}: {}\n", now, m.version, m.name);
+    for l in lines {
+        text.push_str(&format!("    {}\n", l));
+    }
+    eprint!("{}", text);
+    let res = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ctx.file(LOG_FILE))
+        .and_then(|mut f| f.write_all(text.as_bytes()));
+    if let Err(e) = res {
+        eprintln!("failed to write {}: {}", LOG_FILE, e);
+    }
+}
+
+// ---- 1: files kept next to the exe (or in the cwd's parent) by older versions ----
+
+fn move_legacy_files(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    // (file in kano_data, where it used to be relative to a legacy dir)
+    const FILES: &[(&str, &str)] = &[
+        ("games_db.json", "game_data/games_db.json"),
+        ("tauri_config.json", "tauri_config.json"),
+        ("tools.json", "tools.json"),
+        ("notes.json", "notes.json"),
+        ("tokens.json", "tokens.json"),
+    ];
+    for (name, old) in FILES {
+        let target = ctx.file(name);
+        if target.exists() {
+            continue;
+        }
+        if let Some(src) = ctx.legacy_dirs.iter().map(|d| d.join(old)).find(|p| p.is_file()) {
+            move_file(&src, &target)?;
+            log.push(format!("moved {} -> {}", src.display(), target.display()));
+        }
+    }
+    Ok(())
+}
+
+// rename, or copy + delete when the old location is on another drive
+fn move_file(from: &Path, to: &Path) -> Result<(), KanoError> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| KanoError::io(&format!("copy {}", from.display()), e))?;
+    fs::remove_file(from).map_err(|e| KanoError::io(&format!("remove {}", from.display()), e))
+}
+
+// The remaining migrations edit the JSON directly instead of going through the models,
+// so fields the models no longer have can still be read.
+
+fn read_object(path: &Path) -> Result<Option<Map<String, Value>>, KanoError> {
+    let Ok(s) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    match serde_json::from_str(&s) {
+        Ok(Value::Object(m)) => Ok(Some(m)),
+        _ => Err(corrupt(path, "not a JSON object")),
+    }
+}
+
+fn corrupt(path: &Path, e: impl std::fmt::Display) -> KanoError {
+    KanoError::Corrupt { path: path.display().to_string(), message: format!("{} is unreadable: {}", path.display(), e) }
+}
+
+fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), KanoError> {
+    write_atomic(path, &serde_json::to_vec_pretty(value)?)
+}
+
+// ---- 2: games_db.json entries from before games had stable ids ----
+
+fn assign_game_ids(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    let path = ctx.file("games_db.json");
+    let Some(mut db) = read_object(&path)? else {
+        return Ok(());
+    };
+    let mut assigned = 0;
+    if let Some(games) = db.get_mut("games").and_then(Value::as_array_mut) {
+        for g in games.iter_mut().filter_map(Value::as_object_mut) {
+            if g.get("id").and_then(Value::as_str).is_none_or(str::is_empty) {
+                g.insert("id".into(), Value::String(uuid::Uuid::new_v4().to_string()));
+                assigned += 1;
+            }
+        }
+    }
+    if assigned > 0 {
+        write_json(&path, &db)?;
+        log.push(format!("assigned ids to {} games in {}", assigned, path.display()));
+    }
+    Ok(())
+}
+
+// ---- 3: notes that reference their game by `game_path` ----
+
+fn convert_legacy_notes(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    let path = ctx.file("notes.json");
+    let Ok(s) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let mut notes: Vec<Value> = serde_json::from_str(&s).map_err(|e| corrupt(&path, e))?;
+    // path -> (id, name) from the games file, which is still JSON at this point
+    let games: Vec<(String, String, String)> = read_object(&ctx.file("games_db.json"))
+        .unwrap_or_default()
+        .and_then(|mut db| db.remove("games"))
+        .and_then(|g| serde_json::from_value::<Vec<Map<String, Value>>>(g).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|g| {
+            let field = |k: &str| g.get(k).and_then(Value::as_str).unwrap_or_default().to_string();
+            (field("path"), field("id"), field("name"))
+        })
+        .collect();
+    let (mut converted, mut linked) = (0, 0);
+    for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+        let Some(game_path) = note.remove("game_path") else {
+            continue;
+        };
+        converted += 1;
+        if note.get("game_id").is_some_and(|v| !v.is_null()) {
+            continue;
+        }
+        let game = game_path
+            .as_str()
+            .and_then(|gp| games.iter().find(|(p, id, _)| p == gp && !id.is_empty()));
+        if let Some((_, id, name)) = game {
+            note.insert("game_id".into(), Value::String(id.clone()));
+            note.insert("game_name".into(), Value::String(name.clone()));
+            linked += 1;
+        }
+    }
+    if converted > 0 {
+        write_json(&path, &notes)?;
+        log.push(format!("converted {} notes in {} ({} linked to their game)", converted, path.display(), linked));
+    }
+    Ok(())
+}
+
+// ---- 4 and 5: fields that used to live in tauri_config.json ----
+
+fn read_config(ctx: &MigrationContext) -> Result<Option<Map<String, Value>>, KanoError> {
+    read_object(&ctx.file("tauri_config.json"))
+}
+
+fn move_tools_out_of_config(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    let Some(mut cfg) = read_config(ctx)? else {
+        return Ok(());
+    };
+    let Some(tools) = cfg.remove("tools") else {
+        return Ok(());
+    };
+    let count = tools.as_array().map_or(0, |a| a.len());
+    let tools_path = ctx.file("tools.json");
+    // once tools.json (or the database it was imported into) exists, the config copy is stale
+    if count > 0 && !tools_path.exists() && !ctx.file("kano.db").exists() {
+        write_json(&tools_path, &tools)?;
+        log.push(format!("moved {} tools to {}", count, tools_path.display()));
+    } else if count > 0 {
+        log.push(format!("dropped {} outdated tools from tauri_config.json", count));
+    }
+    write_json(&ctx.file("tauri_config.json"), &cfg)
+}
+
+fn move_token_out_of_config(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    let Some(mut cfg) = read_config(ctx)? else {
+        return Ok(());
+    };
+    let Some(token) = cfg.remove("access_token") else {
+        return Ok(());
+    };
+    let token = token.as_str().map(str::trim).unwrap_or_default().to_string();
+    let tokens_path = ctx.file("tokens.json");
+    let has_token = fs::read_to_string(&tokens_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+        .and_then(|v| v.get("access_token").and_then(|t| t.as_str()).map(|t| !t.trim().is_empty()))
+        .unwrap_or(false);
+    if !token.is_empty() && !has_token {
+        write_json(&tokens_path, &serde_json::json!({ "access_token": token }))?;
+        log.push(format!("moved the access token to {}", tokens_path.display()));
+    }
+    write_json(&ctx.file("tauri_config.json"), &cfg)
+}
+
+// ---- 6: details the frontend kept in bangumi_cache.json become GameEntry.metadata ----
+
+fn copy_cached_metadata(ctx: &MigrationContext, log: &mut Vec<String>) -> Result<(), KanoError> {
+    let path = ctx.file("games_db.json");
+    let Some(mut db) = read_object(&path)? else {
+        return Ok(());
+    };
+    // the cache can always be rebuilt, so an unreadable one is just skipped
+    let Ok(Some(cache)) = read_object(&ctx.file("bangumi_cache.json")) else {
+        return Ok(());
+    };
+    let mut copied = 0;
+    if let Some(games) = db.get_mut("games").and_then(Value::as_array_mut) {
+        for g in games.iter_mut().filter_map(Value::as_object_mut) {
+            if g.get("metadata").is_some_and(|m| !m.is_null()) {
+                continue;
+            }
+            let Some(sid) = g.get("subject_id").and_then(Value::as_i64) else {
+                continue;
+            };
+            // entries cached before the metadata format are raw Bangumi subjects
+            let meta = match cache.get(&format!("subject:{}", sid)) {
+                Some(meta) if meta.get("source").is_some() => meta.clone(),
+                Some(subject) => match crate::bangumi::metadata_from_json(subject.clone()) {
+                    Some(m) => serde_json::to_value(m)?,
+                    None => continue,
+                },
+                None => continue,
+            };
+            g.insert("metadata".into(), meta);
+            copied += 1;
+        }
+    }
+    if copied > 0 {
+        write_json(&path, &db)?;
+        log.push(format!("copied cached details to {} games in {}", copied, path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_ctx() -> MigrationContext {
+        let root = std::env::temp_dir().join(format!("kano-migrate-{}-{}", std::process::id(), uuid::Uuid::new_v4().simple()));
+        let ctx = MigrationContext { data_dir: root.join("kano_data"), legacy_dirs: vec![root.join("exe")] };
+        fs::create_dir_all(&ctx.data_dir).unwrap();
+        fs::create_dir_all(&ctx.legacy_dirs[0]).unwrap();
+        ctx
+    }
+
+    fn put(path: &Path, value: Value) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, serde_json::to_vec_pretty(&value).unwrap()).unwrap();
+    }
+
+    fn get(path: &Path) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    // every file under the context's folders with its content
+    fn files(ctx: &MigrationContext) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut out = Vec::new();
+        for dir in std::iter::once(&ctx.data_dir).chain(&ctx.legacy_dirs) {
+            let mut stack = vec![dir.clone()];
+            while let Some(d) = stack.pop() {
+                for e in fs::read_dir(&d).unwrap().flatten() {
+                    let p = e.path();
+                    if p.is_dir() {
+                        stack.push(p);
+                    } else {
+                        out.push((p.clone(), fs::read(&p).unwrap()));
+                    }
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+
+    // runs the migration twice; the second run must not change or log anything
+    fn run_twice(ctx: &MigrationContext, version: u32) -> Vec<String> {
+        let m = MIGRATIONS.iter().find(|m| m.version == version).unwrap();
+        let mut log = Vec::new();
+        (m.run)(ctx, &mut log).unwrap();
+        let after = files(ctx);
+        let mut again = Vec::new();
+        (m.run)(ctx, &mut again).unwrap();
+        assert!(again.is_empty(), "migration {} logged on re-run: {:?}", version, again);
+        assert_eq!(files(ctx), after, "migration {} changed files on re-run", version);
+        log
+    }
+
+    #[test]
+    fn moves_legacy_files_into_the_data_dir() {
+        let ctx = temp_ctx();
+        let exe = ctx.legacy_dirs[0].clone();
+        put(&exe.join("game_data/games_db.json"), json!({ "games": [] }));
+        put(&exe.join("tools.json"), json!([]));
+        // already in kano_data: the legacy copy stays where it is
+        put(&exe.join("notes.json"), json!(["old"]));
+        put(&ctx.file("notes.json"), json!(["new"]));
+
+        let log = run_twice(&ctx, 1);
+        assert_eq!(log.len(), 2);
+        assert_eq!(get(&ctx.file("games_db.json")), json!({ "games": [] }));
+        assert!(ctx.file("tools.json").is_file());
+        assert!(!exe.join("game_data/games_db.json").exists());
+        assert_eq!(get(&ctx.file("notes.json")), json!(["new"]));
+        assert!(exe.join("notes.json").exists());
+    }
+
+    #[test]
+    fn assigns_ids_to_games_without_one() {
+        let ctx = temp_ctx();
+        put(&ctx.file("games_db.json"), json!({ "games": [
+            { "name": "a", "path": "/a" },
+            { "id": "", "name": "b", "path": "/b" },
+            { "id": "keep", "name": "c", "path": "/c" },
+        ]}));
+
+        let log = run_twice(&ctx, 2);
+        assert_eq!(log.len(), 1);
+        let games = get(&ctx.file("games_db.json"))["games"].as_array().unwrap().clone();
+        let ids: Vec<&str> = games.iter().map(|g| g["id"].as_str().unwrap()).collect();
+        assert!(ids[0].len() == 36 && ids[1].len() == 36 && ids[0] != ids[1]);
+        assert_eq!(ids[2], "keep");
+    }
+
+    #[test]
+    fn links_legacy_notes_by_game_path() {
+        let ctx = temp_ctx();
+        put(&ctx.file("games_db.json"), json!({ "games": [{ "id": "g1", "name": "Game", "path": "/g" }] }));
+        put(&ctx.file("notes.json"), json!([
+            { "id": "n1", "content": "x", "game_path": "/g" },
+            { "id": "n2", "content": "y", "game_path": "/gone" },
+            { "id": "n3", "content": "z", "game_id": "g1" },
+        ]));
+
+        let log = run_twice(&ctx, 3);
+        assert_eq!(log, vec![format!("converted 2 notes in {} (1 linked to their game)", ctx.file("notes.json").display())]);
+        let notes = get(&ctx.file("notes.json"));
+        assert_eq!(notes[0]["game_id"], "g1");
+        assert_eq!(notes[0]["game_name"], "Game");
+        assert!(notes[0].get("game_path").is_none());
+        assert!(notes[1].get("game_id").is_none() && notes[1].get("game_path").is_none());
+        assert_eq!(notes[2]["game_id"], "g1");
+    }
+
+    #[test]
+    fn moves_tools_out_of_the_config() {
+        let ctx = temp_ctx();
+        put(&ctx.file("tauri_config.json"), json!({ "theme": "dark", "tools": [{ "name": "t" }] }));
+
+        let log = run_twice(&ctx, 4);
+        assert_eq!(log.len(), 1);
+        assert_eq!(get(&ctx.file("tools.json")), json!([{ "name": "t" }]));
+        assert_eq!(get(&ctx.file("tauri_config.json")), json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn config_tools_never_overwrite_tools_json() {
+        let ctx = temp_ctx();
+        put(&ctx.file("tauri_config.json"), json!({ "tools": [{ "name": "stale" }] }));
+        put(&ctx.file("tools.json"), json!([{ "name": "current" }]));
+
+        run_twice(&ctx, 4);
+        assert_eq!(get(&ctx.file("tools.json")), json!([{ "name": "current" }]));
+        assert_eq!(get(&ctx.file("tauri_config.json")), json!({}));
+    }
+
+    #[test]
+    fn moves_the_token_out_of_the_config() {
+        let ctx = temp_ctx();
+        put(&ctx.file("tauri_config.json"), json!({ "access_token": " abc ", "theme": "dark" }));
+
+        let log = run_twice(&ctx, 5);
+        assert_eq!(log.len(), 1);
+        assert_eq!(get(&ctx.file("tokens.json")), json!({ "access_token": "abc" }));
+        assert_eq!(get(&ctx.file("tauri_config.json")), json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn copies_cached_metadata_onto_games() {
+        let ctx = temp_ctx();
+        let meta = json!({ "source": "bangumi", "id": "1", "title": "T" });
+        put(&ctx.file("games_db.json"), json!({ "games": [
+            { "id": "a", "subject_id": 1 },
+            { "id": "b", "subject_id": 2 },
+            { "id": "c", "subject_id": 1, "metadata": { "source": "vndb", "id": "v1" } },
+            { "id": "d", "subject_id": 3 },
+            { "id": "e", "subject_id": 4 },
+        ]}));
+        put(&ctx.file("bangumi_cache.json"), json!({
+            "subject:1": meta,
+            // cached by the frontend of older versions: the API response trimmed to a few
+            // fields, with the cover replaced by where it was saved
+            "subject:3": {
+                "id": 3, "name": "原題", "name_cn": "中文名", "date": "2019-06-28", "summary": "あらすじ",
+                "meta_tags": ["ADV"], "images": { "large": "kano_data/images/3.jpg" },
+                "infobox": [{ "key": "开发", "value": "Key" }, { "key": "平台", "value": "PC" }],
+            },
+            // a full API response
+            "subject:4": { "id": 4, "name": "T4", "date": "2019年6月28日", "images": { "large": "https://lain.bgm.tv/l/4.jpg" } },
+        }));
+
+        let log = run_twice(&ctx, 6);
+        assert_eq!(log.len(), 1);
+        let games = get(&ctx.file("games_db.json"))["games"].clone();
+        assert_eq!(games[0]["metadata"], meta);
+        assert!(games[1].get("metadata").is_none());
+        assert_eq!(games[2]["metadata"]["source"], "vndb");
+        let converted = &games[3]["metadata"];
+        assert_eq!(converted["source"], "bangumi");
+        assert_eq!(converted["id"], "3");
+        assert_eq!(converted["title"], "原題");
+        assert_eq!(converted["release_date"], "2019-06-28");
+        assert_eq!(converted["summary"], "あらすじ");
+        assert_eq!(converted["developers"], json!(["Key"]));
+        assert_eq!(converted["platforms"], json!(["PC"]));
+        assert!(converted["cover"].is_null(), "{}", converted["cover"]);
+        assert_eq!(games[4]["metadata"]["release_date"], "2019-06-28");
+        assert_eq!(games[4]["metadata"]["cover"]["large"], "https://lain.bgm.tv/l/4.jpg");
+    }
+
+    #[test]
+    fn run_records_the_version_and_stops_at_a_failure() {
+        let ctx = temp_ctx();
+        put(&ctx.file("games_db.json"), json!({ "games": [{ "name": "A", "path": "C:/A/a.exe" }] }));
+        // migration 2 can't write its result while something is in the way of the temp file
+        let blocker = ctx.file("games_db.json").with_extension(format!("tmp.{}", std::process::id()));
+        fs::create_dir_all(&blocker).unwrap();
+        assert!(is_pending(&ctx.data_dir));
+
+        assert!(run(&ctx).is_err());
+        assert_eq!(schema_version(&ctx.data_dir), 1);
+        assert!(is_pending(&ctx.data_dir));
+        assert!(fs::read_to_string(ctx.file(LOG_FILE)).unwrap().contains("failed"));
+
+        fs::remove_dir(&blocker).unwrap();
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert_eq!(run(&ctx).unwrap(), latest);
+        assert_eq!(schema_version(&ctx.data_dir), latest);
+        assert!(!is_pending(&ctx.data_dir));
+        assert_eq!(run(&ctx).unwrap(), latest);
+    }
+
+    #[test]
+    fn corrupt_files_are_quarantined_and_the_run_continues() {
+        let ctx = temp_ctx();
+        fs::write(ctx.file("games_db.json"), "not json").unwrap();
+        put(&ctx.file("tauri_config.json"), json!({ "access_token": "tok" }));
+
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert_eq!(run(&ctx).unwrap(), latest);
+        assert!(!is_pending(&ctx.data_dir));
+        assert!(!ctx.file("games_db.json").exists());
+        let names: Vec<String> = fs::read_dir(&ctx.data_dir).unwrap().flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect();
+        let quarantined = names.iter().find(|n| n.starts_with("games_db.corrupt-")).expect("quarantined copy");
+        assert_eq!(fs::read_to_string(ctx.file(quarantined)).unwrap(), "not json");
+        let log = fs::read_to_string(ctx.file(LOG_FILE)).unwrap();
+        assert!(log.contains("quarantined") && !log.contains("failed"), "{}", log);
+        // later migrations still ran
+        assert_eq!(get(&ctx.file("tokens.json")), json!({ "access_token": "tok" }));
+        store::dismiss_issue(&ctx.file("games_db.json"));
+    }
+}
@@ -1,13 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::metadata::GameMetadata;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameEntry {
+    #[serde(default)]
+    pub id: String, // 稳定的游戏ID (UUID)，旧数据在加载时补全
     pub name: String,
     pub path: String,
     pub image: Option<String>,       // 本地图片路径
     #[serde(default)]
     pub image_url: Option<String>,   // 网络图片URL
-    pub subject_id: Option<i64>,
+    pub subject_id: Option<i64>,     // Bangumi 条目 id
+    #[serde(default)]
+    pub vndb_id: Option<String>,     // VNDB 条目 id，如 "v17"
     #[serde(default)]
     pub playtime: i64, // 总游戏时长（秒）
     #[serde(default)]
@@ -16,20 +22,263 @@ pub struct GameEntry {
     pub folder_path: Option<Vec<String>>, // 从用户选择的文件夹开始的文件夹路径
     #[serde(default)]
     pub tags: Vec<String>, // 游戏标签
+    #[serde(default)]
+    pub watch_process: Option<String>, // 计时跟踪的进程名（启动器拉起真正游戏时使用）
+    #[serde(default)]
+    pub launch_profiles: Vec<LaunchProfile>, // 启动配置，为空时直接运行 exe
+    #[serde(default)]
+    pub default_profile: Option<String>,     // 默认启动配置 id
+    #[serde(default)]
+    pub runner_id: Option<String>,           // 转区/兼容层运行器 id（见 AppConfig.runners）
+    #[serde(default)]
+    pub pre_launch_hooks: Vec<GameHook>,     // 启动前依次执行（挂载镜像、切换分辨率等）
+    #[serde(default)]
+    pub post_exit_hooks: Vec<GameHook>,      // 退出后依次执行（卸载镜像、备份存档等）
+    #[serde(default)]
+    pub companion_tools: Vec<String>,        // 随游戏一起启动的工具箱工具 id（如 Textractor）
+    #[serde(default)]
+    pub close_companions_on_exit: bool,
+    #[serde(default)]
+    pub save_dirs: Vec<String>,              // 存档目录，支持 %APPDATA%、~ 和 {dir}（游戏目录）
+    #[serde(default)]
+    pub metadata: Option<GameMetadata>,      // 关联条目的信息（标题、开发商、制作人员、标签等）
+}
+
+/// A command run before launch or after exit: either a command line or a Toolbox tool
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GameHook {
+    #[serde(default)]
+    pub command: Option<String>,   // 命令行，可用 {exe} / {dir} 指代游戏
+    #[serde(default)]
+    pub tool_id: Option<String>,   // 或者引用工具箱中的工具
+    #[serde(default)]
+    pub wait: bool,                // 等待执行结束（受超时限制）再继续
+    #[serde(default)]
+    pub timeout_secs: Option<u64>, // 默认 30 秒
+    #[serde(default)]
+    pub required: bool,            // 启动前钩子失败时取消启动
+}
+
+/// How to start a game: extra arguments, environment, working directory and an optional wrapper
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LaunchProfile {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub cwd: Option<String>,    // 工作目录，相对路径以 exe 所在目录为基准
+    #[serde(default)]
+    pub run_as: Option<String>, // 包装命令，例如 "wine"，优先于游戏的运行器
+}
+
+/// One launch of a game, stored in sessions.json
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaySession {
+    pub id: String,
+    pub game_id: String,
+    pub start: String,  // 开始时间 (ISO 8601 格式)
+    pub end: String,    // 结束时间 (ISO 8601 格式)
+    pub duration: i64,  // 时长（秒）
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub killed: bool,   // 是否通过 kill_game 结束
+    #[serde(default)]
+    pub idle_seconds: i64, // 挂机时长（秒），不计入 duration
+    #[serde(default)]
+    pub idle_spans: Vec<IdleSpan>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdleSpan {
+    pub start: String,
+    pub end: String,
+    pub seconds: i64,
+}
+
+/// A candidate executable found under a game folder
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExeCandidate {
+    pub path: String,
+    pub folder_path: Vec<String>, // 相对于游戏文件夹的文件夹路径
+    pub size: u64,                // 文件大小（字节）
+    pub modified: Option<String>, // 修改时间 (ISO 8601 格式)
+    pub score: i64,               // 排序分数，越高越可能是游戏本体
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GamesDB {
     pub games: Vec<GameEntry>,
     #[serde(default)]
     pub custom_tags: Vec<String>, // 用户自定义标签
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// How a command refers to a game: by its stable id, or by exe path (legacy commands)
+pub enum GameKey<'a> {
+    Id(&'a str),
+    Path(&'a str),
+}
+
+impl GameKey<'_> {
+    pub fn matches(&self, g: &GameEntry) -> bool {
+        match self {
+            GameKey::Id(id) => g.id == *id,
+            GameKey::Path(path) => g.path == *path,
+        }
+    }
+}
+
+impl GamesDB {
+    pub fn find(&self, key: &GameKey) -> Option<&GameEntry> {
+        self.games.iter().find(|g| key.matches(g))
+    }
+
+    pub fn find_mut(&mut self, key: &GameKey) -> Option<&mut GameEntry> {
+        self.games.iter_mut().find(|g| key.matches(g))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct AppConfig {
-    pub access_token: Option<String>,
+    #[serde(default = "crate::exe_ranking::default_keyword_weights")]
+    pub exe_keyword_weights: Vec<KeywordWeight>, // 扫描时 exe 文件名关键字加权
+    #[serde(default)]
+    pub idle_policy: IdlePolicy,
+    #[serde(default)]
+    pub runners: Vec<RunnerEntry>, // Locale Emulator / Wine / Proton 等包装命令
+    #[serde(default)]
+    pub save_backup: SaveBackupPolicy,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub metadata: MetadataSettings,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            exe_keyword_weights: crate::exe_ranking::default_keyword_weights(),
+            idle_policy: IdlePolicy::default(),
+            runners: Vec::new(),
+            save_backup: SaveBackupPolicy::default(),
+            sync: SyncSettings::default(),
+            metadata: MetadataSettings::default(),
+        }
+    }
+}
+
+/// When save snapshots are taken and how many are kept per game
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SaveBackupPolicy {
+    pub auto_on_exit: bool, // 游戏退出时自动备份
+    pub keep_last: usize,   // 每个游戏保留的快照数，0 表示不限制
+}
+
+impl Default for SaveBackupPolicy {
+    fn default() -> Self {
+        SaveBackupPolicy { auto_on_exit: true, keep_last: 10 }
+    }
+}
+
+/// Where game information is fetched from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MetadataSettings {
+    pub bangumi_api_url: String, // Bangumi API 地址
+    pub vndb_api_url: String,    // VNDB Kana API 地址，可指向本地镜像或测试服务器
+    pub cache_ttl_hours: u64,    // Bangumi 响应缓存有效期，0 表示不缓存
+    pub cache_max_entries: usize, // 缓存条目上限，超出时丢弃最旧的；0 表示不限制
+    pub cache_max_bytes: u64,     // 缓存大小上限（序列化后的字节数），同样丢弃最旧的；0 表示不限制
+}
+
+impl Default for MetadataSettings {
+    fn default() -> Self {
+        MetadataSettings {
+            bangumi_api_url: crate::bangumi::DEFAULT_API_URL.to_string(),
+            vndb_api_url: crate::vndb::DEFAULT_API_URL.to_string(),
+            cache_ttl_hours: 24 * 7,
+            cache_max_entries: 2000,
+            cache_max_bytes: 32 * 1024 * 1024,
+        }
+    }
+}
+
+/// Where the library is synced to and how this machine's paths relate to the others
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncSettings {
+    #[serde(default)]
+    pub backend: Option<SyncBackendConfig>,
+    #[serde(default)]
+    pub machine_id: String,              // 本机标识，首次同步时生成
     #[serde(default)]
-    pub tools: Vec<ToolEntry>,
+    pub machine_name: String,
+    #[serde(default)]
+    pub path_mappings: Vec<PathMapping>, // 其他机器的路径前缀 -> 本机路径前缀
+    #[serde(default)]
+    pub last_sync: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SyncBackendConfig {
+    /// Any folder kept in sync by another tool (Syncthing, Dropbox, a network share)
+    Folder { path: String },
+    /// A WebDAV collection URL, e.g. https://dav.example.com/kano/
+    Webdav {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PathMapping {
+    pub from: String, // e.g. D:\Games
+    pub to: String,   // e.g. /home/me/Games
+}
+
+/// A zip snapshot of a game's save directories under `kano_data/saves/<game id>/`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveSnapshot {
+    pub id: String,      // zip 文件名（不含扩展名）
+    pub game_id: String,
+    pub created: String, // ISO 8601
+    pub reason: String,  // "manual" | "exit" | "pre-restore"
+    pub size: u64,
+    pub dirs: Vec<String>,
+}
+
+/// A wrapper that launches games, e.g. Locale Emulator or Wine.
+/// `command` is a template: `{exe}` game path, `{dir}` game folder, `{args}` profile arguments.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunnerEntry {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+}
+
+/// When to stop counting playtime; `None` disables a check. Windows only: elsewhere focus
+/// and input can't be read and set_idle_policy refuses thresholds.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IdlePolicy {
+    #[serde(default)]
+    pub unfocused_minutes: Option<u64>, // 游戏窗口失去焦点超过 N 分钟视为挂机
+    #[serde(default)]
+    pub no_input_minutes: Option<u64>,  // 无键鼠输入超过 N 分钟视为挂机
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeywordWeight {
+    pub keyword: String,
+    pub weight: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,416 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a watched process name to show up before giving up on it
+const WATCH_GRACE: Duration = Duration::from_secs(60);
+
+/// A running process as seen in a system snapshot
+#[derive(Debug, Clone)]
+pub struct ProcInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub group: Option<u32>, // process group (Unix only)
+    pub name: String,       // executable file name, e.g. "game.exe"
+}
+
+/// Follows a launched process, everything it spawns, and optionally a process
+/// matched by name, so launcher hand-offs don't end the session early.
+pub struct ProcessTracker {
+    root: u32,
+    known: HashSet<u32>, // live pids attributed to the game at the last poll, used for parent matching
+    watch_name: Option<String>,
+    seen_watch: bool,
+    started: Instant,
+    grace_since: Option<Instant>, // 启动的进程树已结束，仍在等待被监视的进程
+}
+
+/// Result of one poll: the pids still belonging to the game and whether the session goes on
+pub struct TrackState {
+    pub pids: Vec<u32>,
+    pub alive: bool,
+}
+
+impl ProcessTracker {
+    pub fn new(root: u32, watch_name: Option<String>) -> Self {
+        let watch_name = watch_name
+            .map(|n| normalize_name(&n))
+            .filter(|n| !n.is_empty());
+        let mut known = HashSet::new();
+        known.insert(root);
+        ProcessTracker { root, known, watch_name, seen_watch: false, started: Instant::now(), grace_since: None }
+    }
+
+    pub fn poll(&mut self, procs: &[ProcInfo]) -> TrackState {
+        self.poll_at(procs, Instant::now())
+    }
+
+    fn poll_at(&mut self, procs: &[ProcInfo], now: Instant) -> TrackState {
+        // Grow the tree until no new descendants are found. Parents that died since the
+        // last poll are still in `known`, so children of an exited launcher are attributed
+        // (Windows keeps the ppid); afterwards only live pids are kept, so a reused pid
+        // doesn't pull an unrelated process into the game.
+        loop {
+            let mut added = false;
+            for p in procs {
+                let in_group = p.group == Some(self.root);
+                if !self.known.contains(&p.pid) && (self.known.contains(&p.ppid) || in_group) {
+                    self.known.insert(p.pid);
+                    added = true;
+                }
+            }
+            if !added {
+                break;
+            }
+        }
+        let tree: Vec<u32> = procs
+            .iter()
+            .filter(|p| self.known.contains(&p.pid))
+            .map(|p| p.pid)
+            .collect();
+        self.known = tree.iter().copied().collect();
+
+        let watch_name = match &self.watch_name {
+            Some(n) => n,
+            None => {
+                let alive = !tree.is_empty();
+                return TrackState { pids: tree, alive };
+            }
+        };
+
+        let watched: Vec<u32> = procs
+            .iter()
+            .filter(|p| normalize_name(&p.name) == *watch_name)
+            .map(|p| p.pid)
+            .collect();
+        if !watched.is_empty() {
+            self.seen_watch = true;
+        }
+        // Once the watched process was seen, it alone decides when the session ends;
+        // until then the launched tree (or the grace period) keeps it going.
+        let alive = if self.seen_watch {
+            !watched.is_empty()
+        } else {
+            if tree.is_empty() {
+                self.grace_since.get_or_insert(now);
+            } else {
+                self.grace_since = None;
+            }
+            !tree.is_empty() || now.saturating_duration_since(self.started) < WATCH_GRACE
+        };
+        let mut pids = tree;
+        for pid in watched {
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+        TrackState { pids, alive }
+    }
+
+    /// Time spent only waiting for a watched process that never showed up; it isn't playtime
+    pub fn grace_wait(&self, now: Instant) -> Duration {
+        match self.grace_since {
+            Some(since) if !self.seen_watch => now.saturating_duration_since(since),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+// Compare names case-insensitively and with or without the ".exe" suffix
+fn normalize_name(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    match lower.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => lower,
+    }
+}
+
+// File name of a path that may use either separator (Wine reports Windows paths)
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(target_os = "linux")]
+pub fn snapshot() -> Vec<ProcInfo> {
+    snapshot_from(std::path::Path::new("/proc"))
+}
+
+/// Read processes from a procfs-style directory (`/proc` normally)
+#[cfg(target_os = "linux")]
+pub fn snapshot_from(proc_root: &std::path::Path) -> Vec<ProcInfo> {
+    let mut procs = Vec::new();
+    let entries = match std::fs::read_dir(proc_root) {
+        Ok(e) => e,
+        Err(_) => return procs,
+    };
+    for entry in entries.flatten() {
+        let pid: u32 = match entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let stat = match std::fs::read_to_string(entry.path().join("stat")) {
+            Ok(s) => s,
+            Err(_) => continue,
+        };
+        // "<pid> (<comm>) <state> <ppid> <pgrp> ..."; comm may itself contain spaces or ')'
+        let (open, close) = match (stat.find('('), stat.rfind(')')) {
+            (Some(o), Some(c)) if c > o => (o, c),
+            _ => continue,
+        };
+        let comm = &stat[open + 1..close];
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        if fields.len() < 3 || fields[0] == "Z" || fields[0] == "X" {
+            continue; // zombie or dead
+        }
+        let ppid = fields[1].parse().unwrap_or(0);
+        let group = fields[2].parse().ok();
+
+        // argv[0] gives the full exe name (comm is truncated to 15 chars, and is
+        // "wine-preloader" style noise for Wine processes)
+        let name = std::fs::read(entry.path().join("cmdline"))
+            .ok()
+            .and_then(|b| {
+                let argv0 = b.split(|c| *c == 0).next()?.to_vec();
+                let s = String::from_utf8_lossy(&argv0).to_string();
+                if s.is_empty() { None } else { Some(base_name(&s).to_string()) }
+            })
+            .unwrap_or_else(|| comm.to_string());
+
+        procs.push(ProcInfo { pid, ppid, group, name });
+    }
+    procs
+}
+
+#[cfg(target_os = "windows")]
+pub fn snapshot() -> Vec<ProcInfo> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+    };
+
+    let mut procs = Vec::new();
+    unsafe {
+        let snap = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snap == INVALID_HANDLE_VALUE {
+            return procs;
+        }
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut ok = Process32FirstW(snap, &mut entry);
+        while ok != 0 {
+            let len = entry.szExeFile.iter().position(|c| *c == 0).unwrap_or(entry.szExeFile.len());
+            procs.push(ProcInfo {
+                pid: entry.th32ProcessID,
+                ppid: entry.th32ParentProcessID,
+                group: None,
+                name: String::from_utf16_lossy(&entry.szExeFile[..len]),
+            });
+            ok = Process32NextW(snap, &mut entry);
+        }
+        CloseHandle(snap);
+    }
+    procs
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn snapshot() -> Vec<ProcInfo> {
+    // macOS and other Unix: ask ps
+    let out = match std::process::Command::new("ps").args(["-axo", "pid=,ppid=,pgid=,comm="]).output() {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pid = parts.next()?.parse().ok()?;
+            let ppid = parts.next()?.parse().ok()?;
+            let group = parts.next()?.parse().ok();
+            let comm = parts.collect::<Vec<_>>().join(" ");
+            Some(ProcInfo { pid, ppid, group, name: base_name(&comm).to_string() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proc(pid: u32, ppid: u32, name: &str) -> ProcInfo {
+        ProcInfo { pid, ppid, group: None, name: name.to_string() }
+    }
+
+    fn sorted(mut pids: Vec<u32>) -> Vec<u32> {
+        pids.sort();
+        pids
+    }
+
+    #[test]
+    fn poll_follows_children_after_launcher_exits() {
+        let mut t = ProcessTracker::new(10, None);
+        let s = t.poll(&[proc(1, 0, "init"), proc(10, 1, "launcher.exe"), proc(11, 10, "game.exe")]);
+        assert!(s.alive);
+        assert_eq!(sorted(s.pids), vec![10, 11]);
+
+        // launcher exits; the game keeps the session going
+        let s = t.poll(&[proc(1, 0, "init"), proc(11, 10, "game.exe")]);
+        assert!(s.alive);
+        assert_eq!(s.pids, vec![11]);
+
+        let s = t.poll(&[proc(1, 0, "init")]);
+        assert!(!s.alive);
+        assert!(s.pids.is_empty());
+    }
+
+    #[test]
+    fn poll_attributes_grandchild_whose_parent_died_between_polls() {
+        let mut t = ProcessTracker::new(10, None);
+        t.poll(&[proc(10, 1, "launcher.exe")]);
+        // 10 spawned 11, which spawned 12; 10 exited before we looked again
+        let s = t.poll(&[proc(11, 10, "updater.exe"), proc(12, 11, "game.exe")]);
+        assert_eq!(sorted(s.pids), vec![11, 12]);
+    }
+
+    #[test]
+    fn poll_matches_process_group() {
+        let mut t = ProcessTracker::new(10, None);
+        let s = t.poll(&[ProcInfo { pid: 20, ppid: 1, group: Some(10), name: "wineserver".into() }]);
+        assert!(s.alive);
+        assert_eq!(s.pids, vec![20]);
+    }
+
+    #[test]
+    fn poll_ignores_reused_pids() {
+        let mut t = ProcessTracker::new(10, None);
+        t.poll(&[proc(10, 1, "launcher.exe"), proc(11, 10, "game.exe")]);
+        t.poll(&[proc(10, 1, "launcher.exe")]);
+        // 11 is gone; a new process reusing pid 11 is unrelated, and so are its children
+        let s = t.poll(&[proc(10, 1, "launcher.exe"), proc(11, 1, "browser"), proc(30, 11, "tab")]);
+        assert_eq!(s.pids, vec![10]);
+    }
+
+    #[test]
+    fn watch_name_is_case_and_suffix_insensitive() {
+        let cases = [("Game.EXE", "game.exe"), ("game", "GAME.exe"), (" game.exe ", "game")];
+        for (watch, running) in cases {
+            let mut t = ProcessTracker::new(10, Some(watch.to_string()));
+            let s = t.poll(&[proc(50, 1, running)]);
+            assert!(s.alive, "{} vs {}", watch, running);
+            assert_eq!(s.pids, vec![50], "{} vs {}", watch, running);
+        }
+        let mut t = ProcessTracker::new(10, Some("game.exe".into()));
+        let s = t.poll(&[proc(10, 1, "launcher.exe"), proc(50, 1, "gamer.exe")]);
+        assert_eq!(s.pids, vec![10]);
+    }
+
+    #[test]
+    fn watched_process_decides_once_seen() {
+        let mut t = ProcessTracker::new(10, Some("game.exe".into()));
+        let s = t.poll(&[proc(10, 1, "launcher.exe"), proc(50, 1, "game.exe")]);
+        assert!(s.alive);
+        assert_eq!(sorted(s.pids), vec![10, 50]);
+
+        // launcher still open, but the watched game has exited
+        let s = t.poll(&[proc(10, 1, "launcher.exe")]);
+        assert!(!s.alive);
+        assert_eq!(t.grace_wait(Instant::now()), Duration::ZERO);
+    }
+
+    #[test]
+    fn grace_expires_when_watched_process_never_appears() {
+        let mut t = ProcessTracker::new(10, Some("game.exe".into()));
+        let start = t.started;
+        assert!(t.poll_at(&[proc(10, 1, "launcher.exe")], start).alive);
+
+        // launcher gone at 5s; waiting for the game within the grace period
+        let gone = start + Duration::from_secs(5);
+        assert!(t.poll_at(&[], gone).alive);
+        assert!(t.poll_at(&[], start + Duration::from_secs(30)).alive);
+
+        let end = start + WATCH_GRACE + Duration::from_secs(1);
+        assert!(!t.poll_at(&[], end).alive);
+        assert_eq!(t.grace_wait(end), end - gone);
+    }
+
+    #[test]
+    fn grace_wait_resets_when_watched_process_shows_up() {
+        let mut t = ProcessTracker::new(10, Some("game.exe".into()));
+        let start = t.started;
+        t.poll_at(&[], start + Duration::from_secs(1));
+        let s = t.poll_at(&[proc(50, 1, "game.exe")], start + Duration::from_secs(20));
+        assert!(s.alive);
+        let end = start + Duration::from_secs(600);
+        assert!(!t.poll_at(&[], end).alive);
+        assert_eq!(t.grace_wait(end), Duration::ZERO);
+    }
+
+    #[cfg(target_os = "linux")]
+    mod procfs {
+        use super::super::*;
+        use std::fs;
+        use std::path::{Path, PathBuf};
+
+        fn fake_proc(name: &str) -> PathBuf {
+            let root = std::env::temp_dir().join(format!("kano-proc-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            root
+        }
+
+        fn add(root: &Path, pid: &str, stat: &str, cmdline: Option<&[u8]>) {
+            let dir = root.join(pid);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("stat"), stat).unwrap();
+            if let Some(c) = cmdline {
+                fs::write(dir.join("cmdline"), c).unwrap();
+            }
+        }
+
+        #[test]
+        fn snapshot_from_reads_stat_and_cmdline() {
+            let root = fake_proc("read");
+            add(&root, "100", "100 (bash) S 1 100 100 0 -1", Some(b"/usr/bin/bash\0-l\0"));
+            // Wine reports a Windows path in argv[0]
+            add(&root, "200", "200 (game.exe) S 100 100 100 0 -1", Some(b"C:\\Games\\Foo\\Game.exe\0"));
+            // comm with spaces and ')' and no cmdline: fall back to comm
+            add(&root, "300", "300 (we) ird name) R 200 100 100 0 -1", None);
+            // empty cmdline (kernel threads)
+            add(&root, "400", "400 (kworker/0:1) I 2 0 0 0 -1", Some(b""));
+
+            let mut procs = snapshot_from(&root);
+            procs.sort_by_key(|p| p.pid);
+            let got: Vec<(u32, u32, Option<u32>, &str)> =
+                procs.iter().map(|p| (p.pid, p.ppid, p.group, p.name.as_str())).collect();
+            assert_eq!(
+                got,
+                vec![
+                    (100, 1, Some(100), "bash"),
+                    (200, 100, Some(100), "Game.exe"),
+                    (300, 200, Some(100), "we) ird name"),
+                    (400, 2, Some(0), "kworker/0:1"),
+                ]
+            );
+            let _ = fs::remove_dir_all(&root);
+        }
+
+        #[test]
+        fn snapshot_from_skips_dead_and_malformed_entries() {
+            let root = fake_proc("skip");
+            add(&root, "10", "10 (zombie) Z 1 10 10", None);
+            add(&root, "11", "11 (dead) X 1 11 11", None);
+            add(&root, "12", "garbage", None);
+            add(&root, "13", "13 (short) S", None);
+            add(&root, "self", "14 (self) S 1 14 14", None);
+            fs::create_dir_all(root.join("15")).unwrap(); // exited while listing: no stat
+            add(&root, "16", "16 (ok) S 1 16 16", None);
+
+            let procs = snapshot_from(&root);
+            assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![16]);
+            let _ = fs::remove_dir_all(&root);
+        }
+
+        #[test]
+        fn snapshot_from_missing_root_is_empty() {
+            assert!(snapshot_from(Path::new("/nonexistent/kano-proc")).is_empty());
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use crate::config::saves_dir_path;
+use crate::error::KanoError;
+use crate::models::{GameEntry, SaveSnapshot};
+
+const MANIFEST_NAME: &str = "kano_snapshot.json";
+
+/// Stored inside every snapshot; files of `dirs[i]` live under `i/` in the archive.
+/// The directories are kept unexpanded so a snapshot restores on another machine.
+#[derive(Serialize, Deserialize)]
+struct SnapshotManifest {
+    game_id: String,
+    game_name: String,
+    created: String,
+    reason: String,
+    dirs: Vec<String>,
+}
+
+// ids come from the frontend; don't let them point outside the saves folder
+fn check_id(reason: &'static str, id: &str) -> Result<(), KanoError> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err(KanoError::invalid(reason, format!("invalid {}", reason.replace('_', " "))));
+    }
+    Ok(())
+}
+
+pub fn game_saves_dir(game_id: &str) -> Result<PathBuf, KanoError> {
+    check_id("game_id", game_id)?;
+    Ok(saves_dir_path().join(game_id))
+}
+
+/// Expand a configured save directory: `%VAR%` environment variables, a leading `~`
+/// and `{dir}` (the game's folder). Relative paths are taken from the game's folder.
+pub fn expand_save_path(raw: &str, game: &GameEntry) -> PathBuf {
+    let game_dir = Path::new(&game.path).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let mut s = raw.trim().replace("{dir}", &game_dir.to_string_lossy());
+
+    // %APPDATA%\Game\Save -> C:\Users\me\AppData\Roaming\Game\Save
+    let mut out = String::new();
+    while let Some(start) = s.find('%') {
+        match s[start + 1..].find('%') {
+            Some(len) => {
+                let name = &s[start + 1..start + 1 + len];
+                out.push_str(&s[..start]);
+                match std::env::var(name) {
+                    Ok(v) if !name.is_empty() => out.push_str(&v),
+                    _ => out.push_str(&s[start..start + len + 2]),
+                }
+                s = s[start + len + 2..].to_string();
+            }
+            None => break,
+        }
+    }
+    out.push_str(&s);
+
+    if let Some(rest) = out.strip_prefix('~') {
+        if let Ok(home) = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+            out = format!("{}{}", home, rest);
+        }
+    }
+    let p = PathBuf::from(out);
+    if p.is_relative() { game_dir.join(p) } else { p }
+}
+
+/// Refuse save directories that would cover far more than saves: the game's own folder,
+/// the home folder, a drive root or a bare environment root like `%APPDATA%`.
+/// Restores write into these, so a too-broad entry is rejected up front.
+pub fn check_save_dir(raw: &str, game: &GameEntry) -> Result<(), KanoError> {
+    let bare = raw.trim().trim_end_matches(['/', '\\']);
+    let env_root = bare.len() > 2 && bare.starts_with('%') && bare.ends_with('%') && !bare[1..bare.len() - 1].contains('%');
+    let expanded = expand_save_path(raw, game);
+    let game_dir = Path::new(&game.path).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok().map(PathBuf::from);
+    let too_broad = bare.is_empty()
+        || env_root
+        || matches!(bare, "." | "~" | "{dir}")
+        || expanded.parent().is_none()
+        || expanded == game_dir
+        || home.is_some_and(|h| expanded == h);
+    if too_broad {
+        return Err(KanoError::invalid("save_dir_too_broad", format!("{} is not a save directory", expanded.display())));
+    }
+    Ok(())
+}
+
+/// Zip the game's save directories into a new snapshot, then apply the retention limit
+pub fn create_snapshot(game: &GameEntry, reason: &str, keep_last: usize) -> Result<SaveSnapshot, KanoError> {
+    snapshot_dirs(game, &game.save_dirs, reason, keep_last)
+}
+
+fn snapshot_dirs(game: &GameEntry, dirs: &[String], reason: &str, keep_last: usize) -> Result<SaveSnapshot, KanoError> {
+    if dirs.is_empty() {
+        return Err(KanoError::invalid("no_save_dirs", "no save directories configured"));
+    }
+    let roots: Vec<PathBuf> = dirs.iter().map(|d| expand_save_path(d, game)).collect();
+    if !roots.iter().any(|r| r.is_dir()) {
+        return Err(KanoError::not_found("save_dirs"));
+    }
+
+    let dir = game_saves_dir(&game.id)?;
+    fs::create_dir_all(&dir).map_err(|e| KanoError::io("create saves dir", e))?;
+    let now = chrono::Utc::now();
+    let stamp = now.format("%Y%m%d-%H%M%S").to_string();
+    let mut id = format!("{}_{}", stamp, reason);
+    let mut n = 1;
+    while dir.join(format!("{}.zip", id)).exists() {
+        id = format!("{}-{}_{}", stamp, n, reason);
+        n += 1;
+    }
+    let path = dir.join(format!("{}.zip", id));
+    let manifest = SnapshotManifest {
+        game_id: game.id.clone(),
+        game_name: game.name.clone(),
+        created: now.to_rfc3339(),
+        reason: reason.to_string(),
+        dirs: dirs.to_vec(),
+    };
+
+    // write to a temp file first so a crash never leaves a half-written snapshot behind
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    let res = write_archive(&tmp, &manifest, &roots);
+    if let Err(e) = res {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, &path)?;
+
+    prune_snapshots(&game.id, keep_last);
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(SaveSnapshot {
+        id,
+        game_id: manifest.game_id,
+        created: manifest.created,
+        reason: manifest.reason,
+        size,
+        dirs: manifest.dirs,
+    })
+}
+
+fn write_archive(path: &Path, manifest: &SnapshotManifest, roots: &[PathBuf]) -> Result<(), KanoError> {
+    let file = fs::File::create(path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let json = serde_json::to_vec_pretty(manifest)?;
+    zip.start_file(MANIFEST_NAME, options)?;
+    zip.write_all(&json)?;
+
+    for (i, root) in roots.iter().enumerate() {
+        // a missing directory (no saves yet) is recorded as empty
+        zip.add_directory(format!("{}/", i), options)?;
+        if !root.is_dir() {
+            continue;
+        }
+        let mut files = Vec::new();
+        collect_files(root, root, &mut files);
+        for (abs, rel) in files {
+            let mut data = Vec::new();
+            fs::File::open(&abs)
+                .and_then(|mut f| f.read_to_end(&mut data))
+                .map_err(|e| KanoError::io(&format!("read {}", abs.display()), e))?;
+            zip.start_file(format!("{}/{}", i, rel), options)?;
+            zip.write_all(&data)?;
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+// Files below `dir` with their path relative to `root`, always `/`-separated
+fn collect_files(dir: &Path, root: &Path, out: &mut Vec<(PathBuf, String)>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_files(&path, root, out);
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                let rel = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                out.push((path, rel));
+            }
+        }
+    }
+}
+
+/// Snapshots of a game, newest first
+pub fn list_snapshots(game_id: &str) -> Result<Vec<SaveSnapshot>, KanoError> {
+    let mut list = Vec::new();
+    let entries = match fs::read_dir(game_saves_dir(game_id)?) {
+        Ok(e) => e,
+        Err(_) => return Ok(list),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("zip") {
+            continue;
+        }
+        let id = match path.file_stem() {
+            Some(s) => s.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let manifest = match read_manifest(&path) {
+            Ok(m) => m,
+            Err(_) => continue, // not one of ours, or damaged
+        };
+        list.push(SaveSnapshot {
+            id,
+            game_id: game_id.to_string(),
+            created: manifest.created,
+            reason: manifest.reason,
+            size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            dirs: manifest.dirs,
+        });
+    }
+    list.sort_by(|a, b| b.created.cmp(&a.created));
+    Ok(list)
+}
+
+fn read_manifest(path: &Path) -> Result<SnapshotManifest, KanoError> {
+    let file = fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entry = archive.by_name(MANIFEST_NAME)?;
+    let mut s = String::new();
+    entry.read_to_string(&mut s)?;
+    Ok(serde_json::from_str(&s)?)
+}
+
+pub fn snapshot_path(game_id: &str, snapshot_id: &str) -> Result<PathBuf, KanoError> {
+    check_id("snapshot_id", snapshot_id)?;
+    let path = game_saves_dir(game_id)?.join(format!("{}.zip", snapshot_id));
+    if !path.is_file() {
+        return Err(KanoError::not_found("snapshot"));
+    }
+    Ok(path)
+}
+
+/// Write the snapshot's files back into the directories it was taken from. Only files in
+/// the snapshot are overwritten; nothing else in those directories is touched.
+/// The current state of the same directories is snapshotted first ("pre-restore") so a
+/// restore can be undone.
+pub fn restore_snapshot(game: &GameEntry, snapshot_id: &str, keep_last: usize) -> Result<(), KanoError> {
+    let path = snapshot_path(&game.id, snapshot_id)?;
+    let manifest = read_manifest(&path)?;
+    for d in &manifest.dirs {
+        check_save_dir(d, game)?;
+    }
+    let roots: Vec<PathBuf> = manifest.dirs.iter().map(|d| expand_save_path(d, game)).collect();
+    // no pruning yet, it could remove the snapshot being restored
+    if roots.iter().any(|r| r.is_dir()) {
+        snapshot_dirs(game, &manifest.dirs, "pre-restore", 0)?;
+    }
+    for root in &roots {
+        fs::create_dir_all(root).map_err(|e| KanoError::io(&format!("create {}", root.display()), e))?;
+    }
+
+    let file = fs::File::open(&path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() || entry.name() == MANIFEST_NAME {
+            continue;
+        }
+        // enclosed_name rejects absolute paths and `..`
+        let rel = match entry.enclosed_name() {
+            Some(p) => p,
+            None => continue,
+        };
+        let mut parts = rel.components();
+        let root = match parts
+            .next()
+            .and_then(|c| c.as_os_str().to_str())
+            .and_then(|s| s.parse::<usize>().ok())
+            .and_then(|idx| roots.get(idx))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+        let target = root.join(parts.as_path());
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = fs::File::create(&target).map_err(|e| KanoError::io(&format!("write {}", target.display()), e))?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+    prune_snapshots(&game.id, keep_last);
+    Ok(())
+}
+
+// Keep the newest `keep_last` snapshots (0 = unlimited). Pre-restore snapshots count too.
+fn prune_snapshots(game_id: &str, keep_last: usize) {
+    if keep_last == 0 {
+        return;
+    }
+    let (Ok(dir), Ok(list)) = (game_saves_dir(game_id), list_snapshots(game_id)) else { return };
+    for old in list.into_iter().skip(keep_last) {
+        let _ = fs::remove_file(dir.join(format!("{}.zip", old.id)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> GameEntry {
+        serde_json::from_value(serde_json::json!({ "id": "g1", "name": "Game", "path": "/games/foo/game.exe" })).unwrap()
+    }
+
+    #[test]
+    fn check_save_dir_rejects_broad_roots() {
+        let g = game();
+        for raw in ["", ".", "./", "~", "~/", "{dir}", "{dir}/", "%APPDATA%", "%APPDATA%\\", "/", "/games/foo"] {
+            assert!(check_save_dir(raw, &g).is_err(), "{:?} should be refused", raw);
+        }
+        for raw in ["save", "{dir}/save", "%APPDATA%\\Studio\\Game", "~/.local/share/game", "/srv/saves/foo"] {
+            assert!(check_save_dir(raw, &g).is_ok(), "{:?} should be allowed", raw);
+        }
+    }
+
+    #[test]
+    fn create_restore_and_prune() {
+        let dir = crate::data_dir::TestDataDir::new("saves");
+        let game_dir = dir.root.join("game");
+        let save = game_dir.join("save");
+        fs::create_dir_all(save.join("sub")).unwrap();
+        fs::write(save.join("a.sav"), "a1").unwrap();
+        fs::write(save.join("sub/b.sav"), "b1").unwrap();
+        let mut g = game();
+        g.path = game_dir.join("game.exe").to_string_lossy().to_string();
+        g.save_dirs = vec!["save".into(), "missing".into()];
+
+        let first = create_snapshot(&g, "manual", 0).unwrap();
+        assert_eq!((first.reason.as_str(), first.dirs.clone()), ("manual", g.save_dirs.clone()));
+        fs::write(save.join("a.sav"), "a2").unwrap();
+        fs::remove_file(save.join("sub/b.sav")).unwrap();
+        fs::write(save.join("c.sav"), "c2").unwrap();
+
+        restore_snapshot(&g, &first.id, 0).unwrap();
+        assert_eq!(fs::read_to_string(save.join("a.sav")).unwrap(), "a1");
+        assert_eq!(fs::read_to_string(save.join("sub/b.sav")).unwrap(), "b1");
+        // files that aren't in the snapshot are left alone
+        assert_eq!(fs::read_to_string(save.join("c.sav")).unwrap(), "c2");
+        // the state before the restore was kept
+        let list = list_snapshots(&g.id).unwrap();
+        let reasons: Vec<&str> = list.iter().map(|s| s.reason.as_str()).collect();
+        assert_eq!(reasons, ["pre-restore", "manual"]);
+        restore_snapshot(&g, &list[0].id, 0).unwrap();
+        assert_eq!(fs::read_to_string(save.join("a.sav")).unwrap(), "a2");
+
+        // only the newest ones are kept
+        let latest = create_snapshot(&g, "exit", 2).unwrap();
+        let list = list_snapshots(&g.id).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, latest.id);
+        assert!(list.iter().all(|s| s.id != first.id));
+        assert!(matches!(restore_snapshot(&g, &first.id, 2), Err(KanoError::NotFound { .. })));
+    }
+
+    #[test]
+    fn ids_cannot_leave_the_saves_folder() {
+        for id in ["", "..", "../x", "a/b", "a\\b"] {
+            assert!(game_saves_dir(id).is_err(), "{:?}", id);
+            assert!(snapshot_path("g1", id).is_err(), "{:?}", id);
+        }
+    }
+}
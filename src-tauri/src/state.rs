@@ -1,7 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 
 /// Global state to track running game processes
 pub struct RunningProcesses {
-    pub processes: Mutex<HashMap<String, u32>>, // path -> process_id
+    pub processes: Mutex<HashMap<String, Vec<u32>>>, // game id (or exe path for non-library exes) -> live process ids
+    pub killed: Mutex<HashSet<String>>, // keys ended through kill_game, consumed by the monitor
 }
@@ -0,0 +1,483 @@
+use std::fs;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::error::KanoError;
+
+const LOCK_ATTEMPTS: u32 = 50;
+const LOCK_RETRY: Duration = Duration::from_millis(100);
+/// A lock older than this is considered abandoned even if its PID is still in use
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+const DEFAULT_BACKUPS: usize = 3;
+
+/// A JSON file holding one value of type `T`.
+/// Writes go to a temp file that is fsynced and renamed over the original, under a
+/// `<file>.lock` that records the writer's PID so a crashed writer can't block everyone.
+/// The previous versions are kept as `<file>.1` .. `<file>.N`.
+pub struct Store<T> {
+    path: PathBuf,
+    backups: usize,
+    disposable: bool,
+    _marker: PhantomData<T>,
+}
+
+/// Why a file couldn't be loaded
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LoadError {
+    /// the file exists but couldn't be read (permissions, locked database, ...)
+    Io { path: String, error: String },
+    /// the content doesn't parse; the file has been moved to `quarantined` if that was possible
+    Corrupt { path: String, error: String, quarantined: Option<String> },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "failed to read {}: {}", path, error),
+            LoadError::Corrupt { path, error, quarantined: Some(q) } => {
+                write!(f, "{} is corrupt ({}); moved to {}", path, error, q)
+            }
+            LoadError::Corrupt { path, error, quarantined: None } => write!(f, "{} is corrupt: {}", path, error),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default> Store<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Store { path, backups: DEFAULT_BACKUPS, disposable: false, _marker: PhantomData }
+    }
+
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// For caches: a corrupt file is still quarantined, but doesn't block new writes
+    pub fn disposable(mut self) -> Self {
+        self.disposable = true;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the value; a missing file gives the default. A file that doesn't parse is
+    /// quarantined and, unless the store is disposable, further saves are refused until
+    /// it's restored or dismissed (see `get_storage_health`).
+    pub fn load(&self) -> Result<T, LoadError> {
+        let s = match fs::read_to_string(&self.path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+            Err(e) => return Err(LoadError::Io { path: self.path.display().to_string(), error: e.to_string() }),
+        };
+        serde_json::from_str(&s).map_err(|e| {
+            let err = quarantine(&self.path, e.to_string());
+            if self.disposable {
+                dismiss_issue(&self.path);
+            }
+            err
+        })
+    }
+
+    pub fn save(&self, value: &T) -> Result<(), KanoError> {
+        let _writing = crate::data_dir::begin_write()?;
+        check_writable(&self.path)?;
+        let _lock = FileLock::acquire(&self.path)?;
+        self.write(value)
+    }
+
+    /// Newest backup that still parses
+    pub fn latest_backup(&self) -> Option<PathBuf> {
+        (1..=self.backups)
+            .map(|i| backup_path(&self.path, i))
+            .find(|b| read_json::<T>(b).ok().flatten().is_some())
+    }
+
+    /// Put the newest readable backup back in place and clear the corruption flag
+    pub fn restore_latest_backup(&self) -> Result<PathBuf, KanoError> {
+        let backup = self.latest_backup().ok_or(KanoError::not_found("backup"))?;
+        let data = fs::read(&backup).map_err(|e| KanoError::io(&format!("read {}", backup.display()), e))?;
+        let _writing = crate::data_dir::begin_write()?;
+        let _lock = FileLock::acquire(&self.path)?;
+        if self.path.exists() {
+            // whatever is there now (e.g. defaults written after a dismiss) is kept aside too
+            move_aside(&self.path).map_err(|e| KanoError::io(&format!("move {} aside", self.path.display()), e))?;
+        }
+        write_atomic(&self.path, &data)?;
+        dismiss_issue(&self.path);
+        Ok(backup)
+    }
+
+    fn write(&self, value: &T) -> Result<(), KanoError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| KanoError::io(&format!("create {}", parent.display()), e))?;
+        }
+        let data = serde_json::to_vec_pretty(value)?;
+        if self.path.exists() && self.backups > 0 {
+            rotate_backups(&self.path, self.backups);
+            if let Err(e) = fs::copy(&self.path, backup_path(&self.path, 1)) {
+                eprintln!("backup of {} failed: {}", self.path.display(), e);
+            }
+        }
+        write_atomic(&self.path, &data)
+    }
+}
+
+/// A file that failed to load during this run
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageIssue {
+    pub path: String,
+    pub error: String,
+    pub quarantined: Option<String>,
+    pub detected: String,
+}
+
+// Files listed here refuse writes: the data in memory came from defaults, and writing it
+// would replace what the user still might want back.
+static ISSUES: Mutex<Vec<StorageIssue>> = Mutex::new(Vec::new());
+
+pub fn issues() -> Vec<StorageIssue> {
+    ISSUES.lock().map(|v| v.clone()).unwrap_or_default()
+}
+
+pub fn issue_for(path: &Path) -> Option<StorageIssue> {
+    let key = path.display().to_string();
+    issues().into_iter().find(|i| i.path == key)
+}
+
+/// Accept the loss and allow writing the file again
+pub fn dismiss_issue(path: &Path) {
+    let key = path.display().to_string();
+    if let Ok(mut v) = ISSUES.lock() {
+        v.retain(|i| i.path != key);
+    }
+}
+
+/// Record a file that failed to load and block writes to it
+pub fn report_issue(path: &Path, error: String, quarantined: Option<PathBuf>) {
+    let issue = StorageIssue {
+        path: path.display().to_string(),
+        error,
+        quarantined: quarantined.map(|q| q.display().to_string()),
+        detected: chrono::Local::now().to_rfc3339(),
+    };
+    if let Ok(mut v) = ISSUES.lock() {
+        v.retain(|i| i.path != issue.path);
+        v.push(issue);
+    }
+}
+
+pub fn check_writable(path: &Path) -> Result<(), KanoError> {
+    match issue_for(path) {
+        Some(i) => Err(KanoError::Corrupt {
+            message: format!(
+                "refusing to overwrite {}: it failed to load ({}); restore it from a backup or dismiss the problem first",
+                i.path, i.error
+            ),
+            path: i.path,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Move an unreadable file to `<stem>.corrupt-<timestamp>.<ext>` and block writes to it
+pub fn quarantine(path: &Path, error: String) -> LoadError {
+    eprintln!("{} is corrupt: {}", path.display(), error);
+    let quarantined = match move_aside(path) {
+        Ok(q) => Some(q),
+        Err(e) => {
+            eprintln!("failed to quarantine {}: {}", path.display(), e);
+            None
+        }
+    };
+    report_issue(path, error.clone(), quarantined.clone());
+    LoadError::Corrupt {
+        path: path.display().to_string(),
+        error,
+        quarantined: quarantined.map(|q| q.display().to_string()),
+    }
+}
+
+fn move_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let target = quarantine_path(path);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// `games_db.json` -> `games_db.corrupt-20240101-120000.json`
+pub fn quarantine_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let ts = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut target = path.with_file_name(format!("{}.corrupt-{}{}", stem, ts, ext));
+    let mut n = 1;
+    while target.exists() {
+        target = path.with_file_name(format!("{}.corrupt-{}-{}{}", stem, ts, n, ext));
+        n += 1;
+    }
+    target
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, KanoError> {
+    let s = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(KanoError::io(&format!("read {}", path.display()), e)),
+    };
+    Ok(Some(serde_json::from_str(&s)?))
+}
+
+/// `games_db.json` -> `games_db.json.2`
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_os_string();
+    s.push(format!(".{}", n));
+    PathBuf::from(s)
+}
+
+/// Shift `<file>.1..N-1` to `.2..N`, dropping the oldest, so `.1` is free for a new backup
+pub fn rotate_backups(path: &Path, keep: usize) {
+    if keep == 0 {
+        return;
+    }
+    let _ = fs::remove_file(backup_path(path, keep));
+    for i in (1..keep).rev() {
+        let from = backup_path(path, i);
+        if from.exists() {
+            let _ = fs::rename(&from, backup_path(path, i + 1));
+        }
+    }
+}
+
+/// Write through a fsynced temp file and rename it over `path`
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), KanoError> {
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    let res = (|| -> std::io::Result<()> {
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+        fs::rename(&tmp, path)?;
+        // make the rename itself durable (not possible on Windows, where it's not needed)
+        #[cfg(unix)]
+        if let Some(parent) = path.parent() {
+            if let Ok(dir) = fs::File::open(parent) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    })();
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res.map_err(|e| KanoError::io(&format!("write {}", path.display()), e))
+}
+
+/// `<file>.lock` containing "<pid> <unix seconds> <token>"; removed on drop
+pub struct FileLock {
+    path: PathBuf,
+    content: String,
+}
+
+impl FileLock {
+    pub fn acquire(target: &Path) -> Result<FileLock, KanoError> {
+        let path = target.with_extension("lock");
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        for _ in 0..LOCK_ATTEMPTS {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                    let content = format!("{} {} {}", std::process::id(), now, uuid::Uuid::new_v4().simple());
+                    let _ = f.write_all(content.as_bytes());
+                    return Ok(FileLock { path, content });
+                }
+                Err(_) => {
+                    if let Some(seen) = stale_lock(&path) {
+                        remove_stale(&path, &seen);
+                        continue;
+                    }
+                    std::thread::sleep(LOCK_RETRY);
+                }
+            }
+        }
+        Err(KanoError::LockTimeout { path: path.display().to_string() })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // if the lock was taken over as stale meanwhile, it belongs to someone else now
+        if fs::read_to_string(&self.path).is_ok_and(|c| c == self.content) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Several waiters can find the same stale lock. Each moves it aside under its own name,
+// which only one of them manages; whoever moved a lock that isn't the one it judged stale
+// (a waiter that got in first already replaced it) puts it back.
+fn remove_stale(lock: &Path, seen: &str) {
+    let aside = lock.with_extension(format!("lock.stale-{}", uuid::Uuid::new_v4().simple()));
+    if fs::rename(lock, &aside).is_err() {
+        return; // someone else got there first
+    }
+    if fs::read_to_string(&aside).is_ok_and(|c| c == seen) {
+        eprintln!("removed stale lock {}", lock.display());
+    } else if let Err(e) = fs::hard_link(&aside, lock) {
+        eprintln!("failed to put back lock {}: {}", lock.display(), e);
+    }
+    let _ = fs::remove_file(&aside);
+}
+
+// A lock is stale when its owner is gone or it has been held far longer than any write takes.
+// Locks from older versions have no content; they are judged by the file's age.
+// Returns the content it was judged by.
+fn stale_lock(lock: &Path) -> Option<String> {
+    let content = fs::read_to_string(lock).ok()?;
+    let mut parts = content.split_whitespace();
+    let pid: Option<u32> = parts.next().and_then(|p| p.parse().ok());
+    let age = match parts.next().and_then(|t| t.parse::<u64>().ok()) {
+        Some(t) => SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|now| now.saturating_sub(Duration::from_secs(t)))
+            .unwrap_or_default(),
+        None => fs::metadata(lock)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.elapsed().ok())
+            .unwrap_or_default(),
+    };
+    if age > LOCK_STALE_AFTER {
+        return Some(content);
+    }
+    let owner_gone = match pid {
+        Some(pid) if pid != std::process::id() => {
+            let procs = crate::process_tree::snapshot();
+            // an empty snapshot means we can't tell; rely on the age then
+            !procs.is_empty() && !procs.iter().any(|p| p.pid == pid)
+        }
+        _ => false,
+    };
+    owner_gone.then_some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kano-store-{}-{}", std::process::id(), uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn read(path: &Path) -> Option<u32> {
+        read_json(path).unwrap()
+    }
+
+    #[test]
+    fn save_rotates_backups() {
+        let path = temp_file("n.json");
+        let store: Store<u32> = Store::new(path.clone()).with_backups(2);
+        for n in 1..=4 {
+            store.save(&n).unwrap();
+        }
+        assert_eq!(store.load().unwrap(), 4);
+        assert_eq!(read(&backup_path(&path, 1)), Some(3));
+        assert_eq!(read(&backup_path(&path, 2)), Some(2));
+        assert!(!backup_path(&path, 3).exists());
+        assert!(!path.with_extension("lock").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined_and_blocks_saves() {
+        let path = temp_file("c.json");
+        let store: Store<u32> = Store::new(path.clone());
+        store.save(&1).unwrap();
+        store.save(&2).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        let err = store.load().unwrap_err();
+        let quarantined = match err {
+            LoadError::Corrupt { quarantined: Some(q), .. } => PathBuf::from(q),
+            e => panic!("unexpected {:?}", e),
+        };
+        assert_eq!(fs::read_to_string(&quarantined).unwrap(), "{ not json");
+        assert!(!path.exists());
+        assert!(matches!(store.save(&3), Err(KanoError::Corrupt { .. })));
+
+        // restoring the backup puts the last good value back and allows saving again
+        assert_eq!(store.restore_latest_backup().unwrap(), backup_path(&path, 1));
+        assert_eq!(store.load().unwrap(), 1);
+        store.save(&3).unwrap();
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn disposable_store_keeps_saving_after_corruption() {
+        let path = temp_file("d.json");
+        let store: Store<u32> = Store::new(path.clone()).disposable();
+        fs::write(&path, "garbage").unwrap();
+        assert!(store.load().is_err());
+        store.save(&5).unwrap();
+        assert_eq!(store.load().unwrap(), 5);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn stale_locks_are_taken_over() {
+        let path = temp_file("s.json");
+        let lock = path.with_extension("lock");
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let cases = [
+            format!("{} {}", std::process::id(), now - 3600), // too old
+            format!("{} {}", u32::MAX - 7, now),              // owner is gone
+        ];
+        for content in cases {
+            fs::write(&lock, &content).unwrap();
+            assert_eq!(stale_lock(&lock).as_deref(), Some(content.as_str()));
+            let held = FileLock::acquire(&path).unwrap();
+            assert_ne!(fs::read_to_string(&lock).unwrap(), content);
+            drop(held);
+            assert!(!lock.exists());
+        }
+        // our own, fresh lock isn't stale
+        fs::write(&lock, format!("{} {}", std::process::id(), now)).unwrap();
+        assert!(stale_lock(&lock).is_none());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn stale_removal_puts_back_a_lock_that_was_replaced() {
+        let path = temp_file("r.json");
+        let lock = path.with_extension("lock");
+        // another waiter already replaced the stale lock with its own
+        fs::write(&lock, "fresh").unwrap();
+        remove_stale(&lock, "old");
+        assert_eq!(fs::read_to_string(&lock).unwrap(), "fresh");
+
+        remove_stale(&lock, "fresh");
+        assert!(!lock.exists());
+        let leftovers = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(leftovers, 0);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn dropping_a_lock_taken_over_leaves_the_new_one() {
+        let path = temp_file("t.json");
+        let lock = path.with_extension("lock");
+        let held = FileLock::acquire(&path).unwrap();
+        fs::write(&lock, "someone else").unwrap();
+        drop(held);
+        assert_eq!(fs::read_to_string(&lock).unwrap(), "someone else");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,911 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::commands::notes::{self, Note};
+use crate::commands::relocate::strip_prefix_components;
+use crate::config::{load_games_db, load_sessions, save_sessions, sync_state_path};
+use crate::db;
+use crate::error::KanoError;
+use crate::metadata::USER_AGENT;
+use crate::models::{GameEntry, GamesDB, PathMapping, PlaySession, SyncBackendConfig, SyncSettings};
+use crate::store::{write_atomic, FileLock, Store};
+
+/// Name of the shared document inside the sync folder / WebDAV collection
+const SYNC_FILE: &str = "kano_sync.json";
+const SYNC_VERSION: u32 = 1;
+/// Game settings that only make sense on the machine they were made on; kept per machine
+/// in `SyncRecord::local` like the exe path, never as shared data
+const MACHINE_FIELDS: &[&str] = &[
+    "folder_path",
+    "save_dirs",
+    "launch_profiles",
+    "default_profile",
+    "runner_id",
+    "pre_launch_hooks",
+    "post_exit_hooks",
+    "companion_tools",
+    "close_companions_on_exit",
+];
+
+/// Where the shared document lives. `read` returns the document together with a version
+/// tag; `write` must fail when the remote no longer matches that version so two machines
+/// syncing at once can't silently overwrite each other.
+pub trait SyncBackend {
+    fn read(&self) -> Result<Option<(Vec<u8>, String)>, KanoError>;
+    fn write(&self, data: &[u8], expected: Option<&str>) -> Result<(), KanoError>;
+}
+
+fn remote_changed() -> KanoError {
+    KanoError::Busy { reason: "remote_changed", message: "remote changed during sync, try again".into() }
+}
+
+pub fn backend_from_config(cfg: &SyncBackendConfig) -> Result<Box<dyn SyncBackend>, KanoError> {
+    match cfg {
+        SyncBackendConfig::Folder { path } => Ok(Box::new(FolderBackend { dir: PathBuf::from(path) })),
+        SyncBackendConfig::Webdav { url, username, password } => Ok(Box::new(WebDavBackend {
+            url: format!("{}/{}", url.trim_end_matches('/'), SYNC_FILE),
+            username: username.clone(),
+            password: password.clone(),
+            client: reqwest::blocking::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(std::time::Duration::from_secs(30))
+                .build()?,
+        })),
+    }
+}
+
+/// A plain directory kept in sync by something else (Syncthing, Dropbox, a network share)
+pub struct FolderBackend {
+    pub dir: PathBuf,
+}
+
+impl FolderBackend {
+    fn version(&self) -> Option<String> {
+        let meta = fs::metadata(self.dir.join(SYNC_FILE)).ok()?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Some(format!("{}-{}", modified, meta.len()))
+    }
+}
+
+impl SyncBackend for FolderBackend {
+    fn read(&self) -> Result<Option<(Vec<u8>, String)>, KanoError> {
+        if !self.dir.is_dir() {
+            return Err(KanoError::not_found("folder"));
+        }
+        let version = match self.version() {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let data = fs::read(self.dir.join(SYNC_FILE)).map_err(|e| KanoError::io("read sync file", e))?;
+        Ok(Some((data, version)))
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&str>) -> Result<(), KanoError> {
+        // the version check and the rename happen under the lock, so another sync from
+        // this machine can't slip in between them
+        let path = self.dir.join(SYNC_FILE);
+        let _lock = FileLock::acquire(&path)?;
+        if self.version().as_deref() != expected {
+            return Err(remote_changed());
+        }
+        write_atomic(&path, data)
+    }
+}
+
+/// A file on a WebDAV server; ETags guard against concurrent writers
+pub struct WebDavBackend {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+impl WebDavBackend {
+    fn request(&self, method: reqwest::Method) -> reqwest::blocking::RequestBuilder {
+        let req = self.client.request(method, &self.url);
+        match &self.username {
+            Some(user) => req.basic_auth(user, self.password.as_ref()),
+            None => req,
+        }
+    }
+}
+
+impl SyncBackend for WebDavBackend {
+    fn read(&self) -> Result<Option<(Vec<u8>, String)>, KanoError> {
+        let resp = self.request(reqwest::Method::GET).send()?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(KanoError::Http { status: resp.status().as_u16(), message: "WebDAV GET failed".into() });
+        }
+        let version = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .or_else(|| resp.headers().get(reqwest::header::LAST_MODIFIED))
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let data = resp.bytes()?.to_vec();
+        Ok(Some((data, version)))
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&str>) -> Result<(), KanoError> {
+        let mut req = self
+            .request(reqwest::Method::PUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(data.to_vec());
+        req = match expected {
+            Some(v) if v.starts_with('"') || v.starts_with("W/") => req.header(reqwest::header::IF_MATCH, v),
+            Some(v) if !v.is_empty() => req.header(reqwest::header::IF_UNMODIFIED_SINCE, v),
+            Some(_) => req,
+            None => req.header(reqwest::header::IF_NONE_MATCH, "*"),
+        };
+        let resp = req.send()?;
+        if resp.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+            return Err(remote_changed());
+        }
+        if !resp.status().is_success() {
+            return Err(KanoError::Http { status: resp.status().as_u16(), message: "WebDAV PUT failed".into() });
+        }
+        Ok(())
+    }
+}
+
+/// The shared document: every record of every collection with who changed it last
+#[derive(Serialize, Deserialize, Default)]
+pub struct SyncDocument {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub updated: String,
+    #[serde(default)]
+    pub machines: BTreeMap<String, String>, // machine id -> name
+    #[serde(default)]
+    pub collections: BTreeMap<String, BTreeMap<String, SyncRecord>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SyncRecord {
+    pub modified: String,
+    pub machine: String,
+    #[serde(default)]
+    pub deleted: bool, // tombstone, so a deletion reaches the other machines
+    #[serde(default)]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub paths: BTreeMap<String, String>, // games: exe path per machine id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub local: BTreeMap<String, Value>, // games: MACHINE_FIELDS per machine id
+}
+
+/// What each record looked like after the last sync on this machine, for three-way merging
+#[derive(Serialize, Deserialize, Default)]
+pub struct SyncState {
+    #[serde(default)]
+    pub hashes: BTreeMap<String, BTreeMap<String, String>>, // collection -> record id -> fingerprint
+    #[serde(default)]
+    pub playtime: BTreeMap<String, i64>,                    // game id -> playtime at last sync
+}
+
+pub fn state_store() -> Store<SyncState> {
+    Store::new(sync_state_path())
+}
+
+/// The local data a sync merges with
+pub trait LocalLibrary {
+    fn load_games(&self) -> Result<GamesDB, KanoError>;
+    /// `loaded` is what `load_games` returned, so only changed games need writing
+    fn save_games(&self, loaded: &GamesDB, db: &GamesDB) -> Result<(), KanoError>;
+    fn load_notes(&self) -> Result<Vec<Note>, KanoError>;
+    /// `loaded` is what `load_notes` returned, so only changed notes need writing
+    fn save_notes(&self, loaded: &[Note], notes: &[Note]) -> Result<(), KanoError>;
+    fn load_sessions(&self) -> Result<Vec<PlaySession>, KanoError>;
+    fn save_sessions(&self, sessions: &[PlaySession]) -> Result<(), KanoError>;
+    fn load_state(&self) -> Result<SyncState, KanoError>;
+    fn save_state(&self, state: &SyncState) -> Result<(), KanoError>;
+}
+
+/// The app's own library in kano_data
+pub struct AppLibrary;
+
+impl LocalLibrary for AppLibrary {
+    fn load_games(&self) -> Result<GamesDB, KanoError> {
+        Ok(load_games_db()?.into_inner())
+    }
+    fn save_games(&self, loaded: &GamesDB, db: &GamesDB) -> Result<(), KanoError> {
+        db::save_games_changes(loaded, db)
+    }
+    fn load_notes(&self) -> Result<Vec<Note>, KanoError> {
+        notes::load_all()
+    }
+    fn save_notes(&self, loaded: &[Note], notes: &[Note]) -> Result<(), KanoError> {
+        notes::save_changes(loaded, notes)
+    }
+    fn load_sessions(&self) -> Result<Vec<PlaySession>, KanoError> {
+        Ok(load_sessions()?)
+    }
+    fn save_sessions(&self, sessions: &[PlaySession]) -> Result<(), KanoError> {
+        save_sessions(sessions)
+    }
+    fn load_state(&self) -> Result<SyncState, KanoError> {
+        Ok(state_store().load()?)
+    }
+    fn save_state(&self, state: &SyncState) -> Result<(), KanoError> {
+        state_store().save(state)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub conflicts: Vec<String>,
+    pub remote_updated: bool,
+    pub synced_at: String,
+}
+
+/// Pull the shared document, merge it record by record with the local library and push the result.
+pub fn run_sync(settings: &SyncSettings) -> Result<SyncReport, KanoError> {
+    let backend_cfg = settings
+        .backend
+        .as_ref()
+        .ok_or_else(|| KanoError::invalid("sync_not_configured", "sync is not configured"))?;
+    let backend = backend_from_config(backend_cfg)?;
+    sync_with(backend.as_ref(), &AppLibrary, settings)
+}
+
+/// The remote is written first; local files and the merge base only once it has the
+/// result. If the remote write fails nothing local changes, so the next sync merges the
+/// same deltas again instead of counting pulled playtime twice.
+pub fn sync_with(backend: &dyn SyncBackend, local: &dyn LocalLibrary, settings: &SyncSettings) -> Result<SyncReport, KanoError> {
+    let machine = settings.machine_id.as_str();
+    if machine.is_empty() {
+        return Err(KanoError::invalid("machine_id_missing", "machine id missing"));
+    }
+    let (mut doc, version) = match backend.read()? {
+        Some((data, version)) => {
+            let doc: SyncDocument = serde_json::from_slice(&data)
+                .map_err(|e| KanoError::Parse { message: format!("remote sync file is invalid: {}", e) })?;
+            (doc, Some(version))
+        }
+        None => (SyncDocument { version: SYNC_VERSION, ..Default::default() }, None),
+    };
+    if doc.version > SYNC_VERSION {
+        return Err(KanoError::invalid(
+            "sync_format_too_new",
+            format!("remote was written by a newer Kano (format {})", doc.version),
+        ));
+    }
+    let before = serde_json::to_vec(&doc)?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut state = local.load_state()?;
+    let mut report = SyncReport { synced_at: now.clone(), ..Default::default() };
+    let mut ctx = MergeCtx { machine, now: &now, report: &mut report };
+
+    // Games: the exe path and MACHINE_FIELDS are machine specific and kept per machine
+    // outside the record data; playtime is merged as a sum of deltas so sessions on two
+    // machines both count.
+    let local_db = local.load_games()?;
+    let mut db = local_db.clone();
+    let mut local_paths = BTreeMap::new();
+    let mut local_fields = BTreeMap::new();
+    let mut local_games = BTreeMap::new();
+    for g in &db.games {
+        local_paths.insert(g.id.clone(), g.path.clone());
+        let mut v = serde_json::to_value(g)?;
+        local_fields.insert(g.id.clone(), take_machine_fields(&mut v));
+        local_games.insert(g.id.clone(), v);
+    }
+    let local_playtime: BTreeMap<String, (i64, Option<String>)> = db
+        .games
+        .iter()
+        .map(|g| (g.id.clone(), (g.playtime, g.last_played.clone())))
+        .collect();
+    let remote_games = doc.collections.entry("games".into()).or_default();
+    let remote_playtime: BTreeMap<String, (i64, Option<String>)> = remote_games
+        .iter()
+        .filter(|(_, r)| !r.deleted)
+        .filter_map(|(id, r)| {
+            let d = r.data.as_ref()?;
+            let pt = d.get("playtime").and_then(|v| v.as_i64()).unwrap_or(0);
+            let lp = d.get("last_played").and_then(|v| v.as_str()).map(|s| s.to_string());
+            Some((id.clone(), (pt, lp)))
+        })
+        .collect();
+    ctx.merge("games", &mut local_games, remote_games, state.hashes.entry("games".into()).or_default(), game_fingerprint);
+
+    state.playtime.retain(|id, _| local_games.contains_key(id));
+    for (id, value) in local_games.iter_mut() {
+        let (pt, lp) = match (local_playtime.get(id), remote_playtime.get(id)) {
+            (Some((l, llp)), Some((r, rlp))) => {
+                // both sides' play since the last sync counts
+                let pt = match state.playtime.get(id) {
+                    Some(base) => l + r - base,
+                    None => *l.max(r),
+                };
+                (pt.max(0), llp.clone().max(rlp.clone()))
+            }
+            (Some((l, llp)), None) => (*l, llp.clone()),
+            (None, Some((r, rlp))) => (*r, rlp.clone()),
+            (None, None) => continue,
+        };
+        state.playtime.insert(id.clone(), pt);
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("playtime".into(), Value::from(pt));
+            obj.insert("last_played".into(), serde_json::to_value(&lp).unwrap_or(Value::Null));
+        }
+        if let Some(rec) = remote_games.get_mut(id) {
+            if let Some(data) = rec.data.as_mut().and_then(|d| d.as_object_mut()) {
+                data.insert("playtime".into(), Value::from(pt));
+                data.insert("last_played".into(), serde_json::to_value(&lp).unwrap_or(Value::Null));
+            }
+            let p = match local_paths.get(id) {
+                Some(p) => p.clone(),
+                None => map_path(&rec.paths, machine, &settings.path_mappings),
+            };
+            rec.paths.insert(machine.to_string(), p.clone());
+            local_paths.insert(id.clone(), p);
+            // a game pulled for the first time gets what this machine had before, if anything
+            let fields = match local_fields.get(id) {
+                Some(f) => f.clone(),
+                None => rec.local.get(machine).cloned().unwrap_or_else(|| Value::Object(Default::default())),
+            };
+            rec.local.insert(machine.to_string(), fields.clone());
+            local_fields.insert(id.clone(), fields);
+        }
+    }
+
+    // Rebuild the games list, keeping the local order and appending pulled games
+    let mut games: Vec<GameEntry> = Vec::new();
+    let mut order: Vec<String> = db.games.iter().map(|g| g.id.clone()).collect();
+    order.extend(local_games.keys().filter(|id| !local_playtime.contains_key(*id)).cloned());
+    for id in order {
+        let mut value = match local_games.remove(&id) {
+            Some(v) => v,
+            None => continue,
+        };
+        // records written before MACHINE_FIELDS were split off still carry them
+        take_machine_fields(&mut value);
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("path".into(), Value::from(local_paths.get(&id).cloned().unwrap_or_default()));
+            if let Some(Value::Object(fields)) = local_fields.remove(&id) {
+                obj.extend(fields);
+            }
+        }
+        match serde_json::from_value::<GameEntry>(value) {
+            Ok(g) => games.push(g),
+            Err(e) => ctx.report.conflicts.push(format!("game {}: unreadable record ({})", id, e)),
+        }
+    }
+    db.games = games;
+
+    // Custom tags, notes and sessions are plain records
+    let mut local_tags: BTreeMap<String, Value> = db.custom_tags.iter().map(|t| (t.clone(), Value::Bool(true))).collect();
+    let remote_tags = doc.collections.entry("tags".into()).or_default();
+    ctx.merge("tags", &mut local_tags, remote_tags, state.hashes.entry("tags".into()).or_default(), fingerprint);
+    let kept: Vec<String> = db.custom_tags.iter().filter(|t| local_tags.contains_key(*t)).cloned().collect();
+    let added: Vec<String> = local_tags.keys().filter(|t| !db.custom_tags.contains(t)).cloned().collect();
+    db.custom_tags = kept.into_iter().chain(added).collect();
+
+    let local_notes = local.load_notes()?;
+    let mut notes_map = BTreeMap::new();
+    for n in &local_notes {
+        notes_map.insert(note_key(n), serde_json::to_value(n)?);
+    }
+    let remote_notes = doc.collections.entry("notes".into()).or_default();
+    ctx.merge("notes", &mut notes_map, remote_notes, state.hashes.entry("notes".into()).or_default(), fingerprint);
+    let mut notes: Vec<Note> = notes_map.into_values().filter_map(|v| serde_json::from_value(v).ok()).collect();
+    // keep each note on its row
+    for n in notes.iter_mut() {
+        n.row_id = local_notes.iter().find(|l| note_key(l) == note_key(n)).and_then(|l| l.row_id);
+    }
+
+    let mut sessions_map = BTreeMap::new();
+    for s in local.load_sessions()? {
+        sessions_map.insert(s.id.clone(), serde_json::to_value(&s)?);
+    }
+    let remote_sessions = doc.collections.entry("sessions".into()).or_default();
+    ctx.merge("sessions", &mut sessions_map, remote_sessions, state.hashes.entry("sessions".into()).or_default(), fingerprint);
+    let mut sessions: Vec<PlaySession> = sessions_map.into_values().filter_map(|v| serde_json::from_value(v).ok()).collect();
+    sessions.sort_by(|a, b| a.start.cmp(&b.start));
+
+    if !settings.machine_name.is_empty() {
+        doc.machines.insert(machine.to_string(), settings.machine_name.clone());
+    } else {
+        doc.machines.entry(machine.to_string()).or_default();
+    }
+    doc.version = SYNC_VERSION;
+
+    if serde_json::to_vec(&doc)? != before {
+        doc.updated = now.clone();
+        let data = serde_json::to_vec_pretty(&doc)?;
+        backend.write(&data, version.as_deref())?;
+        report.remote_updated = true;
+    }
+    local.save_games(&local_db, &db)?;
+    local.save_notes(&local_notes, &notes)?;
+    local.save_sessions(&sessions)?;
+    local.save_state(&state)?;
+    Ok(report)
+}
+
+struct MergeCtx<'a> {
+    machine: &'a str,
+    now: &'a str,
+    report: &'a mut SyncReport,
+}
+
+impl MergeCtx<'_> {
+    /// Three-way merge of one collection against the fingerprints from the last sync.
+    /// A change on one side wins; if both sides changed the same record differently this
+    /// machine's version is kept (an edit also beats a deletion) and a conflict is reported.
+    fn merge(
+        &mut self,
+        name: &str,
+        local: &mut BTreeMap<String, Value>,
+        remote: &mut BTreeMap<String, SyncRecord>,
+        base: &mut BTreeMap<String, String>,
+        fp: fn(&Value) -> String,
+    ) {
+        let ids: BTreeSet<String> = local.keys().chain(remote.keys()).chain(base.keys()).cloned().collect();
+        for id in ids {
+            let l = local.get(&id).map(fp);
+            let r = remote.get(&id).filter(|r| !r.deleted).and_then(|r| r.data.as_ref()).map(fp);
+            let b = base.get(&id).cloned();
+            let push = match (l != b, r != b) {
+                (false, false) => None,
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (true, true) if l == r => None,
+                (true, true) => {
+                    // a deletion loses against an edit on the other side
+                    let keep_local = l.is_some();
+                    if l.is_some() && r.is_some() {
+                        self.report.conflicts.push(format!("{} {}: changed on several machines, kept this machine's version", name, id));
+                    }
+                    Some(keep_local)
+                }
+            };
+            match push {
+                Some(true) => {
+                    let (paths, machines) = remote.get(&id).map(|r| (r.paths.clone(), r.local.clone())).unwrap_or_default();
+                    remote.insert(id.clone(), SyncRecord {
+                        modified: self.now.to_string(),
+                        machine: self.machine.to_string(),
+                        deleted: l.is_none(),
+                        data: local.get(&id).cloned(),
+                        paths,
+                        local: machines,
+                    });
+                    self.report.pushed += 1;
+                }
+                Some(false) => {
+                    match remote.get(&id).filter(|r| !r.deleted).and_then(|r| r.data.clone()) {
+                        Some(v) => local.insert(id.clone(), v),
+                        None => local.remove(&id),
+                    };
+                    self.report.pulled += 1;
+                }
+                None => {}
+            }
+            match local.get(&id).map(fp) {
+                Some(h) => base.insert(id, h),
+                None => base.remove(&id),
+            };
+        }
+    }
+}
+
+// Remove MACHINE_FIELDS from a game record and return them as an object
+fn take_machine_fields(v: &mut Value) -> Value {
+    let mut fields = serde_json::Map::new();
+    if let Some(obj) = v.as_object_mut() {
+        for f in MACHINE_FIELDS {
+            if let Some(x) = obj.remove(*f) {
+                fields.insert(f.to_string(), x);
+            }
+        }
+    }
+    Value::Object(fields)
+}
+
+// Playtime is merged separately and the machine's own settings are stored per machine,
+// so none of them counts as an edit
+fn game_fingerprint(v: &Value) -> String {
+    let mut v = v.clone();
+    take_machine_fields(&mut v);
+    if let Some(obj) = v.as_object_mut() {
+        obj.remove("playtime");
+        obj.remove("last_played");
+        obj.remove("path");
+    }
+    fingerprint(&v)
+}
+
+// FNV-1a over canonical JSON (sorted keys); stable across runs and app versions
+fn fingerprint(v: &Value) -> String {
+    let mut s = String::new();
+    canonical(v, &mut s);
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in s.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn canonical(v: &Value, out: &mut String) {
+    match v {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for k in keys {
+                out.push_str(&Value::from(k.as_str()).to_string());
+                out.push(':');
+                canonical(&map[k], out);
+                out.push(',');
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for i in items {
+                canonical(i, out);
+                out.push(',');
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+// Notes belong to a game; standalone notes are told apart by creation time
+fn note_key(n: &Note) -> String {
+    match n.game_id.as_deref().filter(|g| !g.is_empty()) {
+        Some(gid) => gid.to_string(),
+        None => format!("note:{}", n.created_at),
+    }
+}
+
+/// Path for a game first added on another machine: apply the first matching prefix mapping,
+/// otherwise keep the other machine's path (it shows up as missing and can be relocated).
+fn map_path(paths: &BTreeMap<String, String>, machine: &str, mappings: &[PathMapping]) -> String {
+    if let Some(p) = paths.get(machine) {
+        return p.clone();
+    }
+    for p in paths.values() {
+        for m in mappings {
+            // a path from a Windows machine is case-insensitive whatever this one runs
+            let windows = p.contains('\\') || p.get(1..2) == Some(":");
+            if let Some(rest) = strip_prefix_components(p, &m.from, windows) {
+                let mut mapped = PathBuf::from(&m.to);
+                for part in rest {
+                    mapped.push(part);
+                }
+                return mapped.to_string_lossy().to_string();
+            }
+        }
+    }
+    paths.values().next().cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kano-sync-{}-{}-{}", name, std::process::id(), uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A machine's library kept as JSON files in a temp dir
+    struct DirLibrary {
+        dir: PathBuf,
+    }
+
+    impl DirLibrary {
+        fn store<T: Serialize + serde::de::DeserializeOwned + Default>(&self, name: &str) -> Store<T> {
+            Store::new(self.dir.join(name))
+        }
+
+        fn put_games(&self, db: &GamesDB) -> Result<(), KanoError> {
+            self.store("games.json").save(db)
+        }
+    }
+
+    impl LocalLibrary for DirLibrary {
+        fn load_games(&self) -> Result<GamesDB, KanoError> {
+            Ok(self.store("games.json").load()?)
+        }
+        fn save_games(&self, _loaded: &GamesDB, db: &GamesDB) -> Result<(), KanoError> {
+            self.store("games.json").save(db)
+        }
+        fn load_notes(&self) -> Result<Vec<Note>, KanoError> {
+            Ok(self.store("notes.json").load()?)
+        }
+        fn save_notes(&self, _loaded: &[Note], notes: &[Note]) -> Result<(), KanoError> {
+            self.store("notes.json").save(&notes.to_vec())
+        }
+        fn load_sessions(&self) -> Result<Vec<PlaySession>, KanoError> {
+            Ok(self.store("sessions.json").load()?)
+        }
+        fn save_sessions(&self, sessions: &[PlaySession]) -> Result<(), KanoError> {
+            self.store("sessions.json").save(&sessions.to_vec())
+        }
+        fn load_state(&self) -> Result<SyncState, KanoError> {
+            Ok(self.store("sync_state.json").load()?)
+        }
+        fn save_state(&self, state: &SyncState) -> Result<(), KanoError> {
+            self.store("sync_state.json").save(state)
+        }
+    }
+
+    fn game(id: &str, path: &str, playtime: i64) -> GameEntry {
+        serde_json::from_value(serde_json::json!({ "id": id, "name": id, "path": path, "playtime": playtime })).unwrap()
+    }
+
+    fn settings(machine: &str, mappings: &[(&str, &str)]) -> SyncSettings {
+        SyncSettings {
+            machine_id: machine.into(),
+            machine_name: machine.to_uppercase(),
+            path_mappings: mappings.iter().map(|(f, t)| PathMapping { from: f.to_string(), to: t.to_string() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn games_of(lib: &DirLibrary) -> Vec<(String, String, i64)> {
+        lib.load_games().unwrap().games.into_iter().map(|g| (g.id, g.path, g.playtime)).collect()
+    }
+
+    fn set_playtime(lib: &DirLibrary, id: &str, playtime: i64) {
+        let mut db = lib.load_games().unwrap();
+        db.games.iter_mut().find(|g| g.id == id).unwrap().playtime = playtime;
+        lib.put_games(&db).unwrap();
+    }
+
+    #[test]
+    fn two_machines_share_games_playtime_and_deletions() {
+        let remote = FolderBackend { dir: temp_dir("remote") };
+        let a = DirLibrary { dir: temp_dir("a") };
+        let b = DirLibrary { dir: temp_dir("b") };
+        let sa = settings("a", &[]);
+        let sb = settings("b", &[("D:\\Games", "/home/me/Games")]);
+
+        a.put_games(&GamesDB { games: vec![game("g1", "D:\\Games\\Foo\\foo.exe", 100)], ..Default::default() }).unwrap();
+        let r = sync_with(&remote, &a, &sa).unwrap();
+        assert_eq!((r.pushed, r.pulled, r.remote_updated), (1, 0, true));
+
+        // B gets the game with its path mapped to this machine
+        let r = sync_with(&remote, &b, &sb).unwrap();
+        assert_eq!((r.pushed, r.pulled), (0, 1));
+        assert_eq!(games_of(&b), vec![("g1".into(), "/home/me/Games/Foo/foo.exe".into(), 100)]);
+
+        // both play: the deltas add up on both sides
+        set_playtime(&a, "g1", 130);
+        set_playtime(&b, "g1", 160);
+        sync_with(&remote, &a, &sa).unwrap();
+        sync_with(&remote, &b, &sb).unwrap();
+        sync_with(&remote, &a, &sa).unwrap();
+        assert_eq!(games_of(&a), vec![("g1".into(), "D:\\Games\\Foo\\foo.exe".into(), 190)]);
+        assert_eq!(games_of(&b), vec![("g1".into(), "/home/me/Games/Foo/foo.exe".into(), 190)]);
+
+        // nothing changed: the remote isn't rewritten
+        let r = sync_with(&remote, &b, &sb).unwrap();
+        assert!(!r.remote_updated);
+
+        // a deletion travels as a tombstone
+        a.put_games(&GamesDB::default()).unwrap();
+        let r = sync_with(&remote, &a, &sa).unwrap();
+        assert_eq!(r.pushed, 1);
+        let doc: SyncDocument = serde_json::from_slice(&remote.read().unwrap().unwrap().0).unwrap();
+        assert!(doc.collections["games"]["g1"].deleted);
+        sync_with(&remote, &b, &sb).unwrap();
+        assert!(games_of(&b).is_empty());
+
+        for dir in [&remote.dir, &a.dir, &b.dir] {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    /// Reads the folder but refuses every write, like a remote changed by another machine
+    struct Rejecting(FolderBackend);
+
+    impl SyncBackend for Rejecting {
+        fn read(&self) -> Result<Option<(Vec<u8>, String)>, KanoError> {
+            self.0.read()
+        }
+        fn write(&self, _: &[u8], _: Option<&str>) -> Result<(), KanoError> {
+            Err(remote_changed())
+        }
+    }
+
+    #[test]
+    fn failed_remote_write_keeps_local_changes_pending() {
+        let remote = FolderBackend { dir: temp_dir("reject") };
+        let a = DirLibrary { dir: temp_dir("reject-a") };
+        a.put_games(&GamesDB { games: vec![game("g1", "/g/1.exe", 0)], ..Default::default() }).unwrap();
+        let rejecting = Rejecting(FolderBackend { dir: remote.dir.clone() });
+        assert_eq!(sync_with(&rejecting, &a, &settings("a", &[])).unwrap_err().code(), "busy");
+        // the next sync still pushes the game
+        let r = sync_with(&remote, &a, &settings("a", &[])).unwrap();
+        assert_eq!(r.pushed, 1);
+        let _ = fs::remove_dir_all(&remote.dir);
+        let _ = fs::remove_dir_all(&a.dir);
+    }
+
+    #[test]
+    fn failed_remote_write_does_not_count_playtime_twice() {
+        let remote = FolderBackend { dir: temp_dir("twice") };
+        let a = DirLibrary { dir: temp_dir("twice-a") };
+        let b = DirLibrary { dir: temp_dir("twice-b") };
+        let (sa, sb) = (settings("a", &[]), settings("b", &[]));
+        a.put_games(&GamesDB { games: vec![game("g1", "/g/1.exe", 100)], ..Default::default() }).unwrap();
+        sync_with(&remote, &a, &sa).unwrap();
+        sync_with(&remote, &b, &sb).unwrap();
+
+        set_playtime(&b, "g1", 160);
+        sync_with(&remote, &b, &sb).unwrap();
+        set_playtime(&a, "g1", 130);
+        let rejecting = Rejecting(FolderBackend { dir: remote.dir.clone() });
+        assert!(sync_with(&rejecting, &a, &sa).is_err());
+        assert_eq!(games_of(&a)[0].2, 130);
+
+        // 100 + 30 on A + 60 on B, however many attempts it took
+        sync_with(&remote, &a, &sa).unwrap();
+        sync_with(&remote, &b, &sb).unwrap();
+        assert_eq!(games_of(&a)[0].2, 190);
+        assert_eq!(games_of(&b)[0].2, 190);
+        for dir in [&remote.dir, &a.dir, &b.dir] {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn machine_settings_stay_on_their_machine() {
+        let remote = FolderBackend { dir: temp_dir("local") };
+        let a = DirLibrary { dir: temp_dir("local-a") };
+        let b = DirLibrary { dir: temp_dir("local-b") };
+        let (sa, sb) = (settings("a", &[]), settings("b", &[]));
+        let mut g = game("g1", "/g/1.exe", 0);
+        g.save_dirs = vec!["%APPDATA%\\Foo".into()];
+        g.runner_id = Some("le".into());
+        g.companion_tools = vec!["t1".into()];
+        a.put_games(&GamesDB { games: vec![g], ..Default::default() }).unwrap();
+        sync_with(&remote, &a, &sa).unwrap();
+
+        // B gets the game without A's settings
+        sync_with(&remote, &b, &sb).unwrap();
+        let gb = b.load_games().unwrap().games.remove(0);
+        assert!(gb.save_dirs.is_empty() && gb.runner_id.is_none() && gb.companion_tools.is_empty());
+        let doc: SyncDocument = serde_json::from_slice(&remote.read().unwrap().unwrap().0).unwrap();
+        let data = doc.collections["games"]["g1"].data.as_ref().unwrap();
+        assert!(MACHINE_FIELDS.iter().all(|f| data.get(*f).is_none()));
+
+        // B's own settings are no edit to the shared record and don't reach A
+        let mut db = b.load_games().unwrap();
+        db.games[0].save_dirs = vec!["~/.local/share/foo".into()];
+        b.put_games(&db).unwrap();
+        let r = sync_with(&remote, &b, &sb).unwrap();
+        assert_eq!((r.pushed, r.conflicts.len()), (0, 0));
+        sync_with(&remote, &a, &sa).unwrap();
+        let ga = a.load_games().unwrap().games.remove(0);
+        assert_eq!(ga.save_dirs, vec!["%APPDATA%\\Foo".to_string()]);
+        assert_eq!(ga.runner_id.as_deref(), Some("le"));
+
+        // a machine that lost its library gets its own settings back
+        let fresh_b = DirLibrary { dir: temp_dir("local-b2") };
+        sync_with(&remote, &fresh_b, &sb).unwrap();
+        assert_eq!(fresh_b.load_games().unwrap().games[0].save_dirs, vec!["~/.local/share/foo".to_string()]);
+        for dir in [&remote.dir, &a.dir, &b.dir, &fresh_b.dir] {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn sync_requires_machine_id() {
+        let remote = FolderBackend { dir: temp_dir("noid") };
+        let a = DirLibrary { dir: temp_dir("noid-a") };
+        assert!(sync_with(&remote, &a, &settings("", &[])).is_err());
+        let _ = fs::remove_dir_all(&remote.dir);
+        let _ = fs::remove_dir_all(&a.dir);
+    }
+
+    #[test]
+    fn folder_backend_rejects_stale_versions() {
+        let dir = temp_dir("folder");
+        let backend = FolderBackend { dir: dir.clone() };
+        assert!(backend.read().unwrap().is_none());
+
+        backend.write(b"{\"v\":1}", None).unwrap();
+        let (data, v1) = backend.read().unwrap().unwrap();
+        assert_eq!(data, b"{\"v\":1}");
+        // someone else created it in the meantime
+        assert_eq!(backend.write(b"{}", None).unwrap_err().code(), "busy");
+
+        backend.write(b"{\"v\":22}", Some(&v1)).unwrap();
+        assert_eq!(backend.write(b"{\"v\":3}", Some(&v1)).unwrap_err().code(), "busy");
+        assert_eq!(backend.read().unwrap().unwrap().0, b"{\"v\":22}");
+        assert!(!dir.join("kano_sync.lock").exists());
+
+        let missing = FolderBackend { dir: Path::new(&dir).join("missing") };
+        assert_eq!(missing.read().unwrap_err(), KanoError::not_found("folder"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn record(machine: &str, data: Option<Value>) -> SyncRecord {
+        SyncRecord {
+            modified: String::new(),
+            machine: machine.into(),
+            deleted: data.is_none(),
+            data,
+            paths: BTreeMap::new(),
+            local: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn merge_three_way() {
+        use serde_json::json;
+        let v = |n: i64| json!({ "n": n });
+        // (local, remote, base) -> (local after, remote data after, pushed, pulled, conflict)
+        type Side = Option<Value>;
+        type Case = (&'static str, Side, Side, Side, Side, Side, usize, usize, bool);
+        let cases: Vec<Case> = vec![
+            ("unchanged", Some(v(1)), Some(v(1)), Some(v(1)), Some(v(1)), Some(v(1)), 0, 0, false),
+            ("local edit", Some(v(2)), Some(v(1)), Some(v(1)), Some(v(2)), Some(v(2)), 1, 0, false),
+            ("remote edit", Some(v(1)), Some(v(2)), Some(v(1)), Some(v(2)), Some(v(2)), 0, 1, false),
+            ("same edit", Some(v(2)), Some(v(2)), Some(v(1)), Some(v(2)), Some(v(2)), 0, 0, false),
+            ("both edit", Some(v(2)), Some(v(3)), Some(v(1)), Some(v(2)), Some(v(2)), 1, 0, true),
+            ("local new", Some(v(1)), None, None, Some(v(1)), Some(v(1)), 1, 0, false),
+            ("remote new", None, Some(v(1)), None, Some(v(1)), Some(v(1)), 0, 1, false),
+            ("local delete", None, Some(v(1)), Some(v(1)), None, None, 1, 0, false),
+            ("remote delete", Some(v(1)), None, Some(v(1)), None, None, 0, 1, false),
+            ("edit beats remote delete", Some(v(2)), None, Some(v(1)), Some(v(2)), Some(v(2)), 1, 0, false),
+            ("remote edit beats delete", None, Some(v(2)), Some(v(1)), Some(v(2)), Some(v(2)), 0, 1, false),
+        ];
+        for (name, l, r, b, want_l, want_r, pushed, pulled, conflict) in cases {
+            let mut local: BTreeMap<String, Value> = l.into_iter().map(|v| ("x".to_string(), v)).collect();
+            let mut remote = BTreeMap::new();
+            if r.is_some() || b.is_some() {
+                remote.insert("x".to_string(), record("b", r));
+            }
+            let mut base: BTreeMap<String, String> = b.iter().map(|v| ("x".to_string(), fingerprint(v))).collect();
+            let mut report = SyncReport::default();
+            let mut ctx = MergeCtx { machine: "a", now: "now", report: &mut report };
+            ctx.merge("t", &mut local, &mut remote, &mut base, fingerprint);
+
+            assert_eq!(local.get("x"), want_l.as_ref(), "{}: local", name);
+            let remote_data = remote.get("x").filter(|r| !r.deleted).and_then(|r| r.data.clone());
+            assert_eq!(remote_data, want_r, "{}: remote", name);
+            assert_eq!((report.pushed, report.pulled), (pushed, pulled), "{}: counts", name);
+            assert_eq!(!report.conflicts.is_empty(), conflict, "{}: conflict", name);
+            assert_eq!(base.get("x").cloned(), want_l.as_ref().map(fingerprint), "{}: base", name);
+            if pushed > 0 {
+                assert_eq!(remote["x"].machine, "a", "{}: pushed by", name);
+            }
+        }
+    }
+
+    #[test]
+    fn map_path_prefers_own_then_mapping() {
+        let mut paths = BTreeMap::new();
+        paths.insert("a".to_string(), "D:\\Games\\Foo\\foo.exe".to_string());
+        let mappings = vec![PathMapping { from: "d:/games".into(), to: "/mnt/games".into() }];
+        assert_eq!(map_path(&paths, "b", &mappings), "/mnt/games/Foo/foo.exe");
+        assert_eq!(map_path(&paths, "b", &[]), "D:\\Games\\Foo\\foo.exe");
+        paths.insert("b".to_string(), "/own/foo.exe".to_string());
+        assert_eq!(map_path(&paths, "b", &mappings), "/own/foo.exe");
+    }
+}
@@ -1,34 +1,37 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use crate::exe_ranking::ExeRanker;
+use crate::models::ExeCandidate;
 
-/// Recursively find exe files with their folder paths
-pub fn find_exe_with_folder_path(start_folder: &PathBuf) -> Option<(String, Vec<String>)> {
+/// List every exe under a game folder, best candidates first
+pub fn list_exe_candidates(start_folder: &PathBuf, ranker: &ExeRanker) -> Vec<ExeCandidate> {
     let mut all_exes = Vec::new();
-    
-    // Collect all exe files recursively with their paths
     collect_exe_files_with_path(start_folder, start_folder, &mut all_exes);
-    
-    if all_exes.is_empty() {
-        return None;
-    }
-    
-    // Prioritize exe files with Chinese keywords
-    let chinese_keywords = ["ch", "chs", "cn", "中文", "chinese", "简体", "繁体", "汉化"];
-    
-    for (exe_path, folder_names) in &all_exes {
-        let file_name = exe_path.to_lowercase();
-        for keyword in &chinese_keywords {
-            if file_name.contains(keyword) {
-                return Some((exe_path.clone(), folder_names.clone()));
-            }
-        }
-    }
-    
-    // If no Chinese keyword found, return the first exe
-    all_exes.into_iter().next()
+
+    let game_folder_name = start_folder
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut candidates: Vec<ExeCandidate> = all_exes
+        .into_iter()
+        .map(|(exe_path, folder_path)| {
+            let meta = fs::metadata(&exe_path).ok();
+            let size = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+            let modified = meta
+                .and_then(|m| m.modified().ok())
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+            let score = ranker.score(Path::new(&exe_path), &folder_path, size, &game_folder_name);
+            ExeCandidate { path: exe_path, folder_path, size, modified, score }
+        })
+        .collect();
+
+    // stable sort keeps directory order for equal scores
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+    candidates
 }
 
-fn collect_exe_files_with_path(
+pub fn collect_exe_files_with_path(
     current: &PathBuf,
     start_folder: &PathBuf,
     result: &mut Vec<(String, Vec<String>)>
@@ -0,0 +1,374 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::KanoError;
+use crate::metadata::{
+    http_client, CoverUrls, ExternalIds, GameMetadata, LocalizedTitle, MetadataFuture, MetadataProvider, MetadataSource,
+    StaffCredit,
+};
+
+// Client for the VNDB Kana API (https://api.vndb.org/kana). Every query is a POST to
+// `<base>/<type>` with filters and a comma separated field list; no token is needed for
+// public data. The base URL comes from AppConfig.metadata so a mock server can stand in.
+
+pub const DEFAULT_API_URL: &str = "https://api.vndb.org/kana";
+
+const VN_FIELDS: &str = "id,title,alttitle,titles.lang,titles.title,titles.latin,titles.official,titles.main,\
+aliases,olang,released,languages,platforms,length_minutes,description,\
+image.url,image.thumbnail,image.sexual,image.violence,rating,votecount,\
+developers.id,developers.name,tags.name,tags.rating,tags.spoiler,staff.name,staff.original,staff.role";
+
+const RELEASE_FIELDS: &str = "id,title,alttitle,released,languages.lang,platforms,minage,official,patch,freeware,\
+producers.id,producers.name,producers.developer,producers.publisher";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbVn {
+    pub id: String, // "v17"
+    pub title: String,
+    pub alttitle: Option<String>, // 原语言标题
+    pub titles: Vec<VndbTitle>,
+    pub aliases: Vec<String>,
+    pub olang: Option<String>,
+    pub released: Option<String>, // "2004-01-30"，也可能只有年/月或 "TBA"
+    pub languages: Vec<String>,
+    pub platforms: Vec<String>,
+    pub length_minutes: Option<i64>,
+    pub description: Option<String>, // 含 VNDB 的格式标记
+    pub image: Option<VndbImage>,
+    pub rating: Option<f64>, // 10-100
+    pub votecount: i64,
+    pub developers: Vec<VndbProducer>,
+    pub tags: Vec<VndbTag>,
+    pub staff: Vec<VndbStaff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbTitle {
+    pub lang: String,
+    pub title: String,
+    pub latin: Option<String>,
+    pub official: bool,
+    pub main: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbImage {
+    pub url: String,
+    pub thumbnail: Option<String>,
+    pub sexual: f64,   // 0-2
+    pub violence: f64, // 0-2
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbProducer {
+    pub id: String,
+    pub name: String,
+    pub developer: bool,
+    pub publisher: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbStaff {
+    pub name: String,             // 罗马字
+    pub original: Option<String>, // 原文名
+    pub role: String,             // "scenario"、"art" ...
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbTag {
+    pub name: String,
+    pub rating: f64,
+    pub spoiler: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbRelease {
+    pub id: String, // "r123"
+    pub title: String,
+    pub alttitle: Option<String>,
+    pub released: Option<String>,
+    pub languages: Vec<VndbReleaseLanguage>,
+    pub platforms: Vec<String>,
+    pub minage: Option<i64>,
+    pub official: bool,
+    pub patch: bool,
+    pub freeware: bool,
+    pub producers: Vec<VndbProducer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VndbReleaseLanguage {
+    pub lang: String,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse<T> {
+    results: Vec<T>,
+}
+
+pub struct VndbClient {
+    base_url: String,
+}
+
+impl VndbClient {
+    pub fn new(base_url: &str) -> Self {
+        let base_url = match base_url.trim().trim_end_matches('/') {
+            "" => DEFAULT_API_URL.to_string(),
+            url => url.to_string(),
+        };
+        VndbClient { base_url }
+    }
+
+    async fn query<T: DeserializeOwned>(&self, endpoint: &str, body: Value) -> Result<Vec<T>, KanoError> {
+        let resp = http_client()
+            .post(format!("{}/{}", self.base_url, endpoint))
+            .json(&body)
+            .send()
+            .await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        // errors (bad filter, 429 throttling ...) come back as plain text
+        if !status.is_success() {
+            return Err(KanoError::Http { status: status.as_u16(), message: text.trim().to_string() });
+        }
+        let parsed: QueryResponse<T> = serde_json::from_str(&text)?;
+        Ok(parsed.results)
+    }
+
+    /// Visual novels matching `query`, best match first
+    pub async fn search(&self, query: &str, limit: u32) -> Result<Vec<VndbVn>, KanoError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        // a pasted id ("v17") or link is looked up directly; bare numbers may be titles
+        let looks_like_id = query.starts_with(['v', 'V']) || query.contains("vndb.org");
+        if let Some(id) = normalize_id(query).filter(|_| looks_like_id) {
+            return self.get(&id).await.map(|vn| vec![vn]).or_else(|e| match e {
+                KanoError::NotFound { .. } => Ok(Vec::new()),
+                e => Err(e),
+            });
+        }
+        self.query(
+            "vn",
+            json!({
+                "filters": ["search", "=", query],
+                "fields": VN_FIELDS,
+                "sort": "searchrank",
+                "results": limit.clamp(1, 100),
+            }),
+        )
+        .await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<VndbVn, KanoError> {
+        let id = normalize_id(id).ok_or_else(|| KanoError::invalid("vndb_id", format!("invalid VNDB id: {}", id)))?;
+        let mut found: Vec<VndbVn> = self
+            .query("vn", json!({ "filters": ["id", "=", id], "fields": VN_FIELDS }))
+            .await?;
+        found.pop().ok_or(KanoError::not_found("vndb_entry"))
+    }
+
+    /// Releases of a visual novel, oldest first
+    pub async fn releases(&self, vn_id: &str) -> Result<Vec<VndbRelease>, KanoError> {
+        let id = normalize_id(vn_id).ok_or_else(|| KanoError::invalid("vndb_id", format!("invalid VNDB id: {}", vn_id)))?;
+        self.query(
+            "release",
+            json!({
+                "filters": ["vn", "=", ["id", "=", id]],
+                "fields": RELEASE_FIELDS,
+                "sort": "released",
+                "results": 100,
+            }),
+        )
+        .await
+    }
+}
+
+impl MetadataProvider for VndbClient {
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> MetadataFuture<'a, Vec<GameMetadata>> {
+        Box::pin(async move { Ok(VndbClient::search(self, query, limit).await?.into_iter().map(to_metadata).collect()) })
+    }
+
+    fn fetch_details<'a>(&'a self, id: &'a str) -> MetadataFuture<'a, GameMetadata> {
+        Box::pin(async move { Ok(to_metadata(self.get(id).await?)) })
+    }
+}
+
+// Tags rated below this are often wrong; spoiler tags are left out entirely
+const MIN_TAG_RATING: f64 = 2.0;
+const MAX_TAGS: usize = 15;
+
+// VNDB role codes, named the way Bangumi's infobox names them
+fn staff_role(role: &str) -> &str {
+    match role {
+        "director" => "导演",
+        "scenario" => "剧本",
+        "chardesign" => "人物设定",
+        "art" => "原画",
+        "music" => "音乐",
+        "songs" => "主题歌",
+        "translator" => "翻译",
+        other => other,
+    }
+}
+
+fn to_metadata(vn: VndbVn) -> GameMetadata {
+    let mut tags: Vec<&VndbTag> = vn.tags.iter().filter(|t| t.spoiler == 0 && t.rating >= MIN_TAG_RATING).collect();
+    tags.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    let titles = vn
+        .titles
+        .iter()
+        .filter(|t| !t.title.is_empty())
+        .map(|t| LocalizedTitle { lang: t.lang.clone(), title: t.title.clone() })
+        .collect();
+    // the main title is in the original language; `title` is its romanization
+    let original = vn.titles.iter().find(|t| t.main).map(|t| t.title.clone());
+    GameMetadata {
+        source: MetadataSource::Vndb,
+        url: format!("https://vndb.org/{}", vn.id),
+        title: original.or_else(|| vn.alttitle.clone()).unwrap_or_else(|| vn.title.clone()),
+        titles,
+        aliases: vn.aliases.clone(),
+        developers: vn.developers.iter().map(|d| d.name.clone()).collect(),
+        publishers: Vec::new(), // 发行商只在 release 上
+        release_date: vn.released.clone().filter(|d| d != "TBA"),
+        platforms: vn.platforms.clone(),
+        genre: None,
+        summary: vn.description.clone().filter(|d| !d.trim().is_empty()),
+        staff: vn
+            .staff
+            .iter()
+            .map(|s| StaffCredit {
+                role: staff_role(&s.role).to_string(),
+                name: s.original.clone().unwrap_or_else(|| s.name.clone()),
+            })
+            .collect(),
+        cover: vn.image.as_ref().map(|i| CoverUrls { large: i.url.clone(), medium: None, small: i.thumbnail.clone() }),
+        tags: tags.iter().take(MAX_TAGS).map(|t| t.name.clone()).collect(),
+        rating: vn.rating.map(|r| r / 10.0),
+        external_ids: ExternalIds { bangumi: None, vndb: Some(vn.id.clone()) },
+        id: vn.id,
+    }
+}
+
+/// Accepts "v17", "17" or a vndb.org link and returns "v17"
+pub fn normalize_id(s: &str) -> Option<String> {
+    let s = s.trim().trim_end_matches('/');
+    let s = s.rsplit('/').next().unwrap_or(s);
+    let digits = s.strip_prefix(['v', 'V']).unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    (n > 0).then(|| format!("v{}", n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_id_accepts_ids_numbers_and_links() {
+        let cases = [
+            ("v17", Some("v17")),
+            ("V17", Some("v17")),
+            (" 17 ", Some("v17")),
+            ("v017", Some("v17")),
+            ("https://vndb.org/v17", Some("v17")),
+            ("https://vndb.org/v17/", Some("v17")),
+            ("vndb.org/v2002", Some("v2002")),
+            ("", None),
+            ("v", None),
+            ("v0", None),
+            ("r17", None),
+            ("v17a", None),
+            ("https://vndb.org/r17", None),
+            ("v99999999999999999999999", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_id(input).as_deref(), expected, "{:?}", input);
+        }
+    }
+
+    // shaped like a `POST /vn` response with VN_FIELDS
+    const VN_JSON: &str = r#"{
+        "id": "v17",
+        "title": "Ever17 -the out of infinity-",
+        "alttitle": "Ever17 -the out of infinity-",
+        "titles": [
+            { "lang": "ja", "title": "Ever17 -the out of infinity-", "latin": null, "official": true, "main": true },
+            { "lang": "zh-Hans", "title": "时空轮回", "latin": "Shikong Lunhui", "official": false, "main": false },
+            { "lang": "en", "title": "", "latin": null, "official": true, "main": false }
+        ],
+        "aliases": ["E17"],
+        "olang": "ja",
+        "released": "2002-08-29",
+        "languages": ["ja", "en", "zh-Hans"],
+        "platforms": ["win", "ps2"],
+        "length_minutes": 2400,
+        "description": "  ",
+        "image": { "url": "https://t.vndb.org/cv/39/1039.jpg", "thumbnail": "https://t.vndb.org/cv.t/39/1039.jpg", "sexual": 0, "violence": 0 },
+        "rating": 86.5,
+        "votecount": 9000,
+        "developers": [{ "id": "p24", "name": "KID" }],
+        "tags": [
+            { "name": "Amnesia", "rating": 2.5, "spoiler": 0 },
+            { "name": "Time Travel", "rating": 2.9, "spoiler": 0 },
+            { "name": "Twist Ending", "rating": 3.0, "spoiler": 2 },
+            { "name": "Weak Tag", "rating": 1.2, "spoiler": 0 }
+        ],
+        "staff": [
+            { "name": "Uchikoshi Kotaro", "original": "打越鋼太郎", "role": "scenario" },
+            { "name": "Someone", "original": null, "role": "staff" }
+        ]
+    }"#;
+
+    #[test]
+    fn converts_a_vn_to_metadata() {
+        let vn: VndbVn = serde_json::from_str(VN_JSON).unwrap();
+        let m = to_metadata(vn);
+        assert_eq!(m.source, MetadataSource::Vndb);
+        assert_eq!(m.id, "v17");
+        assert_eq!(m.url, "https://vndb.org/v17");
+        assert_eq!(m.title, "Ever17 -the out of infinity-");
+        // empty titles are dropped
+        let titles: Vec<(&str, &str)> = m.titles.iter().map(|t| (t.lang.as_str(), t.title.as_str())).collect();
+        assert_eq!(titles, vec![("ja", "Ever17 -the out of infinity-"), ("zh-Hans", "时空轮回")]);
+        assert_eq!(m.aliases, vec!["E17"]);
+        assert_eq!(m.developers, vec!["KID"]);
+        assert_eq!(m.release_date.as_deref(), Some("2002-08-29"));
+        assert_eq!(m.platforms, vec!["win", "ps2"]);
+        assert_eq!(m.summary, None);
+        let staff: Vec<(&str, &str)> = m.staff.iter().map(|s| (s.role.as_str(), s.name.as_str())).collect();
+        assert_eq!(staff, vec![("剧本", "打越鋼太郎"), ("staff", "Someone")]);
+        let cover = m.cover.unwrap();
+        assert_eq!(cover.large, "https://t.vndb.org/cv/39/1039.jpg");
+        assert_eq!(cover.small.as_deref(), Some("https://t.vndb.org/cv.t/39/1039.jpg"));
+        // spoilers and weak tags left out, best rated first
+        assert_eq!(m.tags, vec!["Time Travel", "Amnesia"]);
+        assert_eq!(m.rating, Some(8.65));
+        assert_eq!(m.external_ids.vndb.as_deref(), Some("v17"));
+        assert_eq!(m.external_ids.bangumi, None);
+    }
+
+    #[test]
+    fn conversion_falls_back_for_sparse_entries() {
+        let vn: VndbVn = serde_json::from_str(r#"{ "id": "v5", "title": "Romaji", "alttitle": "原題", "released": "TBA" }"#).unwrap();
+        let m = to_metadata(vn);
+        assert_eq!(m.title, "原題");
+        assert_eq!(m.release_date, None);
+        assert!(m.cover.is_none() && m.rating.is_none() && m.tags.is_empty());
+
+        let vn: VndbVn = serde_json::from_str(r#"{ "id": "v6", "title": "Only Romaji" }"#).unwrap();
+        assert_eq!(to_metadata(vn).title, "Only Romaji");
+    }
+}
@@ -5,10 +5,12 @@ export function useGameLibrary() {
   const games = ref([]);
   const isLoadingGames = ref(false);
   const projectRoot = ref('');
+  const dataDir = ref('');
 
   async function loadProjectRoot() {
     try {
       projectRoot.value = await gameService.getProjectRoot();
+      dataDir.value = (await gameService.getDataDir()).path;
     } catch (e) {
       console.error('getProjectRoot failed', e);
     }
@@ -70,6 +72,7 @@ export function useGameLibrary() {
     isLoadingGames,
   // （进度计数已迁移到 useImageFetch）
     projectRoot,
+    dataDir,
     loadProjectRoot,
     listGames,
     addGame,
@@ -1,22 +1,19 @@
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
 
 // 轻量的 Tauri invoke 调用封装。将前端与直接调用 invoke 的细节隔离开。
-export async function loadCache() {
-  return await invoke('load_cache');
-}
-
 export async function greet(name) {
   return await invoke('greet', { name });
 }
 
-export async function saveCache(cache) {
-  return await invoke('save_cache', { cache });
-}
-
 export async function getProjectRoot() {
   return await invoke('get_project_root');
 }
 
+// kano_data 所在目录（可能在 exe 旁、系统 app-data 目录或用户指定位置）
+export async function getDataDir() {
+  return await invoke('get_data_dir');
+}
+
 export async function listGames() {
   return await invoke('list_games');
 }
@@ -33,16 +30,71 @@ export async function updateGameInfo({ path, name, image, imageUrl, subjectId })
   return await invoke('update_game_info', { path, name, image, imageUrl, subjectId });
 }
 
-export async function downloadImage({ url, subjectId }) {
-  return await invoke('download_image', { url, subjectId });
+// 游戏信息来源（source: 'bangumi' | 'vndb'），结果统一为 GameMetadata：
+// { source, id, url, title, titles: [{ lang, title }], aliases, developers, publishers,
+//   release_date, platforms, genre, summary, cover: { large, medium, small }, tags, rating, external_ids }
+// forceRefresh：不使用本地缓存的结果
+export async function searchMetadata({ source = 'bangumi', query, limit, forceRefresh = false }) {
+  return await invoke('search_metadata', { source, query, limit, forceRefresh });
+}
+
+// Bangumi 为条目数字 id；VNDB 可以是 "v17"、"17" 或 vndb.org 链接
+export async function getMetadata({ source = 'bangumi', id, forceRefresh = false }) {
+  return await invoke('get_metadata', { source, id: String(id), forceRefresh });
+}
+
+// scope: { kind: 'all' | 'subjects' | 'searches' | 'expired' } 或 { kind: 'subject', ids: [...] }
+export async function clearBangumiCache({ scope = null } = {}) {
+  return await invoke('clear_bangumi_cache', { scope });
+}
+
+export async function getBangumiCacheStats() {
+  return await invoke('get_bangumi_cache_stats');
+}
+
+// 下载封面到 kano_data/images/{id}.{ext}，返回相对路径
+export async function downloadMetadataCover(metadata) {
+  return await invoke('download_metadata_cover', { metadata });
+}
+
+export async function getVndbReleases(id) {
+  return await invoke('get_vndb_releases', { id });
+}
+
+// 按条件筛选游戏库：{ keyword, tags, metadataTags, developer, staff, platform, releasedFrom, releasedTo }
+export async function queryGames({ keyword = null, tags = [], metadataTags = [], developer = null, staff = null, platform = null, releasedFrom = null, releasedTo = null } = {}) {
+  return await invoke('query_games', {
+    query: { keyword, tags, metadata_tags: metadataTags, developer, staff, platform, released_from: releasedFrom, released_to: releasedTo },
+  });
+}
+
+// 保存条目信息到游戏上（同时关联条目 id）；metadata 为 null 时清除
+export async function setGameMetadata({ gameId, metadata }) {
+  return await invoke('set_game_metadata', { gameId, metadata });
+}
+
+// 重新获取已关联条目的信息并保存
+export async function refreshGameMetadata({ gameId, source = null }) {
+  return await invoke('refresh_game_metadata', { gameId, source });
+}
+
+// 批量自动匹配；进度通过 auto-match-progress 事件推送
+// 已关联的游戏会跳过，rematch 为 true 时重新匹配 gameIds 中的游戏
+export async function autoMatchGames({ gameIds = null, source = null, threshold = null, rematch = false } = {}) {
+  return await invoke('auto_match_games', { gameIds, source, threshold, rematch });
+}
+
+export async function listMatchReviews() {
+  return await invoke('list_match_reviews');
 }
 
-export async function getBangumiSubject(id) {
-  return await invoke('get_bangumi_subject', { id });
+// metadata 为 null 时只移出待确认列表
+export async function resolveMatchReview({ gameId, metadata = null }) {
+  return await invoke('resolve_match_review', { gameId, metadata });
 }
 
-export async function searchBangumi({ query, filter }) {
-  return await invoke('search_bangumi', { query, filter });
+export async function setGameVndbId({ gameId, vndbId }) {
+  return await invoke('set_game_vndb_id', { gameId, vndbId });
 }
 
 export async function pickExe({ initialDir = null } = {}) {
@@ -69,8 +121,9 @@ export async function updateGamePlaytime({ path, additionalSeconds, lastPlayed }
   return await invoke('update_game_playtime', { path, additionalSeconds, lastPlayed });
 }
 
-export async function deleteCachedImage({ subjectId }) {
-  return await invoke('delete_cached_image', { subjectId });
+// 按元数据来源/id 删除缓存封面：{ source: 'bangumi' | 'vndb', id }
+export async function deleteCachedImage({ source, id }) {
+  return await invoke('delete_cached_image', { source, id });
 }
 
 export async function listNotes() {
@@ -96,17 +149,26 @@ export async function deleteNoteByIndex(index) {
 }
 
 export default {
-  loadCache,
   greet,
-  saveCache,
   getProjectRoot,
+  getDataDir,
   listGames,
   addGame,
   removeGame,
   updateGameInfo,
-  downloadImage,
-  getBangumiSubject,
-  searchBangumi,
+  searchMetadata,
+  getMetadata,
+  clearBangumiCache,
+  getBangumiCacheStats,
+  downloadMetadataCover,
+  getVndbReleases,
+  queryGames,
+  setGameMetadata,
+  refreshGameMetadata,
+  autoMatchGames,
+  listMatchReviews,
+  resolveMatchReview,
+  setGameVndbId,
   pickExe,
   pickFolderAndScan,
   launchExe,
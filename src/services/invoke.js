@@ -0,0 +1,27 @@
+import { invoke as tauriInvoke } from '@tauri-apps/api/core';
+
+// 后端命令失败时返回 { code, key, message, params }，这里包装成 Error，
+// 方便用 e.code 判断（如 'cancelled'），用 key/params 做翻译，'失败: ' + e 仍显示 message
+export class KanoError extends Error {
+  constructor({ code, key, message, params } = {}) {
+    super(message || code || 'unknown error');
+    this.name = 'KanoError';
+    this.code = code || 'other';
+    this.key = key || `errors.${this.code}`;
+    this.params = params || {};
+  }
+
+  toString() {
+    return this.message;
+  }
+}
+
+export async function invoke(cmd, args) {
+  try {
+    return await tauriInvoke(cmd, args);
+  } catch (e) {
+    if (e && typeof e === 'object' && 'code' in e) throw new KanoError(e);
+    // 插件等非本应用命令的错误仍是字符串
+    throw new KanoError({ code: 'other', message: String(e) });
+  }
+}
@@ -0,0 +1,30 @@
+// GameMetadata（见 gameService.searchMetadata）的显示辅助
+
+// 优先中文标题，其次原名
+export function displayTitle(meta) {
+  if (!meta) return '';
+  const zh = (meta.titles || []).find(t => t.lang === 'zh-Hans' || t.lang === 'zh-Hant');
+  return (zh && zh.title) || meta.title || '';
+}
+
+export function coverUrl(meta) {
+  const c = meta && meta.cover;
+  return (c && (c.large || c.medium || c.small)) || null;
+}
+
+// 用于搜索：所有标题和别名
+export function allTitles(meta) {
+  if (!meta) return [];
+  return [meta.title, ...(meta.titles || []).map(t => t.title), ...(meta.aliases || [])].filter(Boolean);
+}
+
+// [{ role, names }]，按条目中出现的顺序
+export function staffByRole(meta) {
+  const groups = [];
+  for (const s of (meta && meta.staff) || []) {
+    const g = groups.find(x => x.role === s.role);
+    if (g) g.names.push(s.name);
+    else groups.push({ role: s.role, names: [s.name] });
+  }
+  return groups;
+}